@@ -0,0 +1,85 @@
+//! Time queries against registries of growing size. Queries look fonts up in
+//! the name index, so the time per query should stay about the same from 100
+//! to 2000 fonts, where a linear scan would grow 20 times.
+//!
+//! Run with `cargo bench --bench query_scaling`
+
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use fontkit::{Error, FontKey, FontKit, LoaderSource};
+
+/// Rename Open Sans Italic to `Fnt {index:05}`, keeping the length of every
+/// name record
+fn open_sans_renamed(original: &[u8], index: usize) -> Vec<u8> {
+    let mut buf = original.to_vec();
+    let num_tables = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+    let record = (0..num_tables)
+        .map(|i| 12 + i * 16)
+        .find(|record| &buf[*record..*record + 4] == b"name")
+        .unwrap();
+    let field = |at: usize| {
+        u32::from_be_bytes(buf[record + at..record + at + 4].try_into().unwrap()) as usize
+    };
+    let (offset, length) = (field(8), field(12));
+    let names = &mut buf[offset..offset + length];
+    let replacements = [
+        ("Open Sans", format!("Fnt {:05}", index)),
+        ("OpenSans", format!("Fnt{:05}", index)),
+    ];
+    for (from, to) in replacements {
+        let utf16 = |s: &str| s.encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+        for (from, to) in [
+            (from.as_bytes().to_vec(), to.as_bytes().to_vec()),
+            (utf16(from), utf16(&to)),
+        ] {
+            let mut i = 0;
+            while i + from.len() <= names.len() {
+                if names[i..i + from.len()] == from[..] {
+                    names[i..i + from.len()].copy_from_slice(&to);
+                    i += from.len();
+                } else {
+                    i += 1;
+                }
+            }
+        }
+    }
+    buf
+}
+
+/// Average time of querying the first ten fonts, which stay loaded after the
+/// first round
+fn time_queries(fontkit: &FontKit) -> Duration {
+    let keys = (0..10)
+        .map(|i| FontKey::new_with_family(format!("Fnt {:05}", i)))
+        .collect::<Vec<_>>();
+    for key in &keys {
+        assert!(fontkit.query(key).is_some());
+    }
+    let rounds = 100;
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for key in &keys {
+            assert!(fontkit.query(key).is_some());
+        }
+    }
+    start.elapsed() / (rounds * keys.len() as u32)
+}
+
+fn main() -> Result<(), Error> {
+    let original = Arc::new(fs::read("examples/OpenSans-Italic.ttf")?);
+    let fontkit = FontKit::new();
+    let mut registered = 0;
+    for count in [100, 500, 2000] {
+        for index in registered..count {
+            let original = original.clone();
+            fontkit.add_font_from_source(LoaderSource::new(move || {
+                Ok(open_sans_renamed(&original, index))
+            }))?;
+        }
+        registered = count;
+        println!("{:>5} fonts: {:?} per query", count, time_queries(&fontkit));
+    }
+    Ok(())
+}
@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::language::language_matches;
+use crate::{normalize_name, Error, FontKey, FontKit};
+
+/// CSS generic font families, ref: https://www.w3.org/TR/css-fonts-4/#generic-font-families
+pub const GENERIC_FAMILIES: &[&str] = &[
+    "serif",
+    "sans-serif",
+    "monospace",
+    "cursive",
+    "fantasy",
+    "system-ui",
+    "emoji",
+    "math",
+    "fangsong",
+];
+
+/// Families commonly installed on Windows, macOS and Linux for the generic
+/// families, by BCP-47 language. `None` applies to every other language
+const GENERIC_DEFAULTS: &[(&str, Option<&str>, &[&str])] = &[
+    (
+        "serif",
+        None,
+        &[
+            "Times New Roman",
+            "Times",
+            "Noto Serif",
+            "DejaVu Serif",
+            "Liberation Serif",
+        ],
+    ),
+    (
+        "serif",
+        Some("zh"),
+        &[
+            "Songti SC",
+            "SimSun",
+            "Noto Serif CJK SC",
+            "Source Han Serif SC",
+        ],
+    ),
+    (
+        "serif",
+        Some("zh-TW"),
+        &[
+            "Songti TC",
+            "PMingLiU",
+            "Noto Serif CJK TC",
+            "Source Han Serif TC",
+        ],
+    ),
+    (
+        "serif",
+        Some("ja"),
+        &[
+            "Hiragino Mincho ProN",
+            "Yu Mincho",
+            "MS Mincho",
+            "Noto Serif CJK JP",
+        ],
+    ),
+    (
+        "serif",
+        Some("ko"),
+        &["AppleMyungjo", "Batang", "Noto Serif CJK KR"],
+    ),
+    (
+        "sans-serif",
+        None,
+        &[
+            "Arial",
+            "Helvetica",
+            "Noto Sans",
+            "DejaVu Sans",
+            "Liberation Sans",
+        ],
+    ),
+    (
+        "sans-serif",
+        Some("zh"),
+        &[
+            "PingFang SC",
+            "Microsoft YaHei",
+            "Noto Sans CJK SC",
+            "Source Han Sans SC",
+        ],
+    ),
+    (
+        "sans-serif",
+        Some("zh-TW"),
+        &[
+            "PingFang TC",
+            "Microsoft JhengHei",
+            "Noto Sans CJK TC",
+            "Source Han Sans TC",
+        ],
+    ),
+    (
+        "sans-serif",
+        Some("ja"),
+        &["Hiragino Sans", "Yu Gothic", "Meiryo", "Noto Sans CJK JP"],
+    ),
+    (
+        "sans-serif",
+        Some("ko"),
+        &["Apple SD Gothic Neo", "Malgun Gothic", "Noto Sans CJK KR"],
+    ),
+    (
+        "monospace",
+        None,
+        &[
+            "Menlo",
+            "Consolas",
+            "Courier New",
+            "DejaVu Sans Mono",
+            "Noto Sans Mono",
+        ],
+    ),
+    (
+        "cursive",
+        None,
+        &["Apple Chancery", "Comic Sans MS", "URW Chancery L"],
+    ),
+    ("fantasy", None, &["Papyrus", "Impact"]),
+    ("system-ui", None, &["sans-serif"]),
+    (
+        "emoji",
+        None,
+        &["Apple Color Emoji", "Segoe UI Emoji", "Noto Color Emoji"],
+    ),
+    (
+        "math",
+        None,
+        &["STIX Two Math", "Cambria Math", "Noto Sans Math"],
+    ),
+    (
+        "fangsong",
+        None,
+        &["STFangsong", "FangSong_GB2312", "Noto Serif CJK SC"],
+    ),
+];
+
+/// Families a family is aliased to, for a language or for every language
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct AliasRule {
+    language: Option<String>,
+    families: Vec<String>,
+}
+
+/// Maps family names, such as CSS generic families or names of proprietary
+/// fonts, to the families that should be tried instead. Rules may be
+/// specific to a language, so `sans-serif` could resolve to "PingFang SC"
+/// for `zh` and to "Arial" otherwise. Family names are compared after
+/// [`normalize_name`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AliasTable {
+    rules: BTreeMap<String, Vec<AliasRule>>,
+}
+
+impl AliasTable {
+    /// An empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A table mapping the generic families to fonts commonly installed on
+    /// desktop systems
+    pub fn generic() -> Self {
+        let mut table = Self::new();
+        for (family, language, families) in GENERIC_DEFAULTS {
+            table
+                .add(family, *language, families.iter().copied())
+                .expect("generic families have no cycles");
+        }
+        table
+    }
+
+    /// Alias `family` to `families`, tried in order. With a `language`, the
+    /// rule only applies to queries in that language or a more specific one.
+    /// A rule replaces the previous rule of the same family and language.
+    /// Rules that would let a family resolve to itself are rejected
+    pub fn add<S: Into<String>>(
+        &mut self,
+        family: &str,
+        language: Option<&str>,
+        families: impl IntoIterator<Item = S>,
+    ) -> Result<(), Error> {
+        let name = normalize_name(family);
+        let rule = AliasRule {
+            language: language.map(|l| l.to_string()),
+            families: families.into_iter().map(Into::into).collect(),
+        };
+        let previous = self.rules.get(&name).cloned();
+        let rules = self.rules.entry(name.clone()).or_default();
+        match rules.iter_mut().find(|r| r.language == rule.language) {
+            Some(existing) => *existing = rule,
+            None => rules.push(rule),
+        }
+        if self.reaches(&name, &name, &mut HashSet::new()) {
+            match previous {
+                Some(rules) => self.rules.insert(name, rules),
+                None => self.rules.remove(&name),
+            };
+            return Err(Error::AliasCycle(family.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Remove every rule of `family`
+    pub fn remove(&mut self, family: &str) {
+        self.rules.remove(&normalize_name(family));
+    }
+
+    /// Whether `target` can be reached from the aliases of `family`, in any
+    /// language
+    fn reaches(&self, family: &str, target: &str, visited: &mut HashSet<String>) -> bool {
+        let Some(rules) = self.rules.get(family) else {
+            return false;
+        };
+        for next in rules.iter().flat_map(|r| r.families.iter()) {
+            let next = normalize_name(next);
+            if next == target
+                || (visited.insert(next.clone()) && self.reaches(&next, target, visited))
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The rule of `family` for `language`: the rule of the most specific
+    /// language matching it, or the rule without language
+    fn rule(&self, family: &str, language: Option<&str>) -> Option<&AliasRule> {
+        let rules = self.rules.get(family)?;
+        let specific = language.and_then(|language| {
+            rules
+                .iter()
+                .filter(|r| {
+                    r.language
+                        .as_deref()
+                        .is_some_and(|l| language_matches(language, l))
+                })
+                .max_by_key(|r| r.language.as_ref().map(|l| l.len()))
+        });
+        specific.or_else(|| rules.iter().find(|r| r.language.is_none()))
+    }
+
+    /// Families a query for `family` tries, in order: the family itself, then
+    /// the families it is aliased to, each followed by its own aliases.
+    /// Families already visited are skipped, which also breaks cycles of
+    /// tables that were not built with [`AliasTable::add`]
+    pub fn resolve(&self, family: &str, language: Option<&str>) -> Vec<String> {
+        let mut visited = HashSet::new();
+        let mut result = vec![];
+        self.expand(family, language, &mut visited, &mut result);
+        result
+    }
+
+    fn expand(
+        &self,
+        family: &str,
+        language: Option<&str>,
+        visited: &mut HashSet<String>,
+        result: &mut Vec<String>,
+    ) {
+        let name = normalize_name(family);
+        if !visited.insert(name.clone()) {
+            return;
+        }
+        result.push(family.to_string());
+        if let Some(rule) = self.rule(&name, language) {
+            for next in rule.families.iter() {
+                self.expand(next, language, visited, result);
+            }
+        }
+    }
+}
+
+impl FontKit {
+    /// Replace the alias table used by queries. The table is empty by
+    /// default, so families are only matched by name. Install
+    /// [`AliasTable::generic`] to resolve generic families such as
+    /// `sans-serif` to commonly installed fonts
+    pub fn set_aliases(&self, aliases: AliasTable) {
+        self.aliases.store(Arc::new(aliases));
+    }
+
+    /// The alias table used by queries
+    pub fn aliases(&self) -> Arc<AliasTable> {
+        self.aliases.load_full()
+    }
+
+    /// Run `f` with `key` and then with its family replaced by each family it
+    /// is aliased to, until it returns a result
+    pub(crate) fn resolve_alias<T>(
+        &self,
+        key: &FontKey,
+        language: Option<&str>,
+        mut f: impl FnMut(&FontKey) -> Option<T>,
+    ) -> Option<T> {
+        self.aliases
+            .load()
+            .resolve(&key.family, language)
+            .into_iter()
+            .find_map(|family| {
+                f(&FontKey {
+                    family,
+                    ..key.clone()
+                })
+            })
+    }
+}
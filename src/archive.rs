@@ -0,0 +1,204 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+
+use crate::search::is_font_path;
+use crate::{Error, Font, FontKit, FontSource, SearchReport};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns whether the path has an archive extension that
+/// `search_fonts_from_path` looks into
+pub(crate) fn is_archive_path(path: &Path) -> bool {
+    ArchiveKind::from_path(path).is_some()
+}
+
+/// Call `visit` with the name and reader of every regular file in an
+/// archive, until it returns `true`
+fn visit_members(
+    archive: &Path,
+    visit: &mut dyn FnMut(&str, &mut dyn Read) -> Result<bool, Error>,
+) -> Result<(), Error> {
+    let kind = ArchiveKind::from_path(archive)
+        .ok_or_else(|| Error::UnsupportedArchive(archive.to_path_buf()))?;
+    let reader = BufReader::new(File::open(archive)?);
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = zip::ZipArchive::new(reader)?;
+            for i in 0..zip.len() {
+                let mut file = zip.by_index(i)?;
+                if !file.is_file() {
+                    continue;
+                }
+                let name = file.name().to_string();
+                if visit(&name, &mut file)? {
+                    break;
+                }
+            }
+            Ok(())
+        }
+        ArchiveKind::Tar => visit_tar(reader, visit),
+        ArchiveKind::TarGz => visit_tar(GzDecoder::new(reader), visit),
+    }
+}
+
+fn visit_tar(
+    reader: impl Read,
+    visit: &mut dyn FnMut(&str, &mut dyn Read) -> Result<bool, Error>,
+) -> Result<(), Error> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        if visit(&name, &mut entry)? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// The path reported for an archive member: the member name joined onto the
+/// archive path, without `.` components. Absolute names and names with `..`
+/// components are rejected, as they would resolve outside of the archive and
+/// could collide with the paths of fonts on disk
+fn member_path(archive: &Path, member: &str) -> Result<PathBuf, Error> {
+    let mut path = archive.to_path_buf();
+    for component in Path::new(member).components() {
+        match component {
+            Component::Normal(name) => path.push(name),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(Error::UnsafeArchiveMember {
+                    archive: archive.to_path_buf(),
+                    member: member.to_string(),
+                })
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// A font stored as a member of a zip, tar or tar.gz archive. The reported
+/// path is the member name joined onto the archive path, which it never
+/// leaves
+#[derive(Debug, Clone)]
+pub struct ArchiveSource {
+    archive: PathBuf,
+    member: String,
+    path: PathBuf,
+}
+
+impl ArchiveSource {
+    /// Fails for members with an absolute name or `..` components
+    pub fn new(archive: impl Into<PathBuf>, member: impl Into<String>) -> Result<Self, Error> {
+        let archive = archive.into();
+        let member = member.into();
+        let path = member_path(&archive, &member)?;
+        Ok(ArchiveSource {
+            archive,
+            member,
+            path,
+        })
+    }
+}
+
+impl FontSource for ArchiveSource {
+    fn read(&self) -> Result<Vec<u8>, Error> {
+        let mut buffer = None;
+        visit_members(&self.archive, &mut |name, reader| {
+            if name != self.member {
+                return Ok(false);
+            }
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data)?;
+            buffer = Some(data);
+            Ok(true)
+        })?;
+        buffer.ok_or_else(|| Error::ArchiveMemberNotFound {
+            archive: self.archive.clone(),
+            member: self.member.clone(),
+        })
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+}
+
+impl FontKit {
+    /// Index the fonts stored in a zip, tar or tar.gz archive. Fonts are
+    /// unloaded after indexing, and read from the archive member again when
+    /// queried
+    pub fn search_fonts_from_archive(&self, path: impl AsRef<Path>) -> Result<SearchReport, Error> {
+        let mut report = SearchReport::default();
+        self.load_archive(path.as_ref(), &mut report)?;
+        self.check_lru();
+        Ok(report)
+    }
+
+    pub(crate) fn load_archive(&self, path: &Path, report: &mut SearchReport) -> Result<(), Error> {
+        visit_members(path, &mut |name, reader| {
+            let source = match ArchiveSource::new(path, name) {
+                Ok(source) => source,
+                Err(e) => {
+                    log::warn!("Skipping member {:?} of {:?}: {:?}", name, path, e);
+                    report.failed.push((path.to_path_buf(), e));
+                    return Ok(false);
+                }
+            };
+            let member_path = source.path.clone();
+            if !is_font_path(Path::new(name)) {
+                report.skipped.push(member_path);
+                return Ok(false);
+            }
+            let mut buffer = Vec::new();
+            let strict = self.config.load().strict_validation;
+            let font = reader
+                .read_to_end(&mut buffer)
+                .map_err(Error::from)
+                .and_then(|_| Font::from_buffer(buffer, self.hit_counter.clone(), strict));
+            match font {
+                Ok(mut font) => {
+                    font.set_source(Arc::new(source));
+                    font.unload();
+                    if self.insert_font(font).is_empty() {
+                        report.shadowed.push(member_path);
+                    } else {
+                        report.loaded.push(member_path);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Failed loading font {:?}: {:?}", member_path, e);
+                    report.failed.push((member_path, e));
+                }
+            }
+            Ok(false)
+        })
+    }
+}
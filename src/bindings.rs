@@ -1,7 +1,7 @@
 // Generated by `wit-bindgen` 0.41.0. DO NOT EDIT!
 // Options used:
 //   * runtime_path: "wit_bindgen_rt"
-//   * additional derives ["Clone", "Hash", "PartialEq", "PartialOrd", "Eq", "Default"]
+//   * additional derives ["Clone", "Hash", "PartialEq", "PartialOrd", "Eq"]
 //   * with "alibaba:fontkit/commons" = "crate::font"
 use crate::font as __with_name0;
 #[rustfmt::skip]
@@ -16,7 +16,7 @@ pub mod exports {
                 static __FORCE_SECTION_REF: fn() = super::super::super::super::__link_custom_section_describing_imports;
                 use super::super::super::super::_rt;
                 pub type FontKey = super::super::super::super::__with_name0::FontKey;
-                #[derive(Clone, Default, Eq, Hash, PartialEq, PartialOrd)]
+                #[derive(Clone, Eq, Hash, PartialEq, PartialOrd)]
                 pub struct Name {
                     pub id: u16,
                     pub name: _rt::String,
@@ -34,7 +34,37 @@ pub mod exports {
                             .finish()
                     }
                 }
-                #[derive(Clone, Default, Eq, Hash, PartialEq, PartialOrd)]
+                /// Why a call failed
+                #[derive(Clone, Eq, Hash, PartialEq, PartialOrd)]
+                pub struct Error {
+                    pub message: _rt::String,
+                    /// The file the error is about, if any
+                    pub path: Option<_rt::String>,
+                    /// The face of a font collection the error is about, if any
+                    pub face_index: Option<u32>,
+                }
+                impl ::core::fmt::Debug for Error {
+                    fn fmt(
+                        &self,
+                        f: &mut ::core::fmt::Formatter<'_>,
+                    ) -> ::core::fmt::Result {
+                        f.debug_struct("Error")
+                            .field("message", &self.message)
+                            .field("path", &self.path)
+                            .field("face-index", &self.face_index)
+                            .finish()
+                    }
+                }
+                impl ::core::fmt::Display for Error {
+                    fn fmt(
+                        &self,
+                        f: &mut ::core::fmt::Formatter<'_>,
+                    ) -> ::core::fmt::Result {
+                        write!(f, "{:?}", self)
+                    }
+                }
+                impl std::error::Error for Error {}
+                #[derive(Clone, Eq, Hash, PartialEq, PartialOrd)]
                 pub struct FontInfo {
                     pub style_names: _rt::Vec<Name>,
                     pub names: _rt::Vec<Name>,
@@ -54,8 +84,67 @@ pub mod exports {
                             .finish()
                     }
                 }
+                /// How the family of a query candidate matched the requested family
+                #[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd)]
+                pub enum FamilyMatch {
+                    /// The family of the font key
+                    Exact,
+                    /// Another name of the font, e.g. its full name or PostScript name
+                    Name,
+                    /// The name of a named instance of a variable font
+                    InstanceName,
+                    /// None of the names match, but one is this number of edits away
+                    Similar(u32),
+                }
+                impl ::core::fmt::Debug for FamilyMatch {
+                    fn fmt(
+                        &self,
+                        f: &mut ::core::fmt::Formatter<'_>,
+                    ) -> ::core::fmt::Result {
+                        match self {
+                            FamilyMatch::Exact => {
+                                f.debug_tuple("FamilyMatch::Exact").finish()
+                            }
+                            FamilyMatch::Name => {
+                                f.debug_tuple("FamilyMatch::Name").finish()
+                            }
+                            FamilyMatch::InstanceName => {
+                                f.debug_tuple("FamilyMatch::InstanceName").finish()
+                            }
+                            FamilyMatch::Similar(e) => {
+                                f.debug_tuple("FamilyMatch::Similar").field(e).finish()
+                            }
+                        }
+                    }
+                }
+                /// A font variant that could answer a query, see `query-candidates`
+                #[derive(Clone, Eq, Hash, PartialEq, PartialOrd)]
+                pub struct QueryCandidate {
+                    /// Key of the variant, which `exact-match` resolves
+                    pub key: FontKey,
+                    pub family_match: FamilyMatch,
+                    pub stretch_distance: u16,
+                    pub italic_matches: bool,
+                    pub weight_distance: u16,
+                    pub variations_match: bool,
+                }
+                impl ::core::fmt::Debug for QueryCandidate {
+                    fn fmt(
+                        &self,
+                        f: &mut ::core::fmt::Formatter<'_>,
+                    ) -> ::core::fmt::Result {
+                        f.debug_struct("QueryCandidate")
+                            .field("key", &self.key)
+                            .field("family-match", &self.family_match)
+                            .field("stretch-distance", &self.stretch_distance)
+                            .field("italic-matches", &self.italic_matches)
+                            .field("weight-distance", &self.weight_distance)
+                            .field("variations-match", &self.variations_match)
+                            .finish()
+                    }
+                }
                 #[repr(C)]
-                #[derive(Clone, Copy, Default, Eq, Hash, PartialEq, PartialOrd)]
+                #[derive(Clone, Copy, Eq, Hash, PartialEq, PartialOrd)]
                 pub struct LineMetrics {
                     pub position: i16,
                     pub thickness: i16,
@@ -1106,6 +1195,134 @@ pub mod exports {
                 }
                 #[doc(hidden)]
                 #[allow(non_snake_case)]
+                pub unsafe fn _export_method_font_instance_buffer_cabi<T: GuestFont>(
+                    arg0: *mut u8,
+                ) -> *mut u8 {
+                    #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
+                    let result0 = T::instance_buffer(
+                        unsafe { FontBorrow::lift(arg0 as u32 as usize) }.get(),
+                    );
+                    let ptr1 = (&raw mut _RET_AREA.0).cast::<u8>();
+                    match result0 {
+                        Ok(e) => {
+                            *ptr1.add(0).cast::<u8>() = (0i32) as u8;
+                            let vec2 = (e).into_boxed_slice();
+                            let ptr2 = vec2.as_ptr().cast::<u8>();
+                            let len2 = vec2.len();
+                            ::core::mem::forget(vec2);
+                            *ptr1
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len2;
+                            *ptr1
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr2.cast_mut();
+                        }
+                        Err(e) => {
+                            *ptr1.add(0).cast::<u8>() = (1i32) as u8;
+                            let Error {
+                                message: message3,
+                                path: path3,
+                                face_index: face_index3,
+                            } = e;
+                            let vec4 = (message3.into_bytes()).into_boxed_slice();
+                            let ptr4 = vec4.as_ptr().cast::<u8>();
+                            let len4 = vec4.len();
+                            ::core::mem::forget(vec4);
+                            *ptr1
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len4;
+                            *ptr1
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr4.cast_mut();
+                            match path3 {
+                                Some(e) => {
+                                    *ptr1
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    let vec5 = (e.into_bytes()).into_boxed_slice();
+                                    let ptr5 = vec5.as_ptr().cast::<u8>();
+                                    let len5 = vec5.len();
+                                    ::core::mem::forget(vec5);
+                                    *ptr1
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len5;
+                                    *ptr1
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = ptr5.cast_mut();
+                                }
+                                None => {
+                                    *ptr1
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match face_index3 {
+                                Some(e) => {
+                                    *ptr1
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *ptr1
+                                        .add(4 + 6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *ptr1
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                        }
+                    };
+                    ptr1
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn __post_return_method_font_instance_buffer<T: GuestFont>(
+                    arg0: *mut u8,
+                ) {
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => {
+                            let l1 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l2 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            let base3 = l1;
+                            let len3 = l2;
+                            _rt::cabi_dealloc(base3, len3 * 1, 1);
+                        }
+                        _ => {
+                            let l4 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l5 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            _rt::cabi_dealloc(l4, l5, 1);
+                            let l6 = i32::from(
+                                *arg0
+                                    .add(3 * ::core::mem::size_of::<*const u8>())
+                                    .cast::<u8>(),
+                            );
+                            match l6 {
+                                0 => {}
+                                _ => {
+                                    let l7 = *arg0
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l8 = *arg0
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l7, l8, 1);
+                                }
+                            }
+                        }
+                    }
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
                 pub unsafe fn _export_method_font_path_cabi<T: GuestFont>(
                     arg0: *mut u8,
                 ) -> *mut u8 {
@@ -1149,6 +1366,7 @@ pub mod exports {
                         stretch: stretch2,
                         family: family2,
                         variations: variations2,
+                        handle: handle2,
                     } = result0;
                     match weight2 {
                         Some(e) => {
@@ -1229,6 +1447,21 @@ pub mod exports {
                     *ptr1
                         .add(8 + 3 * ::core::mem::size_of::<*const u8>())
                         .cast::<*mut u8>() = result6;
+                    match handle2 {
+                        Some(e) => {
+                            *ptr1
+                                .add(8 + 5 * ::core::mem::size_of::<*const u8>())
+                                .cast::<u8>() = (1i32) as u8;
+                            *ptr1
+                                .add(12 + 5 * ::core::mem::size_of::<*const u8>())
+                                .cast::<i32>() = _rt::as_i32(e);
+                        }
+                        None => {
+                            *ptr1
+                                .add(8 + 5 * ::core::mem::size_of::<*const u8>())
+                                .cast::<u8>() = (0i32) as u8;
+                        }
+                    };
                     ptr1
                 }
                 #[doc(hidden)]
@@ -1292,16 +1525,58 @@ pub mod exports {
                         }
                         Err(e) => {
                             *ptr2.add(0).cast::<u8>() = (1i32) as u8;
-                            let vec3 = (e.into_bytes()).into_boxed_slice();
-                            let ptr3 = vec3.as_ptr().cast::<u8>();
-                            let len3 = vec3.len();
-                            ::core::mem::forget(vec3);
+                            let Error {
+                                message: message3,
+                                path: path3,
+                                face_index: face_index3,
+                            } = e;
+                            let vec4 = (message3.into_bytes()).into_boxed_slice();
+                            let ptr4 = vec4.as_ptr().cast::<u8>();
+                            let len4 = vec4.len();
+                            ::core::mem::forget(vec4);
                             *ptr2
                                 .add(2 * ::core::mem::size_of::<*const u8>())
-                                .cast::<usize>() = len3;
+                                .cast::<usize>() = len4;
                             *ptr2
                                 .add(::core::mem::size_of::<*const u8>())
-                                .cast::<*mut u8>() = ptr3.cast_mut();
+                                .cast::<*mut u8>() = ptr4.cast_mut();
+                            match path3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    let vec5 = (e.into_bytes()).into_boxed_slice();
+                                    let ptr5 = vec5.as_ptr().cast::<u8>();
+                                    let len5 = vec5.len();
+                                    ::core::mem::forget(vec5);
+                                    *ptr2
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len5;
+                                    *ptr2
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = ptr5.cast_mut();
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match face_index3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *ptr2
+                                        .add(4 + 6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
                         }
                     };
                     ptr2
@@ -1322,6 +1597,23 @@ pub mod exports {
                                 .add(2 * ::core::mem::size_of::<*const u8>())
                                 .cast::<usize>();
                             _rt::cabi_dealloc(l1, l2, 1);
+                            let l3 = i32::from(
+                                *arg0
+                                    .add(3 * ::core::mem::size_of::<*const u8>())
+                                    .cast::<u8>(),
+                            );
+                            match l3 {
+                                0 => {}
+                                _ => {
+                                    let l4 = *arg0
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l5 = *arg0
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l4, l5, 1);
+                                }
+                            }
                         }
                     }
                 }
@@ -1427,9 +1719,9 @@ pub mod exports {
                     arg2: i32,
                     arg3: *mut u8,
                     arg4: usize,
-                ) {
+                ) -> *mut u8 {
                     #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
-                    T::set_config(
+                    let result1 = T::set_config(
                         unsafe { FontKitBorrow::lift(arg0 as u32 as usize) }.get(),
                         arg1 as u32,
                         match arg2 {
@@ -1449,47 +1741,634 @@ pub mod exports {
                             _ => _rt::invalid_enum_discriminant(),
                         },
                     );
+                    let ptr2 = (&raw mut _RET_AREA.0).cast::<u8>();
+                    match result1 {
+                        Ok(_) => {
+                            *ptr2.add(0).cast::<u8>() = (0i32) as u8;
+                        }
+                        Err(e) => {
+                            *ptr2.add(0).cast::<u8>() = (1i32) as u8;
+                            let Error {
+                                message: message3,
+                                path: path3,
+                                face_index: face_index3,
+                            } = e;
+                            let vec4 = (message3.into_bytes()).into_boxed_slice();
+                            let ptr4 = vec4.as_ptr().cast::<u8>();
+                            let len4 = vec4.len();
+                            ::core::mem::forget(vec4);
+                            *ptr2
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len4;
+                            *ptr2
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr4.cast_mut();
+                            match path3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    let vec5 = (e.into_bytes()).into_boxed_slice();
+                                    let ptr5 = vec5.as_ptr().cast::<u8>();
+                                    let len5 = vec5.len();
+                                    ::core::mem::forget(vec5);
+                                    *ptr2
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len5;
+                                    *ptr2
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = ptr5.cast_mut();
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match face_index3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *ptr2
+                                        .add(4 + 6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                        }
+                    };
+                    ptr2
                 }
                 #[doc(hidden)]
                 #[allow(non_snake_case)]
-                pub unsafe fn _export_method_font_kit_add_font_from_buffer_cabi<
-                    T: GuestFontKit,
-                >(arg0: *mut u8, arg1: *mut u8, arg2: usize) {
-                    #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
-                    let len0 = arg2;
-                    T::add_font_from_buffer(
-                        unsafe { FontKitBorrow::lift(arg0 as u32 as usize) }.get(),
-                        _rt::Vec::from_raw_parts(arg1.cast(), len0, len0),
-                    );
+                pub unsafe fn __post_return_method_font_kit_set_config<T: GuestFontKit>(
+                    arg0: *mut u8,
+                ) {
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => {}
+                        _ => {
+                            let l1 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l2 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            _rt::cabi_dealloc(l1, l2, 1);
+                            let l3 = i32::from(
+                                *arg0
+                                    .add(3 * ::core::mem::size_of::<*const u8>())
+                                    .cast::<u8>(),
+                            );
+                            match l3 {
+                                0 => {}
+                                _ => {
+                                    let l4 = *arg0
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l5 = *arg0
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l4, l5, 1);
+                                }
+                            }
+                        }
+                    }
                 }
                 #[doc(hidden)]
                 #[allow(non_snake_case)]
-                pub unsafe fn _export_method_font_kit_add_search_path_cabi<
+                pub unsafe fn _export_method_font_kit_set_collision_policy_cabi<
                     T: GuestFontKit,
-                >(arg0: *mut u8, arg1: *mut u8, arg2: usize) {
+                >(arg0: *mut u8, arg1: *mut u8, arg2: usize) -> *mut u8 {
                     #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
                     let len0 = arg2;
                     let bytes0 = _rt::Vec::from_raw_parts(arg1.cast(), len0, len0);
-                    T::add_search_path(
+                    let result1 = T::set_collision_policy(
                         unsafe { FontKitBorrow::lift(arg0 as u32 as usize) }.get(),
                         _rt::string_lift(bytes0),
                     );
-                }
-                #[doc(hidden)]
-                #[allow(non_snake_case)]
-                pub unsafe fn _export_method_font_kit_query_cabi<T: GuestFontKit>(
-                    arg0: *mut u8,
-                    arg1: i32,
-                    arg2: i32,
-                    arg3: i32,
-                    arg4: i32,
-                    arg5: i32,
-                    arg6: i32,
-                    arg7: *mut u8,
-                    arg8: usize,
-                    arg9: *mut u8,
-                    arg10: usize,
-                ) -> *mut u8 {
+                    let ptr2 = (&raw mut _RET_AREA.0).cast::<u8>();
+                    match result1 {
+                        Ok(_) => {
+                            *ptr2.add(0).cast::<u8>() = (0i32) as u8;
+                        }
+                        Err(e) => {
+                            *ptr2.add(0).cast::<u8>() = (1i32) as u8;
+                            let Error {
+                                message: message3,
+                                path: path3,
+                                face_index: face_index3,
+                            } = e;
+                            let vec4 = (message3.into_bytes()).into_boxed_slice();
+                            let ptr4 = vec4.as_ptr().cast::<u8>();
+                            let len4 = vec4.len();
+                            ::core::mem::forget(vec4);
+                            *ptr2
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len4;
+                            *ptr2
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr4.cast_mut();
+                            match path3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    let vec5 = (e.into_bytes()).into_boxed_slice();
+                                    let ptr5 = vec5.as_ptr().cast::<u8>();
+                                    let len5 = vec5.len();
+                                    ::core::mem::forget(vec5);
+                                    *ptr2
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len5;
+                                    *ptr2
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = ptr5.cast_mut();
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match face_index3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *ptr2
+                                        .add(4 + 6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                        }
+                    };
+                    ptr2
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn __post_return_method_font_kit_set_collision_policy<
+                    T: GuestFontKit,
+                >(arg0: *mut u8) {
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => {}
+                        _ => {
+                            let l1 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l2 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            _rt::cabi_dealloc(l1, l2, 1);
+                            let l3 = i32::from(
+                                *arg0
+                                    .add(3 * ::core::mem::size_of::<*const u8>())
+                                    .cast::<u8>(),
+                            );
+                            match l3 {
+                                0 => {}
+                                _ => {
+                                    let l4 = *arg0
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l5 = *arg0
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l4, l5, 1);
+                                }
+                            }
+                        }
+                    }
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn _export_method_font_kit_add_font_from_buffer_cabi<
+                    T: GuestFontKit,
+                >(arg0: *mut u8, arg1: *mut u8, arg2: usize) -> *mut u8 {
+                    #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
+                    let len0 = arg2;
+                    let result1 = T::add_font_from_buffer(
+                        unsafe { FontKitBorrow::lift(arg0 as u32 as usize) }.get(),
+                        _rt::Vec::from_raw_parts(arg1.cast(), len0, len0),
+                    );
+                    let ptr2 = (&raw mut _RET_AREA.0).cast::<u8>();
+                    match result1 {
+                        Ok(e) => {
+                            *ptr2.add(0).cast::<u8>() = (0i32) as u8;
+                            let vec8 = e;
+                            let len8 = vec8.len();
+                            let layout8 = _rt::alloc::Layout::from_size_align_unchecked(
+                                vec8.len() * (16 + 5 * ::core::mem::size_of::<*const u8>()),
+                                ::core::mem::size_of::<*const u8>(),
+                            );
+                            let result8 = if layout8.size() != 0 {
+                                let ptr = _rt::alloc::alloc(layout8).cast::<u8>();
+                                if ptr.is_null() {
+                                    _rt::alloc::handle_alloc_error(layout8);
+                                }
+                                ptr
+                            } else {
+                                ::core::ptr::null_mut()
+                            };
+                            for (i, e) in vec8.into_iter().enumerate() {
+                                let base = result8
+                                    .add(i * (16 + 5 * ::core::mem::size_of::<*const u8>()));
+                                {
+                                    let super::super::super::super::__with_name0::FontKey {
+                                        weight: weight3,
+                                        italic: italic3,
+                                        stretch: stretch3,
+                                        family: family3,
+                                        variations: variations3,
+                                        handle: handle3,
+                                    } = e;
+                                    match weight3 {
+                                        Some(e) => {
+                                            *base.add(0).cast::<u8>() = (1i32) as u8;
+                                            *base.add(2).cast::<u16>() = (_rt::as_i32(e)) as u16;
+                                        }
+                                        None => {
+                                            *base.add(0).cast::<u8>() = (0i32) as u8;
+                                        }
+                                    };
+                                    match italic3 {
+                                        Some(e) => {
+                                            *base.add(4).cast::<u8>() = (1i32) as u8;
+                                            *base.add(5).cast::<u8>() = (match e {
+                                                true => 1,
+                                                false => 0,
+                                            }) as u8;
+                                        }
+                                        None => {
+                                            *base.add(4).cast::<u8>() = (0i32) as u8;
+                                        }
+                                    };
+                                    match stretch3 {
+                                        Some(e) => {
+                                            *base.add(6).cast::<u8>() = (1i32) as u8;
+                                            *base.add(8).cast::<u16>() = (_rt::as_i32(e)) as u16;
+                                        }
+                                        None => {
+                                            *base.add(6).cast::<u8>() = (0i32) as u8;
+                                        }
+                                    };
+                                    let vec4 = (family3.into_bytes()).into_boxed_slice();
+                                    let ptr4 = vec4.as_ptr().cast::<u8>();
+                                    let len4 = vec4.len();
+                                    ::core::mem::forget(vec4);
+                                    *base
+                                        .add(8 + 2 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len4;
+                                    *base
+                                        .add(8 + 1 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = ptr4.cast_mut();
+                                    let vec7 = variations3;
+                                    let len7 = vec7.len();
+                                    let layout7 = _rt::alloc::Layout::from_size_align_unchecked(
+                                        vec7.len() * (3 * ::core::mem::size_of::<*const u8>()),
+                                        ::core::mem::size_of::<*const u8>(),
+                                    );
+                                    let result7 = if layout7.size() != 0 {
+                                        let ptr = _rt::alloc::alloc(layout7).cast::<u8>();
+                                        if ptr.is_null() {
+                                            _rt::alloc::handle_alloc_error(layout7);
+                                        }
+                                        ptr
+                                    } else {
+                                        ::core::ptr::null_mut()
+                                    };
+                                    for (i, e) in vec7.into_iter().enumerate() {
+                                        let base = result7
+                                            .add(i * (3 * ::core::mem::size_of::<*const u8>()));
+                                        {
+                                            let (t5_0, t5_1) = e;
+                                            let vec6 = (t5_0.into_bytes()).into_boxed_slice();
+                                            let ptr6 = vec6.as_ptr().cast::<u8>();
+                                            let len6 = vec6.len();
+                                            ::core::mem::forget(vec6);
+                                            *base
+                                                .add(::core::mem::size_of::<*const u8>())
+                                                .cast::<usize>() = len6;
+                                            *base.add(0).cast::<*mut u8>() = ptr6.cast_mut();
+                                            *base
+                                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                                .cast::<f32>() = _rt::as_f32(t5_1);
+                                        }
+                                    }
+                                    *base
+                                        .add(8 + 4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len7;
+                                    *base
+                                        .add(8 + 3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = result7;
+                                    match handle3 {
+                                        Some(e) => {
+                                            *base
+                                                .add(8 + 5 * ::core::mem::size_of::<*const u8>())
+                                                .cast::<u8>() = (1i32) as u8;
+                                            *base
+                                                .add(12 + 5 * ::core::mem::size_of::<*const u8>())
+                                                .cast::<i32>() = _rt::as_i32(e);
+                                        }
+                                        None => {
+                                            *base
+                                                .add(8 + 5 * ::core::mem::size_of::<*const u8>())
+                                                .cast::<u8>() = (0i32) as u8;
+                                        }
+                                    };
+                                }
+                            }
+                            *ptr2
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len8;
+                            *ptr2
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = result8;
+                        }
+                        Err(e) => {
+                            *ptr2.add(0).cast::<u8>() = (1i32) as u8;
+                            let Error {
+                                message: message9,
+                                path: path9,
+                                face_index: face_index9,
+                            } = e;
+                            let vec10 = (message9.into_bytes()).into_boxed_slice();
+                            let ptr10 = vec10.as_ptr().cast::<u8>();
+                            let len10 = vec10.len();
+                            ::core::mem::forget(vec10);
+                            *ptr2
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len10;
+                            *ptr2
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr10.cast_mut();
+                            match path9 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    let vec11 = (e.into_bytes()).into_boxed_slice();
+                                    let ptr11 = vec11.as_ptr().cast::<u8>();
+                                    let len11 = vec11.len();
+                                    ::core::mem::forget(vec11);
+                                    *ptr2
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len11;
+                                    *ptr2
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = ptr11.cast_mut();
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match face_index9 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *ptr2
+                                        .add(4 + 6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                        }
+                    };
+                    ptr2
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn __post_return_method_font_kit_add_font_from_buffer<
+                    T: GuestFontKit,
+                >(arg0: *mut u8) {
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => {
+                            let l1 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l2 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            let base10 = l1;
+                            let len10 = l2;
+                            for i in 0..len10 {
+                                let base = base10
+                                    .add(i * (16 + 5 * ::core::mem::size_of::<*const u8>()));
+                                {
+                                    let l3 = *base
+                                        .add(8 + 1 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l4 = *base
+                                        .add(8 + 2 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l3, l4, 1);
+                                    let l5 = *base
+                                        .add(8 + 3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l6 = *base
+                                        .add(8 + 4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    let base9 = l5;
+                                    let len9 = l6;
+                                    for i in 0..len9 {
+                                        let base = base9
+                                            .add(i * (3 * ::core::mem::size_of::<*const u8>()));
+                                        {
+                                            let l7 = *base.add(0).cast::<*mut u8>();
+                                            let l8 = *base
+                                                .add(::core::mem::size_of::<*const u8>())
+                                                .cast::<usize>();
+                                            _rt::cabi_dealloc(l7, l8, 1);
+                                        }
+                                    }
+                                    _rt::cabi_dealloc(
+                                        base9,
+                                        len9 * (3 * ::core::mem::size_of::<*const u8>()),
+                                        ::core::mem::size_of::<*const u8>(),
+                                    );
+                                }
+                            }
+                            _rt::cabi_dealloc(
+                                base10,
+                                len10 * (16 + 5 * ::core::mem::size_of::<*const u8>()),
+                                ::core::mem::size_of::<*const u8>(),
+                            );
+                        }
+                        _ => {
+                            let l11 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l12 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            _rt::cabi_dealloc(l11, l12, 1);
+                            let l13 = i32::from(
+                                *arg0
+                                    .add(3 * ::core::mem::size_of::<*const u8>())
+                                    .cast::<u8>(),
+                            );
+                            match l13 {
+                                0 => {}
+                                _ => {
+                                    let l14 = *arg0
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l15 = *arg0
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l14, l15, 1);
+                                }
+                            }
+                        }
+                    }
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn _export_method_font_kit_add_search_path_cabi<
+                    T: GuestFontKit,
+                >(arg0: *mut u8, arg1: *mut u8, arg2: usize) -> *mut u8 {
+                    #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
+                    let len0 = arg2;
+                    let bytes0 = _rt::Vec::from_raw_parts(arg1.cast(), len0, len0);
+                    let result1 = T::add_search_path(
+                        unsafe { FontKitBorrow::lift(arg0 as u32 as usize) }.get(),
+                        _rt::string_lift(bytes0),
+                    );
+                    let ptr2 = (&raw mut _RET_AREA.0).cast::<u8>();
+                    match result1 {
+                        Ok(_) => {
+                            *ptr2.add(0).cast::<u8>() = (0i32) as u8;
+                        }
+                        Err(e) => {
+                            *ptr2.add(0).cast::<u8>() = (1i32) as u8;
+                            let Error {
+                                message: message3,
+                                path: path3,
+                                face_index: face_index3,
+                            } = e;
+                            let vec4 = (message3.into_bytes()).into_boxed_slice();
+                            let ptr4 = vec4.as_ptr().cast::<u8>();
+                            let len4 = vec4.len();
+                            ::core::mem::forget(vec4);
+                            *ptr2
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len4;
+                            *ptr2
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr4.cast_mut();
+                            match path3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    let vec5 = (e.into_bytes()).into_boxed_slice();
+                                    let ptr5 = vec5.as_ptr().cast::<u8>();
+                                    let len5 = vec5.len();
+                                    ::core::mem::forget(vec5);
+                                    *ptr2
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len5;
+                                    *ptr2
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = ptr5.cast_mut();
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match face_index3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *ptr2
+                                        .add(4 + 6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                        }
+                    };
+                    ptr2
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn __post_return_method_font_kit_add_search_path<
+                    T: GuestFontKit,
+                >(arg0: *mut u8) {
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => {}
+                        _ => {
+                            let l1 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l2 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            _rt::cabi_dealloc(l1, l2, 1);
+                            let l3 = i32::from(
+                                *arg0
+                                    .add(3 * ::core::mem::size_of::<*const u8>())
+                                    .cast::<u8>(),
+                            );
+                            match l3 {
+                                0 => {}
+                                _ => {
+                                    let l4 = *arg0
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l5 = *arg0
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l4, l5, 1);
+                                }
+                            }
+                        }
+                    }
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn _export_method_font_kit_query_cabi<T: GuestFontKit>(
+                    arg0: *mut u8,
+                    arg1: i32,
+                    arg2: i32,
+                    arg3: i32,
+                    arg4: i32,
+                    arg5: i32,
+                    arg6: i32,
+                    arg7: *mut u8,
+                    arg8: usize,
+                    arg9: *mut u8,
+                    arg10: usize,
+                    arg11: i32,
+                    arg12: i32,
+                ) -> *mut u8 {
                     #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
                     let len0 = arg8;
                     let bytes0 = _rt::Vec::from_raw_parts(arg7.cast(), len0, len0);
@@ -1547,6 +2426,14 @@ pub mod exports {
                             },
                             family: _rt::string_lift(bytes0),
                             variations: result5,
+                            handle: match arg11 {
+                                0 => None,
+                                1 => {
+                                    let e = arg12 as u32;
+                                    Some(e)
+                                }
+                                _ => _rt::invalid_enum_discriminant(),
+                            },
                         },
                     );
                     let ptr7 = (&raw mut _RET_AREA.0).cast::<u8>();
@@ -1563,6 +2450,325 @@ pub mod exports {
                 }
                 #[doc(hidden)]
                 #[allow(non_snake_case)]
+                pub unsafe fn _export_method_font_kit_query_candidates_cabi<
+                    T: GuestFontKit,
+                >(
+                    arg0: *mut u8,
+                    arg1: i32,
+                    arg2: i32,
+                    arg3: i32,
+                    arg4: i32,
+                    arg5: i32,
+                    arg6: i32,
+                    arg7: *mut u8,
+                    arg8: usize,
+                    arg9: *mut u8,
+                    arg10: usize,
+                    arg11: i32,
+                    arg12: i32,
+                ) -> *mut u8 {
+                    #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
+                    let len0 = arg8;
+                    let bytes0 = _rt::Vec::from_raw_parts(arg7.cast(), len0, len0);
+                    let base5 = arg9;
+                    let len5 = arg10;
+                    let mut result5 = _rt::Vec::with_capacity(len5);
+                    for i in 0..len5 {
+                        let base = base5
+                            .add(i * (3 * ::core::mem::size_of::<*const u8>()));
+                        let e5 = {
+                            let l1 = *base.add(0).cast::<*mut u8>();
+                            let l2 = *base
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            let len3 = l2;
+                            let bytes3 = _rt::Vec::from_raw_parts(l1.cast(), len3, len3);
+                            let l4 = *base
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<f32>();
+                            (_rt::string_lift(bytes3), l4)
+                        };
+                        result5.push(e5);
+                    }
+                    _rt::cabi_dealloc(
+                        base5,
+                        len5 * (3 * ::core::mem::size_of::<*const u8>()),
+                        ::core::mem::size_of::<*const u8>(),
+                    );
+                    let result6 = T::query_candidates(
+                        unsafe { FontKitBorrow::lift(arg0 as u32 as usize) }.get(),
+                        super::super::super::super::__with_name0::FontKey {
+                            weight: match arg1 {
+                                0 => None,
+                                1 => {
+                                    let e = arg2 as u16;
+                                    Some(e)
+                                }
+                                _ => _rt::invalid_enum_discriminant(),
+                            },
+                            italic: match arg3 {
+                                0 => None,
+                                1 => {
+                                    let e = _rt::bool_lift(arg4 as u8);
+                                    Some(e)
+                                }
+                                _ => _rt::invalid_enum_discriminant(),
+                            },
+                            stretch: match arg5 {
+                                0 => None,
+                                1 => {
+                                    let e = arg6 as u16;
+                                    Some(e)
+                                }
+                                _ => _rt::invalid_enum_discriminant(),
+                            },
+                            family: _rt::string_lift(bytes0),
+                            variations: result5,
+                            handle: match arg11 {
+                                0 => None,
+                                1 => {
+                                    let e = arg12 as u32;
+                                    Some(e)
+                                }
+                                _ => _rt::invalid_enum_discriminant(),
+                            },
+                        },
+                    );
+                    let ptr7 = (&raw mut _RET_AREA.0).cast::<u8>();
+                    let vec14 = result6;
+                    let len14 = vec14.len();
+                    let layout14 = _rt::alloc::Layout::from_size_align_unchecked(
+                        vec14.len() * (32 + 5 * ::core::mem::size_of::<*const u8>()),
+                        ::core::mem::size_of::<*const u8>(),
+                    );
+                    let result14 = if layout14.size() != 0 {
+                        let ptr = _rt::alloc::alloc(layout14).cast::<u8>();
+                        if ptr.is_null() {
+                            _rt::alloc::handle_alloc_error(layout14);
+                        }
+                        ptr
+                    } else {
+                        ::core::ptr::null_mut()
+                    };
+                    for (i, e) in vec14.into_iter().enumerate() {
+                        let base = result14
+                            .add(i * (32 + 5 * ::core::mem::size_of::<*const u8>()));
+                        {
+                            let QueryCandidate {
+                                key: key8,
+                                family_match: family_match8,
+                                stretch_distance: stretch_distance8,
+                                italic_matches: italic_matches8,
+                                weight_distance: weight_distance8,
+                                variations_match: variations_match8,
+                            } = e;
+                            let super::super::super::super::__with_name0::FontKey {
+                                weight: weight9,
+                                italic: italic9,
+                                stretch: stretch9,
+                                family: family9,
+                                variations: variations9,
+                                handle: handle9,
+                            } = key8;
+                            match weight9 {
+                                Some(e) => {
+                                    *base.add(0).cast::<u8>() = (1i32) as u8;
+                                    *base.add(2).cast::<u16>() = (_rt::as_i32(e)) as u16;
+                                }
+                                None => {
+                                    *base.add(0).cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match italic9 {
+                                Some(e) => {
+                                    *base.add(4).cast::<u8>() = (1i32) as u8;
+                                    *base.add(5).cast::<u8>() = (match e {
+                                        true => 1,
+                                        false => 0,
+                                    }) as u8;
+                                }
+                                None => {
+                                    *base.add(4).cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match stretch9 {
+                                Some(e) => {
+                                    *base.add(6).cast::<u8>() = (1i32) as u8;
+                                    *base.add(8).cast::<u16>() = (_rt::as_i32(e)) as u16;
+                                }
+                                None => {
+                                    *base.add(6).cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            let vec10 = (family9.into_bytes()).into_boxed_slice();
+                            let ptr10 = vec10.as_ptr().cast::<u8>();
+                            let len10 = vec10.len();
+                            ::core::mem::forget(vec10);
+                            *base
+                                .add(8 + 2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len10;
+                            *base
+                                .add(8 + 1 * ::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr10.cast_mut();
+                            let vec13 = variations9;
+                            let len13 = vec13.len();
+                            let layout13 = _rt::alloc::Layout::from_size_align_unchecked(
+                                vec13.len() * (3 * ::core::mem::size_of::<*const u8>()),
+                                ::core::mem::size_of::<*const u8>(),
+                            );
+                            let result13 = if layout13.size() != 0 {
+                                let ptr = _rt::alloc::alloc(layout13).cast::<u8>();
+                                if ptr.is_null() {
+                                    _rt::alloc::handle_alloc_error(layout13);
+                                }
+                                ptr
+                            } else {
+                                ::core::ptr::null_mut()
+                            };
+                            for (i, e) in vec13.into_iter().enumerate() {
+                                let base = result13
+                                    .add(i * (3 * ::core::mem::size_of::<*const u8>()));
+                                {
+                                    let (t11_0, t11_1) = e;
+                                    let vec12 = (t11_0.into_bytes()).into_boxed_slice();
+                                    let ptr12 = vec12.as_ptr().cast::<u8>();
+                                    let len12 = vec12.len();
+                                    ::core::mem::forget(vec12);
+                                    *base
+                                        .add(::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len12;
+                                    *base.add(0).cast::<*mut u8>() = ptr12.cast_mut();
+                                    *base
+                                        .add(2 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<f32>() = _rt::as_f32(t11_1);
+                                }
+                            }
+                            *base
+                                .add(8 + 4 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len13;
+                            *base
+                                .add(8 + 3 * ::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = result13;
+                            match handle9 {
+                                Some(e) => {
+                                    *base
+                                        .add(8 + 5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *base
+                                        .add(12 + 5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *base
+                                        .add(8 + 5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match family_match8 {
+                                FamilyMatch::Exact => {
+                                    *base
+                                        .add(16 + 5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                                FamilyMatch::Name => {
+                                    *base
+                                        .add(16 + 5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                }
+                                FamilyMatch::InstanceName => {
+                                    *base
+                                        .add(16 + 5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (2i32) as u8;
+                                }
+                                FamilyMatch::Similar(e) => {
+                                    *base
+                                        .add(16 + 5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (3i32) as u8;
+                                    *base
+                                        .add(20 + 5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                            }
+                            *base
+                                .add(24 + 5 * ::core::mem::size_of::<*const u8>())
+                                .cast::<u16>() = (_rt::as_i32(stretch_distance8)) as u16;
+                            *base
+                                .add(26 + 5 * ::core::mem::size_of::<*const u8>())
+                                .cast::<u8>() = (match italic_matches8 {
+                                true => 1,
+                                false => 0,
+                            }) as u8;
+                            *base
+                                .add(28 + 5 * ::core::mem::size_of::<*const u8>())
+                                .cast::<u16>() = (_rt::as_i32(weight_distance8)) as u16;
+                            *base
+                                .add(30 + 5 * ::core::mem::size_of::<*const u8>())
+                                .cast::<u8>() = (match variations_match8 {
+                                true => 1,
+                                false => 0,
+                            }) as u8;
+                        }
+                    }
+                    *ptr7.add(::core::mem::size_of::<*const u8>()).cast::<usize>() = len14;
+                    *ptr7.add(0).cast::<*mut u8>() = result14;
+                    ptr7
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn __post_return_method_font_kit_query_candidates<
+                    T: GuestFontKit,
+                >(arg0: *mut u8) {
+                    let l0 = *arg0.add(0).cast::<*mut u8>();
+                    let l1 = *arg0
+                        .add(::core::mem::size_of::<*const u8>())
+                        .cast::<usize>();
+                    let base9 = l0;
+                    let len9 = l1;
+                    for i in 0..len9 {
+                        let base = base9
+                            .add(i * (32 + 5 * ::core::mem::size_of::<*const u8>()));
+                        {
+                            let l2 = *base
+                                .add(8 + 1 * ::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l3 = *base
+                                .add(8 + 2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            _rt::cabi_dealloc(l2, l3, 1);
+                            let l4 = *base
+                                .add(8 + 3 * ::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l5 = *base
+                                .add(8 + 4 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            let base8 = l4;
+                            let len8 = l5;
+                            for i in 0..len8 {
+                                let base = base8
+                                    .add(i * (3 * ::core::mem::size_of::<*const u8>()));
+                                {
+                                    let l6 = *base.add(0).cast::<*mut u8>();
+                                    let l7 = *base
+                                        .add(::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l6, l7, 1);
+                                }
+                            }
+                            _rt::cabi_dealloc(
+                                base8,
+                                len8 * (3 * ::core::mem::size_of::<*const u8>()),
+                                ::core::mem::size_of::<*const u8>(),
+                            );
+                        }
+                    }
+                    _rt::cabi_dealloc(
+                        base9,
+                        len9 * (32 + 5 * ::core::mem::size_of::<*const u8>()),
+                        ::core::mem::size_of::<*const u8>(),
+                    );
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
                 pub unsafe fn _export_method_font_kit_query_font_info_cabi<
                     T: GuestFontKit,
                 >(
@@ -1577,6 +2783,8 @@ pub mod exports {
                     arg8: usize,
                     arg9: *mut u8,
                     arg10: usize,
+                    arg11: i32,
+                    arg12: i32,
                 ) -> *mut u8 {
                     #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
                     let len0 = arg8;
@@ -1635,6 +2843,14 @@ pub mod exports {
                             },
                             family: _rt::string_lift(bytes0),
                             variations: result5,
+                            handle: match arg11 {
+                                0 => None,
+                                1 => {
+                                    let e = arg12 as u32;
+                                    Some(e)
+                                }
+                                _ => _rt::invalid_enum_discriminant(),
+                            },
                         },
                     );
                     let ptr7 = (&raw mut _RET_AREA.0).cast::<u8>();
@@ -1645,7 +2861,7 @@ pub mod exports {
                             let len21 = vec21.len();
                             let layout21 = _rt::alloc::Layout::from_size_align_unchecked(
                                 vec21.len()
-                                    * (8 + 12 * ::core::mem::size_of::<*const u8>()),
+                                    * (16 + 12 * ::core::mem::size_of::<*const u8>()),
                                 ::core::mem::size_of::<*const u8>(),
                             );
                             let result21 = if layout21.size() != 0 {
@@ -1659,7 +2875,7 @@ pub mod exports {
                             };
                             for (i, e) in vec21.into_iter().enumerate() {
                                 let base = result21
-                                    .add(i * (8 + 12 * ::core::mem::size_of::<*const u8>()));
+                                    .add(i * (16 + 12 * ::core::mem::size_of::<*const u8>()));
                                 {
                                     let FontInfo {
                                         style_names: style_names8,
@@ -1785,6 +3001,7 @@ pub mod exports {
                                         stretch: stretch16,
                                         family: family16,
                                         variations: variations16,
+                                        handle: handle16,
                                     } = key8;
                                     match weight16 {
                                         Some(e) => {
@@ -1883,6 +3100,21 @@ pub mod exports {
                                     *base
                                         .add(8 + 10 * ::core::mem::size_of::<*const u8>())
                                         .cast::<*mut u8>() = result20;
+                                    match handle16 {
+                                        Some(e) => {
+                                            *base
+                                                .add(8 + 12 * ::core::mem::size_of::<*const u8>())
+                                                .cast::<u8>() = (1i32) as u8;
+                                            *base
+                                                .add(12 + 12 * ::core::mem::size_of::<*const u8>())
+                                                .cast::<i32>() = _rt::as_i32(e);
+                                        }
+                                        None => {
+                                            *base
+                                                .add(8 + 12 * ::core::mem::size_of::<*const u8>())
+                                                .cast::<u8>() = (0i32) as u8;
+                                        }
+                                    };
                                 }
                             }
                             *ptr7
@@ -1917,7 +3149,7 @@ pub mod exports {
                             let len23 = l2;
                             for i in 0..len23 {
                                 let base = base23
-                                    .add(i * (8 + 12 * ::core::mem::size_of::<*const u8>()));
+                                    .add(i * (16 + 12 * ::core::mem::size_of::<*const u8>()));
                                 {
                                     let l3 = *base.add(0).cast::<*mut u8>();
                                     let l4 = *base
@@ -2021,7 +3253,7 @@ pub mod exports {
                             }
                             _rt::cabi_dealloc(
                                 base23,
-                                len23 * (8 + 12 * ::core::mem::size_of::<*const u8>()),
+                                len23 * (16 + 12 * ::core::mem::size_of::<*const u8>()),
                                 ::core::mem::size_of::<*const u8>(),
                             );
                         }
@@ -2041,6 +3273,8 @@ pub mod exports {
                     arg8: usize,
                     arg9: *mut u8,
                     arg10: usize,
+                    arg11: i32,
+                    arg12: i32,
                 ) -> *mut u8 {
                     #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
                     let len0 = arg8;
@@ -2099,6 +3333,14 @@ pub mod exports {
                             },
                             family: _rt::string_lift(bytes0),
                             variations: result5,
+                            handle: match arg11 {
+                                0 => None,
+                                1 => {
+                                    let e = arg12 as u32;
+                                    Some(e)
+                                }
+                                _ => _rt::invalid_enum_discriminant(),
+                            },
                         },
                     );
                     let ptr7 = (&raw mut _RET_AREA.0).cast::<u8>();
@@ -2126,7 +3368,7 @@ pub mod exports {
                     let vec15 = result0;
                     let len15 = vec15.len();
                     let layout15 = _rt::alloc::Layout::from_size_align_unchecked(
-                        vec15.len() * (8 + 12 * ::core::mem::size_of::<*const u8>()),
+                        vec15.len() * (16 + 12 * ::core::mem::size_of::<*const u8>()),
                         ::core::mem::size_of::<*const u8>(),
                     );
                     let result15 = if layout15.size() != 0 {
@@ -2140,7 +3382,7 @@ pub mod exports {
                     };
                     for (i, e) in vec15.into_iter().enumerate() {
                         let base = result15
-                            .add(i * (8 + 12 * ::core::mem::size_of::<*const u8>()));
+                            .add(i * (16 + 12 * ::core::mem::size_of::<*const u8>()));
                         {
                             let FontInfo {
                                 style_names: style_names2,
@@ -2266,6 +3508,7 @@ pub mod exports {
                                 stretch: stretch10,
                                 family: family10,
                                 variations: variations10,
+                                handle: handle10,
                             } = key2;
                             match weight10 {
                                 Some(e) => {
@@ -2364,6 +3607,21 @@ pub mod exports {
                             *base
                                 .add(8 + 10 * ::core::mem::size_of::<*const u8>())
                                 .cast::<*mut u8>() = result14;
+                            match handle10 {
+                                Some(e) => {
+                                    *base
+                                        .add(8 + 12 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *base
+                                        .add(12 + 12 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *base
+                                        .add(8 + 12 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
                         }
                     }
                     *ptr1.add(::core::mem::size_of::<*const u8>()).cast::<usize>() = len15;
@@ -2383,7 +3641,7 @@ pub mod exports {
                     let len22 = l1;
                     for i in 0..len22 {
                         let base = base22
-                            .add(i * (8 + 12 * ::core::mem::size_of::<*const u8>()));
+                            .add(i * (16 + 12 * ::core::mem::size_of::<*const u8>()));
                         {
                             let l2 = *base.add(0).cast::<*mut u8>();
                             let l3 = *base
@@ -2487,7 +3745,7 @@ pub mod exports {
                     }
                     _rt::cabi_dealloc(
                         base22,
-                        len22 * (8 + 12 * ::core::mem::size_of::<*const u8>()),
+                        len22 * (16 + 12 * ::core::mem::size_of::<*const u8>()),
                         ::core::mem::size_of::<*const u8>(),
                     );
                 }
@@ -2516,6 +3774,8 @@ pub mod exports {
                     arg8: usize,
                     arg9: *mut u8,
                     arg10: usize,
+                    arg11: i32,
+                    arg12: i32,
                 ) {
                     #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
                     let len0 = arg8;
@@ -2574,6 +3834,14 @@ pub mod exports {
                             },
                             family: _rt::string_lift(bytes0),
                             variations: result5,
+                            handle: match arg11 {
+                                0 => None,
+                                1 => {
+                                    let e = arg12 as u32;
+                                    Some(e)
+                                }
+                                _ => _rt::invalid_enum_discriminant(),
+                            },
                         },
                     );
                 }
@@ -2591,8 +3859,10 @@ pub mod exports {
                     arg8: usize,
                     arg9: *mut u8,
                     arg10: usize,
-                    arg11: *mut u8,
-                    arg12: usize,
+                    arg11: i32,
+                    arg12: i32,
+                    arg13: *mut u8,
+                    arg14: usize,
                 ) -> *mut u8 {
                     #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
                     let len0 = arg8;
@@ -2622,8 +3892,8 @@ pub mod exports {
                         len5 * (3 * ::core::mem::size_of::<*const u8>()),
                         ::core::mem::size_of::<*const u8>(),
                     );
-                    let len6 = arg12;
-                    let bytes6 = _rt::Vec::from_raw_parts(arg11.cast(), len6, len6);
+                    let len6 = arg14;
+                    let bytes6 = _rt::Vec::from_raw_parts(arg13.cast(), len6, len6);
                     let result7 = T::measure(
                         unsafe { FontKitBorrow::lift(arg0 as u32 as usize) }.get(),
                         super::super::super::super::__with_name0::FontKey {
@@ -2653,6 +3923,14 @@ pub mod exports {
                             },
                             family: _rt::string_lift(bytes0),
                             variations: result5,
+                            handle: match arg11 {
+                                0 => None,
+                                1 => {
+                                    let e = arg12 as u32;
+                                    Some(e)
+                                }
+                                _ => _rt::invalid_enum_discriminant(),
+                            },
                         },
                         _rt::string_lift(bytes6),
                     );
@@ -2678,12 +3956,76 @@ pub mod exports {
                         unsafe { FontKitBorrow::lift(arg0 as u32 as usize) }.get(),
                     );
                     let ptr1 = (&raw mut _RET_AREA.0).cast::<u8>();
-                    let vec2 = (result0.into_bytes()).into_boxed_slice();
-                    let ptr2 = vec2.as_ptr().cast::<u8>();
-                    let len2 = vec2.len();
-                    ::core::mem::forget(vec2);
-                    *ptr1.add(::core::mem::size_of::<*const u8>()).cast::<usize>() = len2;
-                    *ptr1.add(0).cast::<*mut u8>() = ptr2.cast_mut();
+                    match result0 {
+                        Ok(e) => {
+                            *ptr1.add(0).cast::<u8>() = (0i32) as u8;
+                            let vec2 = (e.into_bytes()).into_boxed_slice();
+                            let ptr2 = vec2.as_ptr().cast::<u8>();
+                            let len2 = vec2.len();
+                            ::core::mem::forget(vec2);
+                            *ptr1
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len2;
+                            *ptr1
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr2.cast_mut();
+                        }
+                        Err(e) => {
+                            *ptr1.add(0).cast::<u8>() = (1i32) as u8;
+                            let Error {
+                                message: message3,
+                                path: path3,
+                                face_index: face_index3,
+                            } = e;
+                            let vec4 = (message3.into_bytes()).into_boxed_slice();
+                            let ptr4 = vec4.as_ptr().cast::<u8>();
+                            let len4 = vec4.len();
+                            ::core::mem::forget(vec4);
+                            *ptr1
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len4;
+                            *ptr1
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr4.cast_mut();
+                            match path3 {
+                                Some(e) => {
+                                    *ptr1
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    let vec5 = (e.into_bytes()).into_boxed_slice();
+                                    let ptr5 = vec5.as_ptr().cast::<u8>();
+                                    let len5 = vec5.len();
+                                    ::core::mem::forget(vec5);
+                                    *ptr1
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len5;
+                                    *ptr1
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = ptr5.cast_mut();
+                                }
+                                None => {
+                                    *ptr1
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match face_index3 {
+                                Some(e) => {
+                                    *ptr1
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *ptr1
+                                        .add(4 + 6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *ptr1
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                        }
+                    };
                     ptr1
                 }
                 #[doc(hidden)]
@@ -2691,11 +4033,44 @@ pub mod exports {
                 pub unsafe fn __post_return_method_font_kit_write_data<T: GuestFontKit>(
                     arg0: *mut u8,
                 ) {
-                    let l0 = *arg0.add(0).cast::<*mut u8>();
-                    let l1 = *arg0
-                        .add(::core::mem::size_of::<*const u8>())
-                        .cast::<usize>();
-                    _rt::cabi_dealloc(l0, l1, 1);
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => {
+                            let l1 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l2 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            _rt::cabi_dealloc(l1, l2, 1);
+                        }
+                        _ => {
+                            let l3 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l4 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            _rt::cabi_dealloc(l3, l4, 1);
+                            let l5 = i32::from(
+                                *arg0
+                                    .add(3 * ::core::mem::size_of::<*const u8>())
+                                    .cast::<u8>(),
+                            );
+                            match l5 {
+                                0 => {}
+                                _ => {
+                                    let l6 = *arg0
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l7 = *arg0
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l6, l7, 1);
+                                }
+                            }
+                        }
+                    }
                 }
                 #[doc(hidden)]
                 #[allow(non_snake_case)]
@@ -2703,14 +4078,112 @@ pub mod exports {
                     arg0: *mut u8,
                     arg1: *mut u8,
                     arg2: usize,
-                ) {
+                ) -> *mut u8 {
                     #[cfg(target_arch = "wasm32")] _rt::run_ctors_once();
                     let len0 = arg2;
                     let bytes0 = _rt::Vec::from_raw_parts(arg1.cast(), len0, len0);
-                    T::read_data(
+                    let result1 = T::read_data(
                         unsafe { FontKitBorrow::lift(arg0 as u32 as usize) }.get(),
                         _rt::string_lift(bytes0),
                     );
+                    let ptr2 = (&raw mut _RET_AREA.0).cast::<u8>();
+                    match result1 {
+                        Ok(_) => {
+                            *ptr2.add(0).cast::<u8>() = (0i32) as u8;
+                        }
+                        Err(e) => {
+                            *ptr2.add(0).cast::<u8>() = (1i32) as u8;
+                            let Error {
+                                message: message3,
+                                path: path3,
+                                face_index: face_index3,
+                            } = e;
+                            let vec4 = (message3.into_bytes()).into_boxed_slice();
+                            let ptr4 = vec4.as_ptr().cast::<u8>();
+                            let len4 = vec4.len();
+                            ::core::mem::forget(vec4);
+                            *ptr2
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>() = len4;
+                            *ptr2
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>() = ptr4.cast_mut();
+                            match path3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    let vec5 = (e.into_bytes()).into_boxed_slice();
+                                    let ptr5 = vec5.as_ptr().cast::<u8>();
+                                    let len5 = vec5.len();
+                                    ::core::mem::forget(vec5);
+                                    *ptr2
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>() = len5;
+                                    *ptr2
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>() = ptr5.cast_mut();
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(3 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                            match face_index3 {
+                                Some(e) => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (1i32) as u8;
+                                    *ptr2
+                                        .add(4 + 6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<i32>() = _rt::as_i32(e);
+                                }
+                                None => {
+                                    *ptr2
+                                        .add(6 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<u8>() = (0i32) as u8;
+                                }
+                            };
+                        }
+                    };
+                    ptr2
+                }
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                pub unsafe fn __post_return_method_font_kit_read_data<T: GuestFontKit>(
+                    arg0: *mut u8,
+                ) {
+                    let l0 = i32::from(*arg0.add(0).cast::<u8>());
+                    match l0 {
+                        0 => {}
+                        _ => {
+                            let l1 = *arg0
+                                .add(::core::mem::size_of::<*const u8>())
+                                .cast::<*mut u8>();
+                            let l2 = *arg0
+                                .add(2 * ::core::mem::size_of::<*const u8>())
+                                .cast::<usize>();
+                            _rt::cabi_dealloc(l1, l2, 1);
+                            let l3 = i32::from(
+                                *arg0
+                                    .add(3 * ::core::mem::size_of::<*const u8>())
+                                    .cast::<u8>(),
+                            );
+                            match l3 {
+                                0 => {}
+                                _ => {
+                                    let l4 = *arg0
+                                        .add(4 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<*mut u8>();
+                                    let l5 = *arg0
+                                        .add(5 * ::core::mem::size_of::<*const u8>())
+                                        .cast::<usize>();
+                                    _rt::cabi_dealloc(l4, l5, 1);
+                                }
+                            }
+                        }
+                    }
                 }
                 #[doc(hidden)]
                 #[allow(non_snake_case)]
@@ -2946,15 +4419,14 @@ pub mod exports {
                     fn glyph_path_string(&self, c: char) -> Option<_rt::String>;
                     /// Return the font buffer
                     fn buffer(&self) -> _rt::Vec<u8>;
+                    /// Return a static font of this face at the variation coordinates of its key
+                    fn instance_buffer(&self) -> Result<_rt::Vec<u8>, Error>;
                     /// Return the path if this font is added from searching a path
                     fn path(&self) -> _rt::String;
                     /// Return the key of this font
                     fn key(&self) -> FontKey;
                     /// Measure text using this font
-                    fn measure(
-                        &self,
-                        text: _rt::String,
-                    ) -> Result<TextMetrics, _rt::String>;
+                    fn measure(&self, text: _rt::String) -> Result<TextMetrics, Error>;
                     fn ascender(&self) -> i16;
                     fn descender(&self) -> i16;
                     fn units_per_em(&self) -> u16;
@@ -3018,14 +4490,26 @@ pub mod exports {
                         &self,
                         limit: u32,
                         cache_path: Option<_rt::String>,
-                    ) -> ();
+                    ) -> Result<(), Error>;
+                    /// Choose what adding a font does when a font from another file is already registered under its key:
+                    /// "keep" the registered font, "replace" it (the default) or "keep-both", giving the new font's keys
+                    /// the first free handle from 2
+                    fn set_collision_policy(
+                        &self,
+                        policy: _rt::String,
+                    ) -> Result<(), Error>;
                     /// Register a font (or several fonts in case of ttc), return the keys of added fonts.
                     /// The file type is extracted from the buffer by checking magic numbers
-                    fn add_font_from_buffer(&self, buffer: _rt::Vec<u8>) -> ();
+                    fn add_font_from_buffer(
+                        &self,
+                        buffer: _rt::Vec<u8>,
+                    ) -> Result<_rt::Vec<FontKey>, Error>;
                     /// Search and add fonts from a path
-                    fn add_search_path(&self, path: _rt::String) -> ();
+                    fn add_search_path(&self, path: _rt::String) -> Result<(), Error>;
                     /// Query font using a key, this API returns valid result only if a single result is found
                     fn query(&self, key: FontKey) -> Option<Font>;
+                    /// List every font variant that could answer a query, best first, including families with small typos
+                    fn query_candidates(&self, key: FontKey) -> _rt::Vec<QueryCandidate>;
                     /// Query the info of font, even if the font is unloaded and `query` returns `None`
                     fn query_font_info(
                         &self,
@@ -3046,9 +4530,9 @@ pub mod exports {
                         text: _rt::String,
                     ) -> Option<TextMetrics>;
                     /// Export all font data into a JSON string
-                    fn write_data(&self) -> _rt::String;
-                    /// Load font data from JSON
-                    fn read_data(&self, data: _rt::String) -> ();
+                    fn write_data(&self) -> Result<_rt::String, Error>;
+                    /// Load font data from JSON, failing without loading any font when an entry is malformed
+                    fn read_data(&self, data: _rt::String) -> Result<(), Error>;
                     fn buffer_size(&self) -> u32;
                 }
                 #[doc(hidden)]
@@ -3277,6 +4761,19 @@ pub mod exports {
                         u8,) { unsafe { $($path_to_types)*::
                         __post_return_method_font_buffer::<<$ty as $($path_to_types)*::
                         Guest >::Font > (arg0) } } #[unsafe (export_name =
+                        "alibaba:fontkit/fontkit-interface#[method]font.instance-buffer")]
+                        unsafe extern "C" fn export_method_font_instance_buffer(arg0 : *
+                        mut u8,) -> * mut u8 { unsafe { $($path_to_types)*::
+                        _export_method_font_instance_buffer_cabi::<<$ty as
+                        $($path_to_types)*:: Guest >::Font > (arg0) } } #[unsafe
+                        (export_name =
+                        "cabi_post_alibaba:fontkit/fontkit-interface#[method]font.instance-buffer")]
+                        unsafe extern "C" fn
+                        _post_return_method_font_instance_buffer(arg0 : * mut u8,) {
+                        unsafe { $($path_to_types)*::
+                        __post_return_method_font_instance_buffer::<<$ty as
+                        $($path_to_types)*:: Guest >::Font > (arg0) } } #[unsafe
+                        (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font.path")] unsafe
                         extern "C" fn export_method_font_path(arg0 : * mut u8,) -> * mut
                         u8 { unsafe { $($path_to_types)*::
@@ -3343,42 +4840,97 @@ pub mod exports {
                         Guest >::FontKit > () } } #[unsafe (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.set-config")]
                         unsafe extern "C" fn export_method_font_kit_set_config(arg0 : *
-                        mut u8, arg1 : i32, arg2 : i32, arg3 : * mut u8, arg4 : usize,) {
-                        unsafe { $($path_to_types)*::
+                        mut u8, arg1 : i32, arg2 : i32, arg3 : * mut u8, arg4 : usize,)
+                        -> * mut u8 { unsafe { $($path_to_types)*::
                         _export_method_font_kit_set_config_cabi::<<$ty as
                         $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2, arg3,
                         arg4) } } #[unsafe (export_name =
+                        "cabi_post_alibaba:fontkit/fontkit-interface#[method]font-kit.set-config")]
+                        unsafe extern "C" fn _post_return_method_font_kit_set_config(arg0
+                        : * mut u8,) { unsafe { $($path_to_types)*::
+                        __post_return_method_font_kit_set_config::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0) } } #[unsafe
+                        (export_name =
+                        "alibaba:fontkit/fontkit-interface#[method]font-kit.set-collision-policy")]
+                        unsafe extern "C" fn
+                        export_method_font_kit_set_collision_policy(arg0 : * mut u8, arg1
+                        : * mut u8, arg2 : usize,) -> * mut u8 { unsafe {
+                        $($path_to_types)*::
+                        _export_method_font_kit_set_collision_policy_cabi::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2) } }
+                        #[unsafe (export_name =
+                        "cabi_post_alibaba:fontkit/fontkit-interface#[method]font-kit.set-collision-policy")]
+                        unsafe extern "C" fn
+                        _post_return_method_font_kit_set_collision_policy(arg0 : * mut
+                        u8,) { unsafe { $($path_to_types)*::
+                        __post_return_method_font_kit_set_collision_policy::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0) } } #[unsafe
+                        (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.add-font-from-buffer")]
                         unsafe extern "C" fn
                         export_method_font_kit_add_font_from_buffer(arg0 : * mut u8, arg1
-                        : * mut u8, arg2 : usize,) { unsafe { $($path_to_types)*::
+                        : * mut u8, arg2 : usize,) -> * mut u8 { unsafe {
+                        $($path_to_types)*::
                         _export_method_font_kit_add_font_from_buffer_cabi::<<$ty as
                         $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2) } }
                         #[unsafe (export_name =
+                        "cabi_post_alibaba:fontkit/fontkit-interface#[method]font-kit.add-font-from-buffer")]
+                        unsafe extern "C" fn
+                        _post_return_method_font_kit_add_font_from_buffer(arg0 : * mut
+                        u8,) { unsafe { $($path_to_types)*::
+                        __post_return_method_font_kit_add_font_from_buffer::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0) } } #[unsafe
+                        (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.add-search-path")]
                         unsafe extern "C" fn export_method_font_kit_add_search_path(arg0
-                        : * mut u8, arg1 : * mut u8, arg2 : usize,) { unsafe {
-                        $($path_to_types)*::
+                        : * mut u8, arg1 : * mut u8, arg2 : usize,) -> * mut u8 { unsafe
+                        { $($path_to_types)*::
                         _export_method_font_kit_add_search_path_cabi::<<$ty as
                         $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2) } }
                         #[unsafe (export_name =
+                        "cabi_post_alibaba:fontkit/fontkit-interface#[method]font-kit.add-search-path")]
+                        unsafe extern "C" fn
+                        _post_return_method_font_kit_add_search_path(arg0 : * mut u8,) {
+                        unsafe { $($path_to_types)*::
+                        __post_return_method_font_kit_add_search_path::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0) } } #[unsafe
+                        (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.query")]
                         unsafe extern "C" fn export_method_font_kit_query(arg0 : * mut
                         u8, arg1 : i32, arg2 : i32, arg3 : i32, arg4 : i32, arg5 : i32,
                         arg6 : i32, arg7 : * mut u8, arg8 : usize, arg9 : * mut u8, arg10
-                        : usize,) -> * mut u8 { unsafe { $($path_to_types)*::
-                        _export_method_font_kit_query_cabi::<<$ty as $($path_to_types)*::
-                        Guest >::FontKit > (arg0, arg1, arg2, arg3, arg4, arg5, arg6,
-                        arg7, arg8, arg9, arg10) } } #[unsafe (export_name =
+                        : usize, arg11 : i32, arg12 : i32,) -> * mut u8 { unsafe {
+                        $($path_to_types)*:: _export_method_font_kit_query_cabi::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2, arg3,
+                        arg4, arg5, arg6, arg7, arg8, arg9, arg10, arg11, arg12) } }
+                        #[unsafe (export_name =
+                        "alibaba:fontkit/fontkit-interface#[method]font-kit.query-candidates")]
+                        unsafe extern "C" fn export_method_font_kit_query_candidates(arg0
+                        : * mut u8, arg1 : i32, arg2 : i32, arg3 : i32, arg4 : i32, arg5
+                        : i32, arg6 : i32, arg7 : * mut u8, arg8 : usize, arg9 : * mut
+                        u8, arg10 : usize, arg11 : i32, arg12 : i32,) -> * mut u8 {
+                        unsafe { $($path_to_types)*::
+                        _export_method_font_kit_query_candidates_cabi::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2, arg3,
+                        arg4, arg5, arg6, arg7, arg8, arg9, arg10, arg11, arg12) } }
+                        #[unsafe (export_name =
+                        "cabi_post_alibaba:fontkit/fontkit-interface#[method]font-kit.query-candidates")]
+                        unsafe extern "C" fn
+                        _post_return_method_font_kit_query_candidates(arg0 : * mut u8,) {
+                        unsafe { $($path_to_types)*::
+                        __post_return_method_font_kit_query_candidates::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0) } } #[unsafe
+                        (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.query-font-info")]
                         unsafe extern "C" fn export_method_font_kit_query_font_info(arg0
                         : * mut u8, arg1 : i32, arg2 : i32, arg3 : i32, arg4 : i32, arg5
                         : i32, arg6 : i32, arg7 : * mut u8, arg8 : usize, arg9 : * mut
-                        u8, arg10 : usize,) -> * mut u8 { unsafe { $($path_to_types)*::
+                        u8, arg10 : usize, arg11 : i32, arg12 : i32,) -> * mut u8 {
+                        unsafe { $($path_to_types)*::
                         _export_method_font_kit_query_font_info_cabi::<<$ty as
                         $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2, arg3,
-                        arg4, arg5, arg6, arg7, arg8, arg9, arg10) } } #[unsafe
-                        (export_name =
+                        arg4, arg5, arg6, arg7, arg8, arg9, arg10, arg11, arg12) } }
+                        #[unsafe (export_name =
                         "cabi_post_alibaba:fontkit/fontkit-interface#[method]font-kit.query-font-info")]
                         unsafe extern "C" fn
                         _post_return_method_font_kit_query_font_info(arg0 : * mut u8,) {
@@ -3390,11 +4942,12 @@ pub mod exports {
                         unsafe extern "C" fn export_method_font_kit_exact_match(arg0 : *
                         mut u8, arg1 : i32, arg2 : i32, arg3 : i32, arg4 : i32, arg5 :
                         i32, arg6 : i32, arg7 : * mut u8, arg8 : usize, arg9 : * mut u8,
-                        arg10 : usize,) -> * mut u8 { unsafe { $($path_to_types)*::
+                        arg10 : usize, arg11 : i32, arg12 : i32,) -> * mut u8 { unsafe {
+                        $($path_to_types)*::
                         _export_method_font_kit_exact_match_cabi::<<$ty as
                         $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2, arg3,
-                        arg4, arg5, arg6, arg7, arg8, arg9, arg10) } } #[unsafe
-                        (export_name =
+                        arg4, arg5, arg6, arg7, arg8, arg9, arg10, arg11, arg12) } }
+                        #[unsafe (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.fonts-info")]
                         unsafe extern "C" fn export_method_font_kit_fonts_info(arg0 : *
                         mut u8,) -> * mut u8 { unsafe { $($path_to_types)*::
@@ -3416,20 +4969,21 @@ pub mod exports {
                         unsafe extern "C" fn export_method_font_kit_remove(arg0 : * mut
                         u8, arg1 : i32, arg2 : i32, arg3 : i32, arg4 : i32, arg5 : i32,
                         arg6 : i32, arg7 : * mut u8, arg8 : usize, arg9 : * mut u8, arg10
-                        : usize,) { unsafe { $($path_to_types)*::
-                        _export_method_font_kit_remove_cabi::<<$ty as
-                        $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2, arg3,
-                        arg4, arg5, arg6, arg7, arg8, arg9, arg10) } } #[unsafe
-                        (export_name =
+                        : usize, arg11 : i32, arg12 : i32,) { unsafe {
+                        $($path_to_types)*:: _export_method_font_kit_remove_cabi::<<$ty
+                        as $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2,
+                        arg3, arg4, arg5, arg6, arg7, arg8, arg9, arg10, arg11, arg12) }
+                        } #[unsafe (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.measure")]
                         unsafe extern "C" fn export_method_font_kit_measure(arg0 : * mut
                         u8, arg1 : i32, arg2 : i32, arg3 : i32, arg4 : i32, arg5 : i32,
                         arg6 : i32, arg7 : * mut u8, arg8 : usize, arg9 : * mut u8, arg10
-                        : usize, arg11 : * mut u8, arg12 : usize,) -> * mut u8 { unsafe {
-                        $($path_to_types)*:: _export_method_font_kit_measure_cabi::<<$ty
-                        as $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2,
-                        arg3, arg4, arg5, arg6, arg7, arg8, arg9, arg10, arg11, arg12) }
-                        } #[unsafe (export_name =
+                        : usize, arg11 : i32, arg12 : i32, arg13 : * mut u8, arg14 :
+                        usize,) -> * mut u8 { unsafe { $($path_to_types)*::
+                        _export_method_font_kit_measure_cabi::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2, arg3,
+                        arg4, arg5, arg6, arg7, arg8, arg9, arg10, arg11, arg12, arg13,
+                        arg14) } } #[unsafe (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.write-data")]
                         unsafe extern "C" fn export_method_font_kit_write_data(arg0 : *
                         mut u8,) -> * mut u8 { unsafe { $($path_to_types)*::
@@ -3444,11 +4998,17 @@ pub mod exports {
                         (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.read-data")]
                         unsafe extern "C" fn export_method_font_kit_read_data(arg0 : *
-                        mut u8, arg1 : * mut u8, arg2 : usize,) { unsafe {
+                        mut u8, arg1 : * mut u8, arg2 : usize,) -> * mut u8 { unsafe {
                         $($path_to_types)*::
                         _export_method_font_kit_read_data_cabi::<<$ty as
                         $($path_to_types)*:: Guest >::FontKit > (arg0, arg1, arg2) } }
                         #[unsafe (export_name =
+                        "cabi_post_alibaba:fontkit/fontkit-interface#[method]font-kit.read-data")]
+                        unsafe extern "C" fn _post_return_method_font_kit_read_data(arg0
+                        : * mut u8,) { unsafe { $($path_to_types)*::
+                        __post_return_method_font_kit_read_data::<<$ty as
+                        $($path_to_types)*:: Guest >::FontKit > (arg0) } } #[unsafe
+                        (export_name =
                         "alibaba:fontkit/fontkit-interface#[method]font-kit.buffer-size")]
                         unsafe extern "C" fn export_method_font_kit_buffer_size(arg0 : *
                         mut u8,) -> i32 { unsafe { $($path_to_types)*::
@@ -3498,10 +5058,10 @@ pub mod exports {
                 struct _RetArea(
                     [::core::mem::MaybeUninit<
                         u8,
-                    >; 8 + 5 * ::core::mem::size_of::<*const u8>()],
+                    >; 16 + 5 * ::core::mem::size_of::<*const u8>()],
                 );
                 static mut _RET_AREA: _RetArea = _RetArea(
-                    [::core::mem::MaybeUninit::uninit(); 8
+                    [::core::mem::MaybeUninit::uninit(); 16
                         + 5 * ::core::mem::size_of::<*const u8>()],
                 );
             }
@@ -3747,66 +5307,75 @@ pub(crate) use __export_fontkit_impl as export;
 )]
 #[doc(hidden)]
 #[allow(clippy::octal_escapes)]
-pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; 2979] = *b"\
-\0asm\x0d\0\x01\0\0\x19\x16wit-component-encoding\x04\0\x07\xa5\x16\x01A\x02\x01\
-A\x05\x01B\x06\x01k{\x01k\x7f\x01o\x02sv\x01p\x02\x01r\x05\x06weight\0\x06italic\
-\x01\x07stretch\0\x06familys\x0avariations\x03\x04\0\x08font-key\x03\0\x04\x03\0\
-\x17alibaba:fontkit/commons\x05\0\x02\x03\0\0\x08font-key\x01B\x82\x01\x02\x03\x02\
-\x01\x01\x04\0\x08font-key\x03\0\0\x01r\x03\x02id{\x04names\x0blanguage-id{\x04\0\
-\x04name\x03\0\x02\x01p\x03\x01ks\x01r\x04\x0bstyle-names\x04\x05names\x04\x04pa\
-th\x05\x03key\x01\x04\0\x09font-info\x03\0\x06\x01r\x02\x08position|\x09thicknes\
-s|\x04\0\x0cline-metrics\x03\0\x08\x04\0\x0ctext-metrics\x03\x01\x04\0\x0cglyph-\
-bitmap\x03\x01\x04\0\x04font\x03\x01\x04\0\x08font-kit\x03\x01\x01i\x0a\x01@\x01\
-\x05values\0\x0e\x04\0\x19[constructor]text-metrics\x01\x0f\x01h\x0a\x01@\x01\x04\
-self\x10\0\x0e\x04\0\x1e[method]text-metrics.duplicate\x01\x11\x01@\x03\x04self\x10\
-\x09font-sizev\x0eletter-spacingv\0v\x04\0\x1a[method]text-metrics.width\x01\x12\
-\x01kv\x01@\x03\x04self\x10\x09font-sizev\x0bline-height\x13\0v\x04\0\x1b[method\
-]text-metrics.height\x01\x14\x01@\x02\x04self\x10\x09font-sizev\0v\x04\0\x1d[met\
-hod]text-metrics.ascender\x01\x15\x01@\x01\x04self\x10\0v\x04\0\x1d[method]text-\
-metrics.line-gap\x01\x16\x04\0\x1a[method]text-metrics.units\x01\x16\x01@\x03\x04\
-self\x10\x05starty\x05county\0\x0e\x04\0\x1a[method]text-metrics.slice\x01\x17\x01\
-@\x01\x04self\x10\0s\x04\0\x1a[method]text-metrics.value\x01\x18\x01@\x01\x04sel\
-f\x10\0\x7f\x04\0\x1b[method]text-metrics.is-rtl\x01\x19\x01@\x02\x04self\x10\x05\
-other\x0e\x01\0\x04\0\x1b[method]text-metrics.append\x01\x1a\x01@\x01\x04self\x10\
-\0y\x04\0\x1a[method]text-metrics.count\x01\x1b\x04\0\x20[method]text-metrics.ha\
-s-missing\x01\x19\x01@\x03\x04self\x10\x05other\x0e\x08fallback\x7f\x01\0\x04\0\x1c\
-[method]text-metrics.replace\x01\x1c\x01@\x04\x04self\x10\x09font-sizev\x0elette\
-r-spacingv\x05widthv\0\x0e\x04\0#[method]text-metrics.split-by-width\x01\x1d\x01\
-pt\x01@\x01\x04self\x10\0\x1e\x04\0\x1a[method]text-metrics.chars\x01\x1f\x01h\x0b\
-\x01@\x01\x04self\x20\0y\x04\0\x1a[method]glyph-bitmap.width\x01!\x04\0\x1b[meth\
-od]glyph-bitmap.height\x01!\x01p}\x01@\x01\x04self\x20\0\"\x04\0\x1b[method]glyp\
-h-bitmap.bitmap\x01#\x01@\x01\x04self\x20\0v\x04\0\x1a[method]glyph-bitmap.x-min\
-\x01$\x04\0\x1a[method]glyph-bitmap.y-max\x01$\x04\0\x1d[method]glyph-bitmap.str\
-oke-x\x01$\x04\0\x1d[method]glyph-bitmap.stroke-y\x01$\x01o\x02\"y\x01k%\x01@\x01\
-\x04self\x20\0&\x04\0\"[method]glyph-bitmap.stroke-bitmap\x01'\x04\0\x1f[method]\
-glyph-bitmap.advanced-x\x01$\x04\0\x1d[method]glyph-bitmap.ascender\x01$\x04\0\x1e\
-[method]glyph-bitmap.descender\x01$\x01h\x0c\x01@\x02\x04self(\x01ct\0\x7f\x04\0\
-\x16[method]font.has-glyph\x01)\x01@\x02\x04self(\x01ct\0\x05\x04\0\x1e[method]f\
-ont.glyph-path-string\x01*\x01@\x01\x04self(\0\"\x04\0\x13[method]font.buffer\x01\
-+\x01@\x01\x04self(\0s\x04\0\x11[method]font.path\x01,\x01@\x01\x04self(\0\x01\x04\
-\0\x10[method]font.key\x01-\x01j\x01\x0e\x01s\x01@\x02\x04self(\x04texts\0.\x04\0\
-\x14[method]font.measure\x01/\x01@\x01\x04self(\0|\x04\0\x15[method]font.ascende\
-r\x010\x04\0\x16[method]font.descender\x010\x01@\x01\x04self(\0{\x04\0\x19[metho\
-d]font.units-per-em\x011\x01i\x0b\x01k2\x01@\x04\x04self(\x01ct\x09font-sizev\x0c\
-stroke-widthv\03\x04\0\x13[method]font.bitmap\x014\x01k\x09\x01@\x01\x04self(\05\
-\x04\0\x1e[method]font.underline-metrics\x016\x01i\x0d\x01@\0\07\x04\0\x15[const\
-ructor]font-kit\x018\x01h\x0d\x01@\x03\x04self9\x05limity\x0acache-path\x05\x01\0\
-\x04\0\x1b[method]font-kit.set-config\x01:\x01@\x02\x04self9\x06buffer\"\x01\0\x04\
-\0%[method]font-kit.add-font-from-buffer\x01;\x01@\x02\x04self9\x04paths\x01\0\x04\
-\0\x20[method]font-kit.add-search-path\x01<\x01i\x0c\x01k=\x01@\x02\x04self9\x03\
-key\x01\0>\x04\0\x16[method]font-kit.query\x01?\x01p\x07\x01k\xc0\0\x01@\x02\x04\
-self9\x03key\x01\0\xc1\0\x04\0\x20[method]font-kit.query-font-info\x01B\x04\0\x1c\
-[method]font-kit.exact-match\x01?\x01@\x01\x04self9\0\xc0\0\x04\0\x1b[method]fon\
-t-kit.fonts-info\x01C\x01@\x01\x04self9\0y\x04\0\x14[method]font-kit.len\x01D\x01\
-@\x02\x04self9\x03key\x01\x01\0\x04\0\x17[method]font-kit.remove\x01E\x01k\x0e\x01\
-@\x03\x04self9\x03key\x01\x04texts\0\xc6\0\x04\0\x18[method]font-kit.measure\x01\
-G\x01@\x01\x04self9\0s\x04\0\x1b[method]font-kit.write-data\x01H\x01@\x02\x04sel\
-f9\x04datas\x01\0\x04\0\x1a[method]font-kit.read-data\x01I\x04\0\x1c[method]font\
--kit.buffer-size\x01D\x01@\x01\x05widths\0{\x04\0\x13str-width-to-number\x01J\x01\
-@\x01\x05width{\0s\x04\0\x13number-width-to-str\x01K\x04\0!alibaba:fontkit/fontk\
-it-interface\x05\x02\x04\0\x17alibaba:fontkit/fontkit\x04\0\x0b\x0d\x01\0\x07fon\
-tkit\x03\0\0\0G\x09producers\x01\x0cprocessed-by\x02\x0dwit-component\x070.227.1\
-\x10wit-bindgen-rust\x060.41.0";
+pub static __WIT_BINDGEN_COMPONENT_TYPE: [u8; 3421] = *b"\
+\0asm\x0d\0\x01\0\0\x19\x16wit-component-encoding\x04\0\x07\xdf\x19\x01A\x02\x01\
+A\x05\x01B\x07\x01k{\x01k\x7f\x01o\x02sv\x01p\x02\x01ky\x01r\x06\x06weight\0\x06\
+italic\x01\x07stretch\0\x06familys\x0avariations\x03\x06handle\x04\x04\0\x08font\
+-key\x03\0\x05\x03\0\x17alibaba:fontkit/commons\x05\0\x02\x03\0\0\x08font-key\x01\
+B\x95\x01\x02\x03\x02\x01\x01\x04\0\x08font-key\x03\0\0\x01r\x03\x02id{\x04names\
+\x0blanguage-id{\x04\0\x04name\x03\0\x02\x01ks\x01ky\x01r\x03\x07messages\x04pat\
+h\x04\x0aface-index\x05\x04\0\x05error\x03\0\x06\x01p\x03\x01r\x04\x0bstyle-name\
+s\x08\x05names\x08\x04path\x04\x03key\x01\x04\0\x09font-info\x03\0\x09\x01q\x04\x05\
+exact\0\0\x04name\0\0\x0dinstance-name\0\0\x07similar\x01y\0\x04\0\x0cfamily-mat\
+ch\x03\0\x0b\x01r\x06\x03key\x01\x0cfamily-match\x0c\x10stretch-distance{\x0eita\
+lic-matches\x7f\x0fweight-distance{\x10variations-match\x7f\x04\0\x0fquery-candi\
+date\x03\0\x0d\x01r\x02\x08position|\x09thickness|\x04\0\x0cline-metrics\x03\0\x0f\
+\x04\0\x0ctext-metrics\x03\x01\x04\0\x0cglyph-bitmap\x03\x01\x04\0\x04font\x03\x01\
+\x04\0\x08font-kit\x03\x01\x01i\x11\x01@\x01\x05values\0\x15\x04\0\x19[construct\
+or]text-metrics\x01\x16\x01h\x11\x01@\x01\x04self\x17\0\x15\x04\0\x1e[method]tex\
+t-metrics.duplicate\x01\x18\x01@\x03\x04self\x17\x09font-sizev\x0eletter-spacing\
+v\0v\x04\0\x1a[method]text-metrics.width\x01\x19\x01kv\x01@\x03\x04self\x17\x09f\
+ont-sizev\x0bline-height\x1a\0v\x04\0\x1b[method]text-metrics.height\x01\x1b\x01\
+@\x02\x04self\x17\x09font-sizev\0v\x04\0\x1d[method]text-metrics.ascender\x01\x1c\
+\x01@\x01\x04self\x17\0v\x04\0\x1d[method]text-metrics.line-gap\x01\x1d\x04\0\x1a\
+[method]text-metrics.units\x01\x1d\x01@\x03\x04self\x17\x05starty\x05county\0\x15\
+\x04\0\x1a[method]text-metrics.slice\x01\x1e\x01@\x01\x04self\x17\0s\x04\0\x1a[m\
+ethod]text-metrics.value\x01\x1f\x01@\x01\x04self\x17\0\x7f\x04\0\x1b[method]tex\
+t-metrics.is-rtl\x01\x20\x01@\x02\x04self\x17\x05other\x15\x01\0\x04\0\x1b[metho\
+d]text-metrics.append\x01!\x01@\x01\x04self\x17\0y\x04\0\x1a[method]text-metrics\
+.count\x01\"\x04\0\x20[method]text-metrics.has-missing\x01\x20\x01@\x03\x04self\x17\
+\x05other\x15\x08fallback\x7f\x01\0\x04\0\x1c[method]text-metrics.replace\x01#\x01\
+@\x04\x04self\x17\x09font-sizev\x0eletter-spacingv\x05widthv\0\x15\x04\0#[method\
+]text-metrics.split-by-width\x01$\x01pt\x01@\x01\x04self\x17\0%\x04\0\x1a[method\
+]text-metrics.chars\x01&\x01h\x12\x01@\x01\x04self'\0y\x04\0\x1a[method]glyph-bi\
+tmap.width\x01(\x04\0\x1b[method]glyph-bitmap.height\x01(\x01p}\x01@\x01\x04self\
+'\0)\x04\0\x1b[method]glyph-bitmap.bitmap\x01*\x01@\x01\x04self'\0v\x04\0\x1a[me\
+thod]glyph-bitmap.x-min\x01+\x04\0\x1a[method]glyph-bitmap.y-max\x01+\x04\0\x1d[\
+method]glyph-bitmap.stroke-x\x01+\x04\0\x1d[method]glyph-bitmap.stroke-y\x01+\x01\
+o\x02)y\x01k,\x01@\x01\x04self'\0-\x04\0\"[method]glyph-bitmap.stroke-bitmap\x01\
+.\x04\0\x1f[method]glyph-bitmap.advanced-x\x01+\x04\0\x1d[method]glyph-bitmap.as\
+cender\x01+\x04\0\x1e[method]glyph-bitmap.descender\x01+\x01h\x13\x01@\x02\x04se\
+lf/\x01ct\0\x7f\x04\0\x16[method]font.has-glyph\x010\x01@\x02\x04self/\x01ct\0\x04\
+\x04\0\x1e[method]font.glyph-path-string\x011\x01@\x01\x04self/\0)\x04\0\x13[met\
+hod]font.buffer\x012\x01j\x01)\x01\x07\x01@\x01\x04self/\03\x04\0\x1c[method]fon\
+t.instance-buffer\x014\x01@\x01\x04self/\0s\x04\0\x11[method]font.path\x015\x01@\
+\x01\x04self/\0\x01\x04\0\x10[method]font.key\x016\x01j\x01\x15\x01\x07\x01@\x02\
+\x04self/\x04texts\07\x04\0\x14[method]font.measure\x018\x01@\x01\x04self/\0|\x04\
+\0\x15[method]font.ascender\x019\x04\0\x16[method]font.descender\x019\x01@\x01\x04\
+self/\0{\x04\0\x19[method]font.units-per-em\x01:\x01i\x12\x01k;\x01@\x04\x04self\
+/\x01ct\x09font-sizev\x0cstroke-widthv\0<\x04\0\x13[method]font.bitmap\x01=\x01k\
+\x10\x01@\x01\x04self/\0>\x04\0\x1e[method]font.underline-metrics\x01?\x01i\x14\x01\
+@\0\0\xc0\0\x04\0\x15[constructor]font-kit\x01A\x01h\x14\x01j\0\x01\x07\x01@\x03\
+\x04self\xc2\0\x05limity\x0acache-path\x04\0\xc3\0\x04\0\x1b[method]font-kit.set\
+-config\x01D\x01@\x02\x04self\xc2\0\x06policys\0\xc3\0\x04\0%[method]font-kit.se\
+t-collision-policy\x01E\x01p\x01\x01j\x01\xc6\0\x01\x07\x01@\x02\x04self\xc2\0\x06\
+buffer)\0\xc7\0\x04\0%[method]font-kit.add-font-from-buffer\x01H\x01@\x02\x04sel\
+f\xc2\0\x04paths\0\xc3\0\x04\0\x20[method]font-kit.add-search-path\x01I\x01i\x13\
+\x01k\xca\0\x01@\x02\x04self\xc2\0\x03key\x01\0\xcb\0\x04\0\x16[method]font-kit.\
+query\x01L\x01p\x0e\x01@\x02\x04self\xc2\0\x03key\x01\0\xcd\0\x04\0![method]font\
+-kit.query-candidates\x01N\x01p\x0a\x01k\xcf\0\x01@\x02\x04self\xc2\0\x03key\x01\
+\0\xd0\0\x04\0\x20[method]font-kit.query-font-info\x01Q\x04\0\x1c[method]font-ki\
+t.exact-match\x01L\x01@\x01\x04self\xc2\0\0\xcf\0\x04\0\x1b[method]font-kit.font\
+s-info\x01R\x01@\x01\x04self\xc2\0\0y\x04\0\x14[method]font-kit.len\x01S\x01@\x02\
+\x04self\xc2\0\x03key\x01\x01\0\x04\0\x17[method]font-kit.remove\x01T\x01k\x15\x01\
+@\x03\x04self\xc2\0\x03key\x01\x04texts\0\xd5\0\x04\0\x18[method]font-kit.measur\
+e\x01V\x01j\x01s\x01\x07\x01@\x01\x04self\xc2\0\0\xd7\0\x04\0\x1b[method]font-ki\
+t.write-data\x01X\x01@\x02\x04self\xc2\0\x04datas\0\xc3\0\x04\0\x1a[method]font-\
+kit.read-data\x01Y\x04\0\x1c[method]font-kit.buffer-size\x01S\x01@\x01\x05widths\
+\0{\x04\0\x13str-width-to-number\x01Z\x01@\x01\x05width{\0s\x04\0\x13number-widt\
+h-to-str\x01[\x04\0!alibaba:fontkit/fontkit-interface\x05\x02\x04\0\x17alibaba:f\
+ontkit/fontkit\x04\0\x0b\x0d\x01\0\x07fontkit\x03\0\0\0G\x09producers\x01\x0cpro\
+cessed-by\x02\x0dwit-component\x070.227.1\x10wit-bindgen-rust\x060.41.0";
 #[inline(never)]
 #[doc(hidden)]
 pub fn __link_custom_section_describing_imports() {
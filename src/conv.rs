@@ -1,2 +1,72 @@
 #[cfg(feature = "parse")]
+mod cff;
+#[cfg(feature = "parse")]
+mod glyf;
+#[cfg(feature = "parse")]
+pub mod instance;
+#[cfg(feature = "parse")]
+pub mod sanitize;
+#[cfg(feature = "parse")]
+mod sfnt;
+#[cfg(feature = "parse")]
+pub mod subset;
+#[cfg(feature = "parse")]
 pub mod woff;
+#[cfg(feature = "parse")]
+pub mod woff2;
+
+/// Formats fonts can be written in
+#[cfg(feature = "parse")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontFormat {
+    /// A bare TrueType or OpenType font
+    #[default]
+    Sfnt,
+    Woff,
+    /// Requires the `encode` feature
+    #[cfg(feature = "encode")]
+    Woff2,
+}
+
+/// Optional blocks stored after the tables of WOFF and WOFF2 files. Bare
+/// fonts don't keep them
+#[cfg(feature = "parse")]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WoffBlocks {
+    /// The extended metadata XML, stored compressed
+    pub metadata: Option<String>,
+    /// Private data, stored as is
+    pub private: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "parse")]
+impl FontFormat {
+    /// Wrap a TrueType or OpenType font into this format
+    pub(crate) fn encode(
+        self,
+        font: Vec<u8>,
+        blocks: &WoffBlocks,
+    ) -> Result<Vec<u8>, std::io::Error> {
+        match self {
+            FontFormat::Sfnt => Ok(font),
+            FontFormat::Woff => woff::convert_otf_to_woff(&font, blocks),
+            #[cfg(feature = "encode")]
+            FontFormat::Woff2 => woff2::convert_otf_to_woff2(&font, blocks),
+        }
+    }
+}
+
+#[cfg(feature = "parse")]
+impl crate::StaticFace {
+    /// Write this face as a standalone font in `format`. Faces of
+    /// collections are extracted, and variable fonts keep their variations
+    pub fn encode(&self, format: FontFormat, blocks: &WoffBlocks) -> Result<Vec<u8>, crate::Error> {
+        let sfnt = sfnt::Sfnt::parse(self.borrow_buffer(), self.collection_index())?;
+        let tables = sfnt
+            .tables
+            .iter()
+            .map(|(tag, data)| (*tag, data.to_vec()))
+            .collect();
+        Ok(format.encode(sfnt::write_sfnt(sfnt.flavor, tables), blocks)?)
+    }
+}
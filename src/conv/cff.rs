@@ -0,0 +1,511 @@
+//! Subsetting of CFF outlines.
+//!
+//! Glyphs are renumbered, while subroutines keep their numbers so that no
+//! charstring has to be rewritten: the ones no kept glyph calls are emptied.
+//!
+//! See the CFF spec: `https://adobe-type-tools.github.io/font-tech-notes/pdfs/5176.CFF.pdf`
+//! and the Type 2 charstring spec: `https://adobe-type-tools.github.io/font-tech-notes/pdfs/5177.Type2.pdf`
+
+use std::collections::HashSet;
+use std::io::Error;
+
+use super::sfnt::{invalid, Reader};
+
+const CHARSET: u16 = 15;
+const ENCODING: u16 = 16;
+const CHAR_STRINGS: u16 = 17;
+const PRIVATE: u16 = 18;
+const SUBRS: u16 = 19;
+const ROS: u16 = 0x0C1E;
+const FD_ARRAY: u16 = 0x0C24;
+const FD_SELECT: u16 = 0x0C25;
+
+/// Deepest subroutine nesting allowed, "Appendix B Type 2 Charstring
+/// Implementation Limits"
+const MAX_SUBR_DEPTH: usize = 10;
+
+/// Body of the subroutines no kept glyph calls
+const EMPTY_SUBR: &[u8] = &[11];
+
+/// "INDEX Data"
+fn read_index<'a>(reader: &mut Reader<'a>) -> Result<Vec<&'a [u8]>, Error> {
+    let count = reader.u16()? as usize;
+    if count == 0 {
+        return Ok(vec![]);
+    }
+    let off_size = reader.u8()? as usize;
+    if !(1..=4).contains(&off_size) {
+        return Err(invalid("CFF INDEX offset size is invalid"));
+    }
+    let offsets = (0..=count)
+        .map(|_| {
+            let bytes = reader.bytes(off_size)?;
+            Ok(bytes.iter().fold(0, |value, b| (value << 8) | *b as usize))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let data = reader.bytes(offsets[count].saturating_sub(1))?;
+    offsets
+        .windows(2)
+        .map(|pair| {
+            data.get(pair[0].wrapping_sub(1)..pair[1].wrapping_sub(1))
+                .ok_or_else(|| invalid("CFF INDEX offsets are out of order"))
+        })
+        .collect()
+}
+
+fn write_index<T: AsRef<[u8]>>(items: &[T], cff: &mut Vec<u8>) {
+    cff.extend_from_slice(&(items.len() as u16).to_be_bytes());
+    if items.is_empty() {
+        return;
+    }
+    let end = 1 + items.iter().map(|i| i.as_ref().len()).sum::<usize>();
+    let off_size = (1..4).find(|size| end < 1 << (8 * size)).unwrap_or(4);
+    cff.push(off_size as u8);
+    let mut offset = 1;
+    for item in items.iter().map(AsRef::as_ref).chain([&[][..]]) {
+        cff.extend_from_slice(&(offset as u32).to_be_bytes()[4 - off_size..]);
+        offset += item.len();
+    }
+    for item in items {
+        cff.extend_from_slice(item.as_ref());
+    }
+}
+
+/// Operators of a DICT, with their raw operands. Escaped operators are
+/// stored as `0x0C00 | b1`
+struct Dict<'a>(Vec<(u16, &'a [u8])>);
+
+impl<'a> Dict<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(data);
+        let mut entries = vec![];
+        let mut start = 0;
+        while reader.pos < data.len() {
+            let pos = reader.pos;
+            match reader.u8()? {
+                0..=21 => {
+                    let op = match data[pos] {
+                        12 => 0x0C00 | reader.u8()? as u16,
+                        op => op as u16,
+                    };
+                    entries.push((op, &data[start..pos]));
+                    start = reader.pos;
+                }
+                28 => _ = reader.bytes(2)?,
+                29 => _ = reader.bytes(4)?,
+                30 => while reader.u8()? & 0x0F != 0x0F {},
+                247..=254 => _ = reader.u8()?,
+                _ => {}
+            }
+        }
+        Ok(Dict(entries))
+    }
+
+    fn operands(&self, op: u16) -> Option<Vec<i32>> {
+        let (_, operands) = self.0.iter().find(|(o, _)| *o == op)?;
+        let mut reader = Reader::new(operands);
+        let mut values = vec![];
+        while reader.pos < operands.len() {
+            values.push(match reader.u8().ok()? {
+                28 => reader.i16().ok()? as i32,
+                29 => reader.u32().ok()? as i32,
+                b0 @ 32..=246 => b0 as i32 - 139,
+                b0 @ 247..=250 => (b0 as i32 - 247) * 256 + reader.u8().ok()? as i32 + 108,
+                b0 @ 251..=254 => -(b0 as i32 - 251) * 256 - reader.u8().ok()? as i32 - 108,
+                _ => return None,
+            });
+        }
+        Some(values)
+    }
+
+    fn offset(&self, op: u16) -> Option<usize> {
+        self.operands(op)?.last().map(|o| *o as usize)
+    }
+
+    /// Write the DICT, giving the operators in `values` new integer operands,
+    /// always stored in 5 bytes so that the size of the DICT doesn't depend
+    /// on them
+    fn write(&self, values: &[(u16, &[usize])], dict: &mut Vec<u8>) {
+        for (op, operands) in self.0.iter() {
+            match values.iter().find(|(o, _)| o == op) {
+                Some((_, values)) => write_integers(values, dict),
+                None => dict.extend_from_slice(operands),
+            }
+            write_operator(*op, dict);
+        }
+        for (op, values) in values {
+            if self.0.iter().all(|(o, _)| o != op) {
+                write_integers(values, dict);
+                write_operator(*op, dict);
+            }
+        }
+    }
+}
+
+fn write_integers(values: &[usize], dict: &mut Vec<u8>) {
+    for value in values {
+        dict.push(29);
+        dict.extend_from_slice(&(*value as u32).to_be_bytes());
+    }
+}
+
+fn write_operator(op: u16, dict: &mut Vec<u8>) {
+    if op >> 8 == 12 {
+        dict.push(12);
+    }
+    dict.push(op as u8);
+}
+
+/// A Private DICT and the local subroutines it points to
+struct Private<'a> {
+    dict: Dict<'a>,
+    subrs: Vec<&'a [u8]>,
+}
+
+impl<'a> Private<'a> {
+    fn parse(cff: &'a [u8], dict: &Dict) -> Result<Self, Error> {
+        let (size, offset) = match dict.operands(PRIVATE).as_deref() {
+            Some([size, offset]) => (*size as usize, *offset as usize),
+            _ => return Err(invalid("CFF Private DICT is missing")),
+        };
+        let data = Reader::at(cff, offset).bytes(size)?;
+        let dict = Dict::parse(data)?;
+        let subrs = match dict.offset(SUBRS) {
+            Some(subrs) => read_index(&mut Reader::at(cff, offset + subrs))?,
+            None => vec![],
+        };
+        Ok(Private { dict, subrs })
+    }
+
+    /// Write the DICT followed by the subroutines in `used`, returning the
+    /// data and the length of the DICT
+    fn write(&self, used: &HashSet<usize>) -> (Vec<u8>, usize) {
+        let mut dict = vec![];
+        if self.dict.offset(SUBRS).is_none() {
+            self.dict.write(&[], &mut dict);
+            let length = dict.len();
+            return (dict, length);
+        }
+        self.dict.write(&[(SUBRS, &[0])], &mut dict);
+        let length = dict.len();
+        dict.clear();
+        self.dict.write(&[(SUBRS, &[length])], &mut dict);
+        write_index(&kept_subrs(&self.subrs, used), &mut dict);
+        (dict, length)
+    }
+}
+
+fn kept_subrs<'a>(subrs: &[&'a [u8]], used: &HashSet<usize>) -> Vec<&'a [u8]> {
+    subrs
+        .iter()
+        .enumerate()
+        .map(|(i, subr)| if used.contains(&i) { *subr } else { EMPTY_SUBR })
+        .collect()
+}
+
+/// "Subroutine Operators", the bias added to subroutine numbers
+fn bias(count: usize) -> i32 {
+    match count {
+        0..1240 => 107,
+        1240..33900 => 1131,
+        _ => 32768,
+    }
+}
+
+/// Walks charstrings to find the subroutines they call
+struct SubrScanner<'a> {
+    global: &'a [&'a [u8]],
+    local: &'a [&'a [u8]],
+    used_global: &'a mut HashSet<usize>,
+    used_local: &'a mut HashSet<usize>,
+    stack: Vec<i32>,
+    stems: usize,
+}
+
+impl SubrScanner<'_> {
+    /// Scan a charstring, returning whether it ended the glyph
+    fn scan(&mut self, charstring: &[u8], depth: usize) -> Result<bool, Error> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err(invalid("CFF subroutines are nested too deeply"));
+        }
+        let mut reader = Reader::new(charstring);
+        while reader.pos < charstring.len() {
+            match reader.u8()? {
+                28 => self.stack.push(reader.i16()? as i32),
+                b0 @ 32..=246 => self.stack.push(b0 as i32 - 139),
+                b0 @ 247..=250 => self
+                    .stack
+                    .push((b0 as i32 - 247) * 256 + reader.u8()? as i32 + 108),
+                b0 @ 251..=254 => self
+                    .stack
+                    .push(-(b0 as i32 - 251) * 256 - reader.u8()? as i32 - 108),
+                255 => self.stack.push(reader.u32()? as i32 >> 16),
+                // hstem, vstem, hstemhm, vstemhm
+                1 | 3 | 18 | 23 => {
+                    self.stems += self.stack.len() / 2;
+                    self.stack.clear();
+                }
+                // hintmask, cntrmask, after implicit vstem operands
+                19 | 20 => {
+                    self.stems += self.stack.len() / 2;
+                    self.stack.clear();
+                    reader.bytes(self.stems.div_ceil(8))?;
+                }
+                op @ (10 | 29) => {
+                    let (subrs, used) = if op == 10 {
+                        (self.local, &mut *self.used_local)
+                    } else {
+                        (self.global, &mut *self.used_global)
+                    };
+                    let index = self.stack.pop().unwrap_or_default() + bias(subrs.len());
+                    let subr = usize::try_from(index)
+                        .ok()
+                        .and_then(|i| Some((i, *subrs.get(i)?)))
+                        .ok_or_else(|| invalid("CFF subroutine number is out of range"))?;
+                    used.insert(subr.0);
+                    if self.scan(subr.1, depth + 1)? {
+                        return Ok(true);
+                    }
+                }
+                11 => return Ok(false),
+                14 => return Ok(true),
+                12 => {
+                    reader.u8()?;
+                    self.stack.clear();
+                }
+                _ => self.stack.clear(),
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// "Charsets", the SID or CID of every glyph but `.notdef`
+fn read_charset(cff: &[u8], offset: usize, num_glyphs: usize) -> Result<Vec<u16>, Error> {
+    match offset {
+        // ISOAdobe
+        0 => return Ok((1..num_glyphs as u16).collect()),
+        1 | 2 => return Err(invalid("Expert CFF charsets aren't supported")),
+        _ => {}
+    }
+    let mut reader = Reader::at(cff, offset);
+    let mut ids = Vec::with_capacity(num_glyphs);
+    match reader.u8()? {
+        0 => {
+            for _ in 1..num_glyphs {
+                ids.push(reader.u16()?);
+            }
+        }
+        format @ (1 | 2) => {
+            while ids.len() + 1 < num_glyphs {
+                let first = reader.u16()?;
+                let left = if format == 1 {
+                    reader.u8()? as u16
+                } else {
+                    reader.u16()?
+                };
+                ids.extend((0..=left).map(|i| first.wrapping_add(i)));
+            }
+            ids.truncate(num_glyphs - 1);
+        }
+        _ => return Err(invalid("CFF charset format is invalid")),
+    }
+    Ok(ids)
+}
+
+/// "FDSelect", the Font DICT of every glyph
+fn read_fd_select(cff: &[u8], offset: usize, num_glyphs: usize) -> Result<Vec<u8>, Error> {
+    let mut reader = Reader::at(cff, offset);
+    match reader.u8()? {
+        0 => Ok(reader.bytes(num_glyphs)?.to_vec()),
+        3 => {
+            let ranges = reader.u16()?;
+            let mut fds = Vec::with_capacity(num_glyphs);
+            let mut first = reader.u16()? as usize;
+            for _ in 0..ranges {
+                let fd = reader.u8()?;
+                let next = reader.u16()? as usize;
+                fds.resize(next.max(first).min(num_glyphs), fd);
+                first = next;
+            }
+            if fds.len() < num_glyphs {
+                return Err(invalid("CFF FDSelect doesn't cover every glyph"));
+            }
+            Ok(fds)
+        }
+        _ => Err(invalid("CFF FDSelect format is invalid")),
+    }
+}
+
+/// Write a CFF table holding the glyphs of `glyphs`, in this order
+pub(super) fn subset_cff(cff: &[u8], glyphs: &[u16]) -> Result<Vec<u8>, Error> {
+    let mut reader = Reader::at(cff, 2);
+    let header_size = reader.u8()? as usize;
+    let mut reader = Reader::at(cff, header_size);
+    let names = read_index(&mut reader)?;
+    let top_dicts = read_index(&mut reader)?;
+    let strings_start = reader.pos;
+    read_index(&mut reader)?;
+    let strings = &cff[strings_start..reader.pos];
+    let global_subrs = read_index(&mut reader)?;
+
+    let top = Dict::parse(
+        top_dicts
+            .first()
+            .ok_or_else(|| invalid("CFF has no Top DICT"))?,
+    )?;
+    let char_strings = top
+        .offset(CHAR_STRINGS)
+        .ok_or_else(|| invalid("CFF has no CharStrings"))?;
+    let char_strings = read_index(&mut Reader::at(cff, char_strings))?;
+    let num_glyphs = char_strings.len();
+    if glyphs.iter().any(|g| *g as usize >= num_glyphs) {
+        return Err(invalid("Glyph ID out of range"));
+    }
+    let charset = read_charset(cff, top.offset(CHARSET).unwrap_or(0), num_glyphs)?;
+
+    // CID-keyed fonts pick a Font DICT for every glyph
+    let cid = top.operands(ROS).is_some();
+    let (font_dicts, fd_select) = if cid {
+        let fd_array = top
+            .offset(FD_ARRAY)
+            .ok_or_else(|| invalid("CID-keyed CFF has no FDArray"))?;
+        let fd_select = top
+            .offset(FD_SELECT)
+            .ok_or_else(|| invalid("CID-keyed CFF has no FDSelect"))?;
+        let font_dicts = read_index(&mut Reader::at(cff, fd_array))?
+            .into_iter()
+            .map(Dict::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        (font_dicts, read_fd_select(cff, fd_select, num_glyphs)?)
+    } else {
+        (vec![], vec![0; num_glyphs])
+    };
+    let privates = if cid {
+        font_dicts
+            .iter()
+            .map(|dict| Private::parse(cff, dict))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![Private::parse(cff, &top)?]
+    };
+
+    let mut used_global = HashSet::new();
+    let mut used_local = vec![HashSet::new(); privates.len()];
+    for glyph in glyphs {
+        let fd = fd_select[*glyph as usize] as usize;
+        let private = privates
+            .get(fd)
+            .ok_or_else(|| invalid("CFF FDSelect index is out of range"))?;
+        SubrScanner {
+            global: &global_subrs,
+            local: &private.subrs,
+            used_global: &mut used_global,
+            used_local: &mut used_local[fd],
+            stack: vec![],
+            stems: 0,
+        }
+        .scan(char_strings[*glyph as usize], 0)?;
+    }
+
+    let mut glyph_ids = vec![];
+    for glyph in glyphs.iter().filter(|g| **g != 0) {
+        glyph_ids.extend_from_slice(&charset[*glyph as usize - 1].to_be_bytes());
+    }
+    let mut charset = vec![0];
+    charset.extend_from_slice(&glyph_ids);
+    let fd_select = if cid {
+        let mut select = vec![0];
+        select.extend(glyphs.iter().map(|g| fd_select[*g as usize]));
+        select
+    } else {
+        vec![]
+    };
+    let mut char_strings_index = vec![];
+    write_index(
+        &glyphs
+            .iter()
+            .map(|g| char_strings[*g as usize])
+            .collect::<Vec<_>>(),
+        &mut char_strings_index,
+    );
+    let privates = privates
+        .iter()
+        .zip(used_local.iter())
+        .map(|(private, used)| private.write(used))
+        .collect::<Vec<_>>();
+    let mut global_index = vec![];
+    write_index(&kept_subrs(&global_subrs, &used_global), &mut global_index);
+
+    // DICTs store offsets in 5 bytes, so their size is known before the
+    // offsets are
+    let top = Dict(
+        top.0
+            .into_iter()
+            .filter(|(op, _)| *op != ENCODING)
+            .collect(),
+    );
+    let write_top = |charset: usize, char_strings: usize, fd: [usize; 2], private: [usize; 2]| {
+        let mut dict = vec![];
+        let (charset, char_strings) = ([charset], [char_strings]);
+        let mut offsets = vec![(CHARSET, &charset[..]), (CHAR_STRINGS, &char_strings[..])];
+        if cid {
+            offsets.extend([(FD_ARRAY, &fd[..1]), (FD_SELECT, &fd[1..])]);
+        } else {
+            offsets.push((PRIVATE, &private[..]));
+        }
+        top.write(&offsets, &mut dict);
+        let mut index = vec![];
+        write_index(&[dict], &mut index);
+        index
+    };
+    let write_fd_array = |private_offsets: &[usize]| {
+        let dicts = font_dicts
+            .iter()
+            .zip(privates.iter().zip(private_offsets))
+            .map(|(dict, ((_, length), offset))| {
+                let mut data = vec![];
+                dict.write(&[(PRIVATE, &[*length, *offset])], &mut data);
+                data
+            })
+            .collect::<Vec<_>>();
+        let mut index = vec![];
+        if cid {
+            write_index(&dicts, &mut index);
+        }
+        index
+    };
+
+    let mut output = cff[..header_size].to_vec();
+    write_index(&names, &mut output);
+    let charset_offset =
+        output.len() + write_top(0, 0, [0; 2], [0; 2]).len() + strings.len() + global_index.len();
+    let fd_select_offset = charset_offset + charset.len();
+    let char_strings_offset = fd_select_offset + fd_select.len();
+    let fd_array_offset = char_strings_offset + char_strings_index.len();
+    let mut private_offsets = vec![];
+    let mut offset = fd_array_offset + write_fd_array(&vec![0; privates.len()]).len();
+    for (private, _) in privates.iter() {
+        private_offsets.push(offset);
+        offset += private.len();
+    }
+    output.extend_from_slice(&write_top(
+        charset_offset,
+        char_strings_offset,
+        [fd_array_offset, fd_select_offset],
+        [
+            privates.first().map_or(0, |(_, length)| *length),
+            private_offsets.first().copied().unwrap_or(0),
+        ],
+    ));
+    output.extend_from_slice(strings);
+    output.extend_from_slice(&global_index);
+    output.extend_from_slice(&charset);
+    output.extend_from_slice(&fd_select);
+    output.extend_from_slice(&char_strings_index);
+    output.extend_from_slice(&write_fd_array(&private_offsets));
+    for (private, _) in privates {
+        output.extend_from_slice(&private);
+    }
+    Ok(output)
+}
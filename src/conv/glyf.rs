@@ -0,0 +1,334 @@
+//! Parsing and writing of TrueType glyphs, shared by the converters.
+//!
+//! See the glyf spec: `https://learn.microsoft.com/en-us/typography/opentype/spec/glyf`
+
+use std::io::Error;
+use std::ops::Range;
+
+use super::sfnt::{invalid, Reader};
+
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+const OVERLAP_SIMPLE: u8 = 0x40;
+
+/// A glyph of the glyf table
+pub(super) enum Glyph<'a> {
+    Empty,
+    Simple(SimpleGlyph<'a>),
+    Composite(CompositeGlyph<'a>),
+}
+
+pub(super) struct SimpleGlyph<'a> {
+    pub(super) end_points: Vec<u16>,
+    pub(super) instructions: &'a [u8],
+    /// Coordinates of every point, and whether it is on the curve
+    pub(super) points: Vec<(i32, i32, bool)>,
+    pub(super) overlap: bool,
+}
+
+pub(super) struct CompositeGlyph<'a> {
+    pub(super) bbox: [i16; 4],
+    pub(super) components: Vec<Component<'a>>,
+    pub(super) instructions: Option<&'a [u8]>,
+}
+
+pub(super) struct Component<'a> {
+    pub(super) flags: u16,
+    pub(super) glyph: u16,
+    /// Offset of the component, or the points it is aligned by when
+    /// [`Component::is_offset`] is false
+    pub(super) args: (i32, i32),
+    /// Raw scale or 2x2 transform of the component
+    pub(super) transform: &'a [u8],
+}
+
+impl Component<'_> {
+    pub(super) fn is_offset(&self) -> bool {
+        self.flags & ARGS_ARE_XY_VALUES != 0
+    }
+}
+
+impl<'a> Glyph<'a> {
+    pub(super) fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if data.is_empty() {
+            return Ok(Glyph::Empty);
+        }
+        let mut reader = Reader::new(data);
+        let contours = reader.i16()?;
+        let bbox = [reader.i16()?, reader.i16()?, reader.i16()?, reader.i16()?];
+        if contours >= 0 {
+            SimpleGlyph::parse(&mut reader, contours as usize).map(Glyph::Simple)
+        } else {
+            CompositeGlyph::parse(&mut reader, bbox).map(Glyph::Composite)
+        }
+    }
+}
+
+impl<'a> SimpleGlyph<'a> {
+    fn parse(reader: &mut Reader<'a>, contours: usize) -> Result<Self, Error> {
+        let end_points = (0..contours)
+            .map(|_| reader.u16())
+            .collect::<Result<Vec<_>, _>>()?;
+        let total = end_points.last().map_or(0, |last| *last as usize + 1);
+        let instruction_length = reader.u16()? as usize;
+        let instructions = reader.bytes(instruction_length)?;
+        let mut flags = Vec::with_capacity(total);
+        while flags.len() < total {
+            let flag = reader.u8()?;
+            flags.push(flag);
+            if flag & 0x08 != 0 {
+                for _ in 0..reader.u8()? {
+                    flags.push(flag);
+                }
+            }
+        }
+        flags.truncate(total);
+        let mut coordinates = [vec![], vec![]];
+        for (axis, (short, same)) in [(0x02, 0x10), (0x04, 0x20)].into_iter().enumerate() {
+            let mut value = 0_i32;
+            for flag in flags.iter() {
+                value += if flag & short != 0 {
+                    let delta = reader.u8()? as i32;
+                    if flag & same != 0 {
+                        delta
+                    } else {
+                        -delta
+                    }
+                } else if flag & same != 0 {
+                    0
+                } else {
+                    reader.i16()? as i32
+                };
+                coordinates[axis].push(value);
+            }
+        }
+        let [xs, ys] = coordinates;
+        let points = xs
+            .into_iter()
+            .zip(ys)
+            .zip(flags.iter())
+            .map(|((x, y), flag)| (x, y, flag & 0x01 != 0))
+            .collect();
+        Ok(SimpleGlyph {
+            end_points,
+            instructions,
+            points,
+            overlap: flags.first().is_some_and(|f| f & OVERLAP_SIMPLE != 0),
+        })
+    }
+
+    /// Ranges of the points of every contour
+    pub(super) fn contours(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        let mut start = 0;
+        self.end_points.iter().map(move |end| {
+            let range = start..*end as usize + 1;
+            start = range.end;
+            range
+        })
+    }
+
+    pub(super) fn bbox(&self) -> [i16; 4] {
+        let xs = self.points.iter().map(|p| p.0);
+        let ys = self.points.iter().map(|p| p.1);
+        [
+            xs.clone().min().unwrap_or(0) as i16,
+            ys.clone().min().unwrap_or(0) as i16,
+            xs.max().unwrap_or(0) as i16,
+            ys.max().unwrap_or(0) as i16,
+        ]
+    }
+
+    pub(super) fn write(&self, glyf: &mut Vec<u8>) {
+        glyf.extend_from_slice(&(self.end_points.len() as i16).to_be_bytes());
+        for value in self.bbox() {
+            glyf.extend_from_slice(&value.to_be_bytes());
+        }
+        for end in self.end_points.iter() {
+            glyf.extend_from_slice(&end.to_be_bytes());
+        }
+        glyf.extend_from_slice(&(self.instructions.len() as u16).to_be_bytes());
+        glyf.extend_from_slice(self.instructions);
+        encode_points(&self.points, self.overlap, glyf);
+    }
+}
+
+impl<'a> CompositeGlyph<'a> {
+    fn parse(reader: &mut Reader<'a>, bbox: [i16; 4]) -> Result<Self, Error> {
+        let mut components = vec![];
+        let mut has_instructions = false;
+        loop {
+            let flags = reader.u16()?;
+            let glyph = reader.u16()?;
+            let args = match (
+                flags & ARG_1_AND_2_ARE_WORDS != 0,
+                flags & ARGS_ARE_XY_VALUES != 0,
+            ) {
+                (true, true) => (reader.i16()? as i32, reader.i16()? as i32),
+                (true, false) => (reader.u16()? as i32, reader.u16()? as i32),
+                (false, true) => (reader.i8()? as i32, reader.i8()? as i32),
+                (false, false) => (reader.u8()? as i32, reader.u8()? as i32),
+            };
+            let transform = reader.bytes(if flags & WE_HAVE_A_SCALE != 0 {
+                2
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                4
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                8
+            } else {
+                0
+            })?;
+            has_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+            components.push(Component {
+                flags,
+                glyph,
+                args,
+                transform,
+            });
+            if flags & MORE_COMPONENTS == 0 {
+                break;
+            }
+        }
+        let instructions = if has_instructions {
+            let length = reader.u16()? as usize;
+            Some(reader.bytes(length)?)
+        } else {
+            None
+        };
+        Ok(CompositeGlyph {
+            bbox,
+            components,
+            instructions,
+        })
+    }
+
+    /// Write the glyph, with arguments stored as words only where they don't
+    /// fit in bytes
+    pub(super) fn write(&self, glyf: &mut Vec<u8>) {
+        glyf.extend_from_slice(&(-1_i16).to_be_bytes());
+        for value in self.bbox {
+            glyf.extend_from_slice(&value.to_be_bytes());
+        }
+        for (i, component) in self.components.iter().enumerate() {
+            let (arg1, arg2) = component.args;
+            let byte_range = if component.is_offset() {
+                -128..=127
+            } else {
+                0..=255
+            };
+            let words = !byte_range.contains(&arg1) || !byte_range.contains(&arg2);
+            let mut flags =
+                component.flags & !(ARG_1_AND_2_ARE_WORDS | MORE_COMPONENTS | WE_HAVE_INSTRUCTIONS);
+            if words {
+                flags |= ARG_1_AND_2_ARE_WORDS;
+            }
+            if i + 1 < self.components.len() {
+                flags |= MORE_COMPONENTS;
+            }
+            // FreeType reads the flag from the last component
+            if self.instructions.is_some() && i + 1 == self.components.len() {
+                flags |= WE_HAVE_INSTRUCTIONS;
+            }
+            glyf.extend_from_slice(&flags.to_be_bytes());
+            glyf.extend_from_slice(&component.glyph.to_be_bytes());
+            for arg in [arg1, arg2] {
+                if words {
+                    glyf.extend_from_slice(&(arg as i16).to_be_bytes());
+                } else {
+                    glyf.push(arg as u8);
+                }
+            }
+            glyf.extend_from_slice(component.transform);
+        }
+        if let Some(instructions) = self.instructions {
+            glyf.extend_from_slice(&(instructions.len() as u16).to_be_bytes());
+            glyf.extend_from_slice(instructions);
+        }
+    }
+}
+
+/// Write the flags and coordinates of a simple glyph, without repeated flags
+pub(super) fn encode_points(points: &[(i32, i32, bool)], overlap: bool, glyf: &mut Vec<u8>) {
+    let mut flags = vec![];
+    let mut xs = vec![];
+    let mut ys = vec![];
+    let (mut x, mut y) = (0, 0);
+    for (i, (px, py, on_curve)) in points.iter().enumerate() {
+        let mut flag = *on_curve as u8;
+        if i == 0 && overlap {
+            flag |= OVERLAP_SIMPLE;
+        }
+        for (delta, short, same, out) in
+            [(px - x, 0x02, 0x10, &mut xs), (py - y, 0x04, 0x20, &mut ys)]
+        {
+            if delta == 0 {
+                flag |= same;
+            } else if delta.abs() < 256 {
+                flag |= short;
+                if delta > 0 {
+                    flag |= same;
+                }
+                out.push(delta.unsigned_abs() as u8);
+            } else {
+                out.extend_from_slice(&(delta as i16).to_be_bytes());
+            }
+        }
+        flags.push(flag);
+        (x, y) = (*px, *py);
+    }
+    glyf.extend_from_slice(&flags);
+    glyf.extend_from_slice(&xs);
+    glyf.extend_from_slice(&ys);
+}
+
+/// Byte ranges of every glyph in the glyf table
+pub(super) fn glyph_ranges(
+    loca: &[u8],
+    long: bool,
+    num_glyphs: usize,
+    glyf_length: usize,
+) -> Result<Vec<Range<usize>>, Error> {
+    let mut reader = Reader::new(loca);
+    let offsets = (0..=num_glyphs)
+        .map(|_| {
+            if long {
+                reader.u32().map(|o| o as usize)
+            } else {
+                reader.u16().map(|o| o as usize * 2)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    offsets
+        .windows(2)
+        .map(|pair| {
+            if pair[0] <= pair[1] && pair[1] <= glyf_length {
+                Ok(pair[0]..pair[1])
+            } else {
+                Err(invalid("Glyph offsets are out of order"))
+            }
+        })
+        .collect()
+}
+
+/// Write the loca table of glyphs starting at `offsets`, followed by the end
+/// of the glyf table. Returns whether the long format is needed
+pub(super) fn write_loca(offsets: &[usize]) -> (Vec<u8>, bool) {
+    let long =
+        offsets.last().is_some_and(|end| *end > 0x1FFFE) || offsets.iter().any(|o| o % 2 != 0);
+    let loca = if long {
+        offsets
+            .iter()
+            .flat_map(|o| (*o as u32).to_be_bytes())
+            .collect()
+    } else {
+        offsets
+            .iter()
+            .flat_map(|o| ((o / 2) as u16).to_be_bytes())
+            .collect()
+    };
+    (loca, long)
+}
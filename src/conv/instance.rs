@@ -0,0 +1,624 @@
+//! A static instancer for TrueType-flavored variable fonts.
+//!
+//! Outlines and phantom points are moved by the `gvar` deltas, inferring the
+//! deltas of points a tuple leaves out, the control values by the `cvar`
+//! deltas, advances by `HVAR` when present, and font-wide metrics by `MVAR`.
+//! The variation tables are dropped afterwards. Variations of `GDEF`, `GPOS`
+//! and `GSUB` are left as they are.
+//!
+//! See the gvar spec: `https://learn.microsoft.com/en-us/typography/opentype/spec/gvar`
+
+use std::io::Error;
+
+use ttf_parser::{Face, GlyphId, Tag};
+
+use super::glyf::{glyph_ranges, write_loca, Glyph, SimpleGlyph};
+use super::sfnt::{
+    glyph_metrics, invalid, read_i16, set_table, table_mut, write_i16, write_sfnt, Reader, Sfnt,
+};
+use crate::font::width_to_stretch;
+use crate::StaticFace;
+
+/// Tables describing variations, and device metrics and signatures that no
+/// longer apply
+const DROPPED_TABLES: [&[u8; 4]; 10] = [
+    b"DSIG", b"avar", b"cvar", b"fvar", b"gvar", b"HVAR", b"MVAR", b"STAT", b"VVAR", b"hdmx",
+];
+
+/// Fields MVAR deltas apply to, by table and offset. Like
+/// [`vertical_metrics`](crate::font::vertical_metrics), the ascender,
+/// descender and line gap deltas also apply to hhea
+const MVAR_FIELDS: [(&[u8; 4], &[u8; 4], usize); 28] = [
+    (b"hasc", b"OS/2", 68),
+    (b"hasc", b"hhea", 4),
+    (b"hdsc", b"OS/2", 70),
+    (b"hdsc", b"hhea", 6),
+    (b"hlgp", b"OS/2", 72),
+    (b"hlgp", b"hhea", 8),
+    (b"hcla", b"OS/2", 74),
+    (b"hcld", b"OS/2", 76),
+    (b"hcrs", b"hhea", 18),
+    (b"hcrn", b"hhea", 20),
+    (b"hcof", b"hhea", 22),
+    (b"sbxs", b"OS/2", 10),
+    (b"sbys", b"OS/2", 12),
+    (b"sbxo", b"OS/2", 14),
+    (b"sbyo", b"OS/2", 16),
+    (b"spxs", b"OS/2", 18),
+    (b"spys", b"OS/2", 20),
+    (b"spxo", b"OS/2", 22),
+    (b"spyo", b"OS/2", 24),
+    (b"strs", b"OS/2", 26),
+    (b"stro", b"OS/2", 28),
+    (b"xhgt", b"OS/2", 86),
+    (b"cpht", b"OS/2", 88),
+    (b"undo", b"post", 8),
+    (b"unds", b"post", 10),
+    (b"vasc", b"vhea", 4),
+    (b"vdsc", b"vhea", 6),
+    (b"vlgp", b"vhea", 8),
+];
+
+impl StaticFace {
+    /// Write a standalone static font of this face, at the variation
+    /// coordinates of its key. Fonts that aren't variable are written as
+    /// they are
+    pub fn instance_buffer(&self) -> Result<Vec<u8>, crate::Error> {
+        Ok(instantiate(
+            self.borrow_buffer(),
+            self.collection_index(),
+            &self.key().variations,
+        )?)
+    }
+}
+
+/// Build a static font from the font at `index` of `buf`, at `coordinates`
+/// given as axis tags and values in user units. Axes left out keep their
+/// default value
+pub fn instantiate(
+    buf: &[u8],
+    index: u32,
+    coordinates: &[(String, f32)],
+) -> Result<Vec<u8>, Error> {
+    let sfnt = Sfnt::parse(buf, index)?;
+    let mut tables = sfnt
+        .tables
+        .iter()
+        .map(|(tag, data)| (*tag, data.to_vec()))
+        .collect::<Vec<_>>();
+    if sfnt.table(b"fvar").is_none() {
+        return Ok(write_sfnt(sfnt.flavor, tables));
+    }
+    if sfnt.table(b"CFF2").is_some() {
+        return Err(invalid("CFF2 outlines can't be instanced"));
+    }
+    let mut face = Face::parse(buf, index).map_err(|_| invalid("Font can't be parsed"))?;
+    for (tag, value) in coordinates {
+        face.set_variation(Tag::from_bytes_lossy(tag.as_bytes()), *value);
+    }
+    let coords = face
+        .variation_coordinates()
+        .iter()
+        .map(|c| c.get() as f32 / 16384.0)
+        .collect::<Vec<_>>();
+    tables.retain(|(tag, _)| !DROPPED_TABLES.contains(&tag));
+
+    if let Some(glyphs) = instantiate_glyphs(&sfnt, &face, &coords)? {
+        let (loca, long) = write_loca(&glyphs.offsets);
+        set_table(&mut tables, b"glyf", glyphs.glyf);
+        set_table(&mut tables, b"loca", loca);
+        let mut hmtx = vec![];
+        for (advance, lsb) in glyphs.metrics.iter() {
+            hmtx.extend_from_slice(&advance.to_be_bytes());
+            hmtx.extend_from_slice(&lsb.to_be_bytes());
+        }
+        set_table(&mut tables, b"hmtx", hmtx);
+        if let Some(head) = table_mut(&mut tables, b"head") {
+            for (i, value) in glyphs.bbox.iter().enumerate() {
+                write_i16(head, 36 + i * 2, *value);
+            }
+            write_i16(head, 50, long as i16);
+        }
+        if let Some(hhea) = table_mut(&mut tables, b"hhea") {
+            write_i16(hhea, 10, glyphs.advance_max as i16);
+            write_i16(hhea, 12, glyphs.min_lsb);
+            write_i16(hhea, 14, glyphs.min_rsb);
+            write_i16(hhea, 16, glyphs.x_max_extent);
+            write_i16(hhea, 34, glyphs.metrics.len() as i16);
+        }
+    }
+
+    if let (Some(cvar), Some(cvt)) = (sfnt.table(b"cvar"), table_mut(&mut tables, b"cvt ")) {
+        let values = cvt.len() / 2;
+        let mut deltas = vec![0.0_f32; values];
+        for tuple in tuples(cvar, 4, coords.len(), &[], &coords, values, 1)? {
+            for (k, delta) in tuple.deltas.iter().enumerate() {
+                let value = tuple.points.as_ref().map_or(k, |p| p[k]);
+                if let Some(sum) = deltas.get_mut(value) {
+                    *sum += *delta as f32 * tuple.scalar;
+                }
+            }
+        }
+        for (value, delta) in deltas.into_iter().enumerate() {
+            let varied = read_i16(cvt, value * 2) as f32 + delta;
+            write_i16(cvt, value * 2, varied.round() as i16);
+        }
+    }
+
+    if let Some(mvar) = face.tables().mvar {
+        for (tag, table, offset) in MVAR_FIELDS {
+            let Some(delta) =
+                mvar.metric_offset(Tag::from_bytes(tag), face.variation_coordinates())
+            else {
+                continue;
+            };
+            let Some(data) = table_mut(&mut tables, table).filter(|d| d.len() >= offset + 2) else {
+                continue;
+            };
+            if matches!(tag, b"hcla" | b"hcld") {
+                let value = u16::from_be_bytes([data[offset], data[offset + 1]]) as f32 + delta;
+                let value = value.round().clamp(0.0, u16::MAX as f32) as u16;
+                data[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+            } else {
+                let value = (read_i16(data, offset) as f32 + delta).round();
+                write_i16(data, offset, value as i16);
+            }
+        }
+    }
+
+    if let Some(os2) = table_mut(&mut tables, b"OS/2").filter(|os2| os2.len() >= 8) {
+        for axis in face.variation_axes() {
+            let tag = String::from_utf8_lossy(&axis.tag.to_bytes()).to_string();
+            let value = coordinates
+                .iter()
+                .find(|(t, _)| *t == tag)
+                .map_or(axis.def_value, |(_, v)| {
+                    v.clamp(axis.min_value, axis.max_value)
+                });
+            match &axis.tag.to_bytes() {
+                b"wght" => write_i16(os2, 4, value.round().clamp(1.0, 1000.0) as i16),
+                b"wdth" => write_i16(os2, 6, width_to_stretch(value) as i16),
+                _ => {}
+            }
+        }
+    }
+    Ok(write_sfnt(sfnt.flavor, tables))
+}
+
+/// The glyf table of an instance, with the metrics depending on it
+struct InstanceGlyphs {
+    glyf: Vec<u8>,
+    offsets: Vec<usize>,
+    /// Advance and left side bearing of every glyph
+    metrics: Vec<(u16, i16)>,
+    bbox: [i16; 4],
+    advance_max: u16,
+    min_lsb: i16,
+    min_rsb: i16,
+    x_max_extent: i16,
+}
+
+/// The gvar table, "gvar Header"
+struct Gvar<'a> {
+    data: &'a [u8],
+    axis_count: usize,
+    shared_tuples: Vec<Vec<f32>>,
+    ranges: Vec<std::ops::Range<usize>>,
+}
+
+impl<'a> Gvar<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let mut reader = Reader::at(data, 4);
+        let axis_count = reader.u16()? as usize;
+        let shared_tuple_count = reader.u16()? as usize;
+        let shared_tuples_offset = reader.u32()? as usize;
+        let glyph_count = reader.u16()? as usize;
+        let flags = reader.u16()?;
+        let data_offset = reader.u32()? as usize;
+        let offsets = (0..=glyph_count)
+            .map(|_| {
+                if flags & 1 != 0 {
+                    reader.u32().map(|o| o as usize)
+                } else {
+                    reader.u16().map(|o| o as usize * 2)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut shared = Reader::at(data, shared_tuples_offset);
+        let shared_tuples = (0..shared_tuple_count)
+            .map(|_| (0..axis_count).map(|_| shared.f2dot14()).collect())
+            .collect::<Result<Vec<_>, _>>()?;
+        let ranges = offsets
+            .windows(2)
+            .map(|pair| data_offset + pair[0]..data_offset + pair[1])
+            .collect();
+        Ok(Gvar {
+            data,
+            axis_count,
+            shared_tuples,
+            ranges,
+        })
+    }
+
+    /// The "GlyphVariationData" of a glyph, if it has variations
+    fn glyph_data(&self, glyph: usize) -> Option<&'a [u8]> {
+        let range = self.ranges.get(glyph)?.clone();
+        self.data.get(range).filter(|data| !data.is_empty())
+    }
+}
+
+fn instantiate_glyphs(
+    sfnt: &Sfnt,
+    face: &Face,
+    coords: &[f32],
+) -> Result<Option<InstanceGlyphs>, Error> {
+    let (Some(glyf), Some(loca), Some(head), Some(hhea), Some(hmtx), Some(maxp)) = (
+        sfnt.table(b"glyf"),
+        sfnt.table(b"loca"),
+        sfnt.table(b"head"),
+        sfnt.table(b"hhea"),
+        sfnt.table(b"hmtx"),
+        sfnt.table(b"maxp"),
+    ) else {
+        return Ok(None);
+    };
+    let num_glyphs = Reader::at(maxp, 4).u16()? as usize;
+    let long = Reader::at(head, 50).i16()? != 0;
+    let num_h_metrics = (Reader::at(hhea, 34).u16()? as usize).clamp(1, num_glyphs.max(1));
+    let ranges = glyph_ranges(loca, long, num_glyphs, glyf.len())?;
+    let gvar = sfnt.table(b"gvar").map(Gvar::parse).transpose()?;
+    let hvar = face.tables().hvar;
+
+    let mut result = InstanceGlyphs {
+        glyf: vec![],
+        offsets: vec![],
+        metrics: vec![],
+        bbox: [i16::MAX, i16::MAX, i16::MIN, i16::MIN],
+        advance_max: 0,
+        min_lsb: i16::MAX,
+        min_rsb: i16::MAX,
+        x_max_extent: i16::MIN,
+    };
+    for (id, range) in ranges.into_iter().enumerate() {
+        let mut glyph = Glyph::parse(&glyf[range.clone()])?;
+        let (advance, lsb) = glyph_metrics(hmtx, num_h_metrics, id)?;
+        let x_min = glyf
+            .get(range.start + 2..range.start + 4)
+            .map_or(lsb, |b| read_i16(b, 0));
+        let origin = (x_min - lsb) as f32;
+        let mut points = match &glyph {
+            Glyph::Empty => vec![],
+            Glyph::Simple(simple) => simple
+                .points
+                .iter()
+                .map(|p| (p.0 as f32, p.1 as f32))
+                .collect(),
+            Glyph::Composite(composite) => composite
+                .components
+                .iter()
+                .map(|c| (c.args.0 as f32, c.args.1 as f32))
+                .collect(),
+        };
+        let outline_points = points.len();
+        points.extend([
+            (origin, 0.0),
+            (origin + advance as f32, 0.0),
+            (0.0, 0.0),
+            (0.0, 0.0),
+        ]);
+        if let Some(data) = gvar.as_ref().and_then(|gvar| gvar.glyph_data(id)) {
+            let gvar = gvar.as_ref().unwrap();
+            let simple = match &glyph {
+                Glyph::Simple(simple) => Some(simple),
+                _ => None,
+            };
+            let deltas = glyph_deltas(gvar, data, coords, &points, simple)?;
+            for (point, delta) in points.iter_mut().zip(deltas) {
+                point.0 += delta.0;
+                point.1 += delta.1;
+            }
+        }
+        let moved = |i: usize| (points[i].0.round() as i32, points[i].1.round() as i32);
+        let origin = points[outline_points].0;
+        let advance = match hvar {
+            Some(hvar) => {
+                let offset = hvar.advance_offset(GlyphId(id as u16), face.variation_coordinates());
+                advance as f32 + offset.unwrap_or(0.0)
+            }
+            None => points[outline_points + 1].0 - origin,
+        };
+        let advance = advance.round().clamp(0.0, u16::MAX as f32) as u16;
+
+        let start = result.glyf.len();
+        let bbox = match &mut glyph {
+            Glyph::Empty => None,
+            Glyph::Simple(simple) => {
+                for (i, point) in simple.points.iter_mut().enumerate() {
+                    (point.0, point.1) = moved(i);
+                }
+                simple.write(&mut result.glyf);
+                Some(simple.bbox())
+            }
+            Glyph::Composite(composite) => {
+                for (i, component) in composite.components.iter_mut().enumerate() {
+                    if component.is_offset() {
+                        component.args = moved(i);
+                    }
+                }
+                if let Some(rect) = face.glyph_bounding_box(GlyphId(id as u16)) {
+                    composite.bbox = [rect.x_min, rect.y_min, rect.x_max, rect.y_max];
+                }
+                composite.write(&mut result.glyf);
+                Some(composite.bbox)
+            }
+        };
+        result.offsets.push(start);
+        result.glyf.resize(result.glyf.len().next_multiple_of(4), 0);
+
+        let lsb = match bbox {
+            Some(bbox) => (bbox[0] as f32 - origin).round() as i16,
+            None => lsb,
+        };
+        result.metrics.push((advance, lsb));
+        result.advance_max = result.advance_max.max(advance);
+        if let Some(bbox) = bbox {
+            let extent = lsb.saturating_add(bbox[2] - bbox[0]);
+            result.min_lsb = result.min_lsb.min(lsb);
+            result.min_rsb = result.min_rsb.min((advance as i32 - extent as i32) as i16);
+            result.x_max_extent = result.x_max_extent.max(extent);
+            for i in 0..2 {
+                result.bbox[i] = result.bbox[i].min(bbox[i]);
+                result.bbox[i + 2] = result.bbox[i + 2].max(bbox[i + 2]);
+            }
+        }
+    }
+    result.offsets.push(result.glyf.len());
+    if result.x_max_extent == i16::MIN {
+        result.bbox = [0; 4];
+        (result.min_lsb, result.min_rsb, result.x_max_extent) = (0, 0, 0);
+    }
+    Ok(Some(result))
+}
+
+/// Sum the deltas of every tuple applying at `coords` to the points of a
+/// glyph, phantom points included. Points a tuple leaves out get inferred
+/// deltas in simple glyphs, "Inferred deltas for un-referenced point numbers"
+fn glyph_deltas(
+    gvar: &Gvar,
+    data: &[u8],
+    coords: &[f32],
+    points: &[(f32, f32)],
+    simple: Option<&SimpleGlyph>,
+) -> Result<Vec<(f32, f32)>, Error> {
+    let mut deltas = vec![(0.0, 0.0); points.len()];
+    let tuples = tuples(
+        data,
+        0,
+        gvar.axis_count,
+        &gvar.shared_tuples,
+        coords,
+        points.len(),
+        2,
+    )?;
+    for tuple in tuples {
+        let count = tuple.deltas.len() / 2;
+        let mut tuple_deltas = vec![(0.0, 0.0); points.len()];
+        let mut touched = vec![false; points.len()];
+        for k in 0..count {
+            let point = tuple.points.as_ref().map_or(k, |p| p[k]);
+            if point < points.len() {
+                tuple_deltas[point] = (tuple.deltas[k] as f32, tuple.deltas[count + k] as f32);
+                touched[point] = true;
+            }
+        }
+        if let (Some(simple), Some(_)) = (simple, tuple.points.as_ref()) {
+            interpolate_untouched(simple, points, &mut tuple_deltas, &touched);
+        }
+        for (delta, tuple_delta) in deltas.iter_mut().zip(tuple_deltas) {
+            delta.0 += tuple_delta.0 * tuple.scalar;
+            delta.1 += tuple_delta.1 * tuple.scalar;
+        }
+    }
+    Ok(deltas)
+}
+
+/// Give the points of every contour a tuple leaves out deltas interpolated
+/// from the touched points around them
+fn interpolate_untouched(
+    glyph: &SimpleGlyph,
+    points: &[(f32, f32)],
+    deltas: &mut [(f32, f32)],
+    touched: &[bool],
+) {
+    for contour in glyph.contours() {
+        let touched_points = contour.clone().filter(|i| touched[*i]).collect::<Vec<_>>();
+        if touched_points.is_empty() || touched_points.len() == contour.len() {
+            continue;
+        }
+        let next = |i: usize| {
+            if i + 1 == contour.end {
+                contour.start
+            } else {
+                i + 1
+            }
+        };
+        for (k, first) in touched_points.iter().enumerate() {
+            let last = touched_points[(k + 1) % touched_points.len()];
+            let mut i = next(*first);
+            while i != last {
+                deltas[i] = (
+                    interpolate(
+                        points[i].0,
+                        (points[*first].0, deltas[*first].0),
+                        (points[last].0, deltas[last].0),
+                    ),
+                    interpolate(
+                        points[i].1,
+                        (points[*first].1, deltas[*first].1),
+                        (points[last].1, deltas[last].1),
+                    ),
+                );
+                i = next(i);
+            }
+        }
+    }
+}
+
+/// Delta of a coordinate between two touched points, given as coordinate
+/// and delta
+fn interpolate(value: f32, a: (f32, f32), b: (f32, f32)) -> f32 {
+    if a.0 == b.0 {
+        return if a.1 == b.1 { a.1 } else { 0.0 };
+    }
+    let (low, high) = if a.0 < b.0 { (a, b) } else { (b, a) };
+    if value <= low.0 {
+        low.1
+    } else if value >= high.0 {
+        high.1
+    } else {
+        low.1 + (value - low.0) * (high.1 - low.1) / (high.0 - low.0)
+    }
+}
+
+/// A tuple variation applying at the instance coordinates
+struct Tuple {
+    scalar: f32,
+    /// Points or values the deltas apply to, all of them when `None`
+    points: Option<Vec<usize>>,
+    /// Deltas of every dimension in turn, x then y for gvar
+    deltas: Vec<i32>,
+}
+
+/// Read a "Tuple Variation Store" whose header starts at `header`, keeping
+/// the tuples applying at `coords`. Offsets are relative to `store`
+fn tuples(
+    store: &[u8],
+    header: usize,
+    axis_count: usize,
+    shared_tuples: &[Vec<f32>],
+    coords: &[f32],
+    point_count: usize,
+    dimensions: usize,
+) -> Result<Vec<Tuple>, Error> {
+    let mut reader = Reader::at(store, header);
+    let count = reader.u16()?;
+    let mut serialized = Reader::at(store, reader.u16()? as usize);
+    let shared_points = if count & 0x8000 != 0 {
+        read_points(&mut serialized)?
+    } else {
+        None
+    };
+    let mut tuples = vec![];
+    for _ in 0..count & 0x0FFF {
+        let size = reader.u16()? as usize;
+        let index = reader.u16()?;
+        let mut read_tuple = || {
+            (0..axis_count)
+                .map(|_| reader.f2dot14())
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let peak = if index & 0x8000 != 0 {
+            read_tuple()?
+        } else {
+            shared_tuples
+                .get((index & 0x0FFF) as usize)
+                .cloned()
+                .ok_or_else(|| invalid("Shared tuple index out of range"))?
+        };
+        let intermediate = if index & 0x4000 != 0 {
+            Some((read_tuple()?, read_tuple()?))
+        } else {
+            None
+        };
+        let mut data = Reader::new(serialized.bytes(size)?);
+        let scalar = tuple_scalar(&peak, intermediate.as_ref(), coords);
+        if scalar == 0.0 {
+            continue;
+        }
+        let points = if index & 0x2000 != 0 {
+            read_points(&mut data)?
+        } else {
+            shared_points.clone()
+        };
+        let count = points.as_ref().map_or(point_count, |p| p.len());
+        let deltas = read_deltas(&mut data, count * dimensions)?;
+        tuples.push(Tuple {
+            scalar,
+            points,
+            deltas,
+        });
+    }
+    Ok(tuples)
+}
+
+/// How much a tuple applies at `coords`, "Algorithm for interpolation of
+/// instance values"
+fn tuple_scalar(peak: &[f32], intermediate: Option<&(Vec<f32>, Vec<f32>)>, coords: &[f32]) -> f32 {
+    let mut scalar = 1.0;
+    for (i, peak) in peak.iter().copied().enumerate() {
+        let value = coords.get(i).copied().unwrap_or(0.0);
+        if peak == 0.0 {
+            continue;
+        }
+        let (start, end) =
+            intermediate.map_or((peak.min(0.0), peak.max(0.0)), |(s, e)| (s[i], e[i]));
+        if start > peak || peak > end || (start < 0.0 && end > 0.0) || value == peak {
+            continue;
+        }
+        if value <= start || end <= value {
+            return 0.0;
+        }
+        scalar *= if value < peak {
+            (value - start) / (peak - start)
+        } else {
+            (end - value) / (end - peak)
+        };
+    }
+    scalar
+}
+
+/// "Packed Point Numbers", `None` standing for every point
+fn read_points(reader: &mut Reader) -> Result<Option<Vec<usize>>, Error> {
+    let first = reader.u8()? as usize;
+    if first == 0 {
+        return Ok(None);
+    }
+    let count = if first & 0x80 != 0 {
+        ((first & 0x7F) << 8) | reader.u8()? as usize
+    } else {
+        first
+    };
+    let mut points = Vec::with_capacity(count);
+    let mut point = 0;
+    while points.len() < count {
+        let control = reader.u8()?;
+        for _ in 0..(control & 0x7F) + 1 {
+            point += if control & 0x80 != 0 {
+                reader.u16()? as usize
+            } else {
+                reader.u8()? as usize
+            };
+            points.push(point);
+        }
+    }
+    points.truncate(count);
+    Ok(Some(points))
+}
+
+/// "Packed Deltas"
+fn read_deltas(reader: &mut Reader, count: usize) -> Result<Vec<i32>, Error> {
+    let mut deltas = Vec::with_capacity(count);
+    while deltas.len() < count {
+        let control = reader.u8()?;
+        for _ in 0..(control & 0x3F) + 1 {
+            deltas.push(match control & 0xC0 {
+                0x80 => 0,
+                0x40 => reader.i16()? as i32,
+                0xC0 => reader.u32()? as i32,
+                _ => reader.i8()? as i32,
+            });
+        }
+    }
+    deltas.truncate(count);
+    Ok(deltas)
+}
@@ -0,0 +1,197 @@
+//! Strict checks of the sfnt structure, run before parsing fonts that may be
+//! corrupted.
+//!
+//! Every font of a collection must have a sane table directory, tables
+//! inside the data that don't overlap and match their checksums, and an fvar
+//! table whose records fit in it, as its instances are read directly.
+//!
+//! The table directory of WOFF2 files is checked in any mode, before their
+//! tables are decompressed and rebuilt.
+
+use std::ops::Range;
+
+use super::sfnt::table_checksum;
+use crate::Error;
+
+const TRUETYPE: u32 = 0x0001_0000;
+
+/// Printable name of a table tag
+pub(crate) fn tag_name(tag: &[u8]) -> String {
+    String::from_utf8_lossy(tag).into_owned()
+}
+
+fn read_u16(buf: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(buf.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(buf: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(buf.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Check every font of `buf`, a single font or a collection
+pub fn sanitize(buf: &[u8]) -> Result<(), Error> {
+    if !buf.starts_with(b"ttcf") {
+        return sanitize_font(buf, 0);
+    }
+    let count = read_u32(buf, 8).ok_or(Error::InvalidDirectory(
+        "Font collection header is truncated",
+    ))? as usize;
+    if count == 0 {
+        return Err(Error::InvalidDirectory("Font collection is empty"));
+    }
+    for index in 0..count {
+        let offset = 12_usize
+            .checked_add(4 * index)
+            .and_then(|at| read_u32(buf, at))
+            .ok_or(Error::InvalidDirectory(
+                "Font collection header is truncated",
+            ))?;
+        sanitize_font(buf, offset as usize)?;
+    }
+    Ok(())
+}
+
+/// Check the directory of a WOFF2 file, which has `num_tables` tables and
+/// `fonts` listing the indices of their tables: every font lists tables of
+/// the file, at least one and each once
+pub(crate) fn sanitize_woff2_directory(num_tables: usize, fonts: &[&[usize]]) -> Result<(), Error> {
+    if num_tables == 0 {
+        return Err(Error::InvalidDirectory("WOFF2 file has no tables"));
+    }
+    if fonts.is_empty() {
+        return Err(Error::InvalidDirectory("Font collection is empty"));
+    }
+    for indices in fonts {
+        if indices.is_empty() {
+            return Err(Error::InvalidDirectory("Font has no tables"));
+        }
+        if indices.iter().any(|i| *i >= num_tables) {
+            return Err(Error::InvalidDirectory(
+                "Collection references an unknown table",
+            ));
+        }
+        let mut sorted = indices.to_vec();
+        sorted.sort_unstable();
+        if sorted.windows(2).any(|pair| pair[0] == pair[1]) {
+            return Err(Error::InvalidDirectory(
+                "Collection lists a table twice for a font",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check the table directory at `offset` and the tables it points to
+fn sanitize_font(buf: &[u8], offset: usize) -> Result<(), Error> {
+    let flavor =
+        read_u32(buf, offset).ok_or(Error::InvalidDirectory("Table directory is truncated"))?;
+    if ![
+        TRUETYPE,
+        u32::from_be_bytes(*b"OTTO"),
+        u32::from_be_bytes(*b"true"),
+    ]
+    .contains(&flavor)
+    {
+        return Err(Error::InvalidDirectory("Unknown sfnt version"));
+    }
+    let num_tables = read_u16(buf, offset + 4)
+        .ok_or(Error::InvalidDirectory("Table directory is truncated"))?
+        as usize;
+    if num_tables == 0 {
+        return Err(Error::InvalidDirectory("Font has no tables"));
+    }
+    let directory_end = offset + 12 + 16 * num_tables;
+    let records = buf
+        .get(offset + 12..directory_end)
+        .ok_or(Error::InvalidDirectory("Table directory is truncated"))?;
+
+    let mut tables: Vec<([u8; 4], u32, Range<usize>)> = Vec::with_capacity(num_tables);
+    for record in records.chunks(16) {
+        let tag: [u8; 4] = record[0..4].try_into().unwrap();
+        if tables.iter().any(|(t, ..)| *t == tag) {
+            return Err(Error::InvalidTable {
+                tag: tag_name(&tag),
+                reason: "Table is listed twice",
+            });
+        }
+        let field = |at: usize| read_u32(record, at).unwrap();
+        let start = field(8) as usize;
+        let end = start
+            .checked_add(field(12) as usize)
+            .filter(|end| *end <= buf.len())
+            .ok_or_else(|| Error::TableOutOfBounds {
+                tag: tag_name(&tag),
+            })?;
+        if start < directory_end && end > offset {
+            return Err(Error::InvalidTable {
+                tag: tag_name(&tag),
+                reason: "Table overlaps the table directory",
+            });
+        }
+        tables.push((tag, field(4), start..end));
+    }
+
+    let mut ranges = tables
+        .iter()
+        .filter(|(_, _, range)| !range.is_empty())
+        .collect::<Vec<_>>();
+    ranges.sort_by_key(|(_, _, range)| range.start);
+    for pair in ranges.windows(2) {
+        let ((tag, _, range), (other, _, next)) = (pair[0], pair[1]);
+        if range.end > next.start {
+            return Err(Error::TablesOverlap {
+                tag: tag_name(tag),
+                other: tag_name(other),
+            });
+        }
+    }
+
+    for (tag, expected, range) in tables.iter() {
+        let found = table_checksum(tag, &buf[range.clone()]);
+        if found != *expected {
+            return Err(Error::TableChecksum {
+                tag: tag_name(tag),
+                found,
+                expected: *expected,
+            });
+        }
+        if tag == b"fvar" {
+            fvar_instances(&buf[range.clone()])?;
+        }
+    }
+    Ok(())
+}
+
+/// The number of axes of an fvar table and its instance records, checking
+/// that the records have the sizes the spec gives and fit in the table
+pub(crate) fn fvar_instances(fvar: &[u8]) -> Result<(usize, Vec<&[u8]>), Error> {
+    let invalid = |reason| Error::InvalidTable {
+        tag: "fvar".to_string(),
+        reason,
+    };
+    let field = |at: usize| read_u16(fvar, at).ok_or_else(|| invalid("Header is truncated"));
+    if field(0)? != 1 {
+        return Err(invalid("Version is not supported"));
+    }
+    let axis_offset = field(4)? as usize;
+    let axis_count = field(8)? as usize;
+    let axis_size = field(10)? as usize;
+    let instance_count = field(12)? as usize;
+    let instance_size = field(14)? as usize;
+    if axis_size != 20 {
+        return Err(invalid("Axis records don't have 20 bytes"));
+    }
+    // the PostScript name ID is optional
+    if instance_size != 4 + 4 * axis_count && instance_size != 6 + 4 * axis_count {
+        return Err(invalid("Instance records don't match the number of axes"));
+    }
+    let start = axis_offset + axis_count * axis_size;
+    // two u16 counts multiplied can overflow usize on wasm32
+    let end = instance_count
+        .checked_mul(instance_size)
+        .and_then(|size| size.checked_add(start));
+    let records = end
+        .and_then(|end| fvar.get(start..end))
+        .ok_or_else(|| invalid("Records lie outside of the table"))?;
+    Ok((axis_count, records.chunks(instance_size).collect()))
+}
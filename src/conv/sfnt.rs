@@ -0,0 +1,203 @@
+//! Reading and writing the table directory of sfnt fonts, shared by the
+//! converters.
+//!
+//! See the OpenType spec: `https://learn.microsoft.com/en-us/typography/opentype/spec/otff`
+
+use std::io::{Error, ErrorKind};
+
+pub(super) fn invalid(message: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// A big-endian cursor over font data
+pub(super) struct Reader<'a> {
+    pub(super) data: &'a [u8],
+    pub(super) pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(super) fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    pub(super) fn at(data: &'a [u8], pos: usize) -> Self {
+        Reader { data, pos }
+    }
+
+    pub(super) fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| invalid("Font data is truncated"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    pub(super) fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(super) fn i8(&mut self) -> Result<i8, Error> {
+        Ok(self.u8()? as i8)
+    }
+
+    pub(super) fn u16(&mut self) -> Result<u16, Error> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    pub(super) fn i16(&mut self) -> Result<i16, Error> {
+        Ok(self.u16()? as i16)
+    }
+
+    pub(super) fn u32(&mut self) -> Result<u32, Error> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// A F2DOT14 number
+    pub(super) fn f2dot14(&mut self) -> Result<f32, Error> {
+        Ok(self.i16()? as f32 / 16384.0)
+    }
+}
+
+/// The tables of a font, in the order of its table directory
+pub(super) struct Sfnt<'a> {
+    pub(super) flavor: u32,
+    pub(super) tables: Vec<([u8; 4], &'a [u8])>,
+}
+
+impl<'a> Sfnt<'a> {
+    /// Read the font at `index` of a font collection, or the font itself
+    /// when `buf` isn't a collection
+    pub(super) fn parse(buf: &'a [u8], index: u32) -> Result<Self, Error> {
+        let offset = if buf.starts_with(b"ttcf") {
+            let mut reader = Reader::at(buf, 8);
+            if index >= reader.u32()? {
+                return Err(invalid("Font collection index out of range"));
+            }
+            Reader::at(buf, 12 + 4 * index as usize).u32()? as usize
+        } else {
+            0
+        };
+        let mut reader = Reader::at(buf, offset);
+        let flavor = reader.u32()?;
+        let num_tables = reader.u16()?;
+        reader.bytes(6)?;
+        let mut tables = vec![];
+        for _ in 0..num_tables {
+            let tag = reader.bytes(4)?.try_into().unwrap();
+            let _checksum = reader.u32()?;
+            let offset = reader.u32()? as usize;
+            let length = reader.u32()? as usize;
+            let data = Reader::at(buf, offset).bytes(length)?;
+            tables.push((tag, data));
+        }
+        Ok(Sfnt { flavor, tables })
+    }
+
+    pub(super) fn table(&self, tag: &[u8; 4]) -> Option<&'a [u8]> {
+        self.tables
+            .iter()
+            .find(|(t, _)| t == tag)
+            .map(|(_, data)| *data)
+    }
+}
+
+/// Tables of a font being rewritten
+pub(super) type Tables = Vec<([u8; 4], Vec<u8>)>;
+
+pub(super) fn table_mut<'a>(tables: &'a mut Tables, tag: &[u8; 4]) -> Option<&'a mut Vec<u8>> {
+    tables
+        .iter_mut()
+        .find(|(t, _)| t == tag)
+        .map(|(_, data)| data)
+}
+
+pub(super) fn set_table(tables: &mut Tables, tag: &[u8; 4], data: Vec<u8>) {
+    if let Some(table) = table_mut(tables, tag) {
+        *table = data;
+    }
+}
+
+pub(super) fn read_i16(data: &[u8], offset: usize) -> i16 {
+    i16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+pub(super) fn write_i16(data: &mut [u8], offset: usize, value: i16) {
+    data[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+/// Advance and side bearing of a glyph in a hmtx or vmtx table holding
+/// `long_count` full metrics
+pub(super) fn glyph_metrics(
+    table: &[u8],
+    long_count: usize,
+    glyph: usize,
+) -> Result<(u16, i16), Error> {
+    let advance = Reader::at(table, 4 * glyph.min(long_count - 1)).u16()?;
+    let bearing = if glyph < long_count {
+        Reader::at(table, 4 * glyph + 2).i16()?
+    } else {
+        Reader::at(table, 4 * long_count + 2 * (glyph - long_count)).i16()?
+    };
+    Ok((advance, bearing))
+}
+
+pub(super) fn checksum(table: &[u8]) -> u32 {
+    table.chunks(4).fold(0_u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Checksum of a table for the table directory, which leaves out the
+/// checksum adjustment of the head table
+pub(super) fn table_checksum(tag: &[u8; 4], table: &[u8]) -> u32 {
+    if tag == b"head" && table.len() >= 12 {
+        let mut head = table.to_vec();
+        head[8..12].fill(0);
+        return checksum(&head);
+    }
+    checksum(table)
+}
+
+/// Write a font with the given tables, sorted by tag and 4-byte aligned, and
+/// set the checksum adjustment of its head table
+pub(super) fn write_sfnt(flavor: u32, mut tables: Tables) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u16;
+    let entry_selector = num_tables.checked_ilog2().unwrap_or(0) as u16;
+    let search_range: u16 = (1 << entry_selector) * 16;
+    let mut output = vec![];
+    output.extend_from_slice(&flavor.to_be_bytes());
+    output.extend_from_slice(&num_tables.to_be_bytes());
+    output.extend_from_slice(&search_range.to_be_bytes());
+    output.extend_from_slice(&entry_selector.to_be_bytes());
+    output.extend_from_slice(&(num_tables * 16 - search_range).to_be_bytes());
+    let mut offset = 12 + 16 * tables.len();
+    let mut head_offset = None;
+    for (tag, data) in tables.iter_mut() {
+        if tag == b"head" && data.len() >= 12 {
+            data[8..12].fill(0);
+            head_offset = Some(offset);
+        }
+        output.extend_from_slice(tag);
+        output.extend_from_slice(&checksum(data).to_be_bytes());
+        output.extend_from_slice(&(offset as u32).to_be_bytes());
+        output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in tables.iter() {
+        output.extend_from_slice(data);
+        output.resize(output.len().next_multiple_of(4), 0);
+    }
+    if let Some(offset) = head_offset {
+        let adjustment = 0xB1B0_AFBA_u32.wrapping_sub(checksum(&output));
+        output[offset + 8..offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    output
+}
@@ -0,0 +1,386 @@
+//! A subsetter keeping only the glyphs a document uses.
+//!
+//! Kept glyphs are renumbered in the order of their original IDs, `.notdef`
+//! first. glyf or CFF outlines are rebuilt along with cmap, hmtx, vmtx, post
+//! and the pairs of kern, while tables referring to glyph IDs that aren't
+//! rebuilt, like the layout and color tables, are dropped. Variable fonts are
+//! subset from their static instance.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Error;
+
+use ttf_parser::Face;
+
+use super::cff::subset_cff;
+use super::glyf::{glyph_ranges, write_loca, Glyph};
+use super::instance::instantiate;
+use super::sfnt::{
+    glyph_metrics, invalid, set_table, table_mut, write_i16, write_sfnt, Reader, Sfnt, Tables,
+};
+use super::{FontFormat, WoffBlocks};
+use crate::StaticFace;
+
+/// Tables that don't refer to glyph IDs, or that are rebuilt
+const KEPT_TABLES: [&[u8; 4]; 18] = [
+    b"CFF ", b"OS/2", b"cmap", b"cvt ", b"fpgm", b"gasp", b"glyf", b"head", b"hhea", b"hmtx",
+    b"kern", b"loca", b"maxp", b"name", b"post", b"prep", b"vhea", b"vmtx",
+];
+
+impl StaticFace {
+    /// Write a font holding only the glyphs `text` uses, in `format`
+    pub fn subset_text(&self, text: &str, format: FontFormat) -> Result<Vec<u8>, crate::Error> {
+        let glyphs = self.with_face(|f| {
+            text.chars()
+                .filter_map(|c| f.glyph_index(c))
+                .map(|g| g.0)
+                .collect::<Vec<_>>()
+        });
+        self.subset_glyphs(&glyphs, format)
+    }
+
+    /// Write a font holding only `glyphs`, along with `.notdef` and the
+    /// components of composite glyphs, in `format`
+    pub fn subset_glyphs(
+        &self,
+        glyphs: &[u16],
+        format: FontFormat,
+    ) -> Result<Vec<u8>, crate::Error> {
+        let font = instantiate(
+            self.borrow_buffer(),
+            self.collection_index(),
+            &self.key().variations,
+        )?;
+        Ok(format.encode(subset(&font, 0, glyphs)?, &WoffBlocks::default())?)
+    }
+}
+
+/// Build a font from the glyphs `glyphs` of the static font at `index` of
+/// `buf`, along with `.notdef` and the components of composite glyphs
+pub fn subset(buf: &[u8], index: u32, glyphs: &[u16]) -> Result<Vec<u8>, Error> {
+    let sfnt = Sfnt::parse(buf, index)?;
+    if sfnt.table(b"fvar").is_some() || sfnt.table(b"CFF2").is_some() {
+        return Err(invalid(
+            "Variable fonts must be instanced before subsetting",
+        ));
+    }
+    let face = Face::parse(buf, index).map_err(|_| invalid("Font can't be parsed"))?;
+    let num_glyphs = face.number_of_glyphs();
+    if glyphs.iter().any(|g| *g >= num_glyphs) {
+        return Err(invalid("Glyph ID out of range"));
+    }
+    let mut kept = BTreeSet::from([0]);
+    kept.extend(glyphs.iter().copied());
+    let mut tables = sfnt
+        .tables
+        .iter()
+        .filter(|(tag, _)| KEPT_TABLES.contains(&tag))
+        .map(|(tag, data)| (*tag, data.to_vec()))
+        .collect::<Tables>();
+
+    if let (Some(glyf), Some(loca), Some(head)) = (
+        sfnt.table(b"glyf"),
+        sfnt.table(b"loca"),
+        sfnt.table(b"head"),
+    ) {
+        let long = Reader::at(head, 50).i16()? != 0;
+        let ranges = glyph_ranges(loca, long, num_glyphs as usize, glyf.len())?;
+        let glyph = |id: u16| Glyph::parse(&glyf[ranges[id as usize].clone()]);
+        let mut queue = kept.iter().copied().collect::<Vec<_>>();
+        while let Some(id) = queue.pop() {
+            if let Glyph::Composite(composite) = glyph(id)? {
+                for component in composite.components {
+                    if component.glyph >= num_glyphs {
+                        return Err(invalid("Glyph ID out of range"));
+                    }
+                    if kept.insert(component.glyph) {
+                        queue.push(component.glyph);
+                    }
+                }
+            }
+        }
+
+        let order = kept.iter().copied().collect::<Vec<_>>();
+        let mut new_glyf = vec![];
+        let mut offsets = vec![];
+        for id in order.iter() {
+            offsets.push(new_glyf.len());
+            match glyph(*id)? {
+                Glyph::Composite(mut composite) => {
+                    for component in composite.components.iter_mut() {
+                        component.glyph = new_id(&order, component.glyph);
+                    }
+                    composite.write(&mut new_glyf);
+                }
+                _ => new_glyf.extend_from_slice(&glyf[ranges[*id as usize].clone()]),
+            }
+            new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+        }
+        offsets.push(new_glyf.len());
+        let (new_loca, long) = write_loca(&offsets);
+        set_table(&mut tables, b"glyf", new_glyf);
+        set_table(&mut tables, b"loca", new_loca);
+        if let Some(head) = table_mut(&mut tables, b"head") {
+            write_i16(head, 50, long as i16);
+        }
+    }
+    let order = kept.into_iter().collect::<Vec<_>>();
+
+    if let Some(cff) = sfnt.table(b"CFF ") {
+        set_table(&mut tables, b"CFF ", subset_cff(cff, &order)?);
+    }
+    for (metrics_tag, header_tag) in [(b"hmtx", b"hhea"), (b"vmtx", b"vhea")] {
+        let (Some(metrics), Some(header)) =
+            (sfnt.table(metrics_tag), table_mut(&mut tables, header_tag))
+        else {
+            continue;
+        };
+        let long_count = (Reader::at(header, 34).u16()? as usize).clamp(1, num_glyphs as usize);
+        let mut data = vec![];
+        let mut advance_max = 0;
+        for id in order.iter() {
+            let (advance, bearing) = glyph_metrics(metrics, long_count, *id as usize)?;
+            data.extend_from_slice(&advance.to_be_bytes());
+            data.extend_from_slice(&bearing.to_be_bytes());
+            advance_max = advance_max.max(advance);
+        }
+        header[10..12].copy_from_slice(&advance_max.to_be_bytes());
+        header[34..36].copy_from_slice(&(order.len() as u16).to_be_bytes());
+        set_table(&mut tables, metrics_tag, data);
+    }
+    if let Some(maxp) = table_mut(&mut tables, b"maxp") {
+        maxp[4..6].copy_from_slice(&(order.len() as u16).to_be_bytes());
+    }
+
+    let mut mapping = BTreeMap::new();
+    if let Some(cmap) = face.tables().cmap {
+        for subtable in cmap.subtables.into_iter().filter(|s| s.is_unicode()) {
+            subtable.codepoints(|c| {
+                let new = subtable
+                    .glyph_index(c)
+                    .and_then(|g| order.binary_search(&g.0).ok())
+                    .filter(|new| *new != 0);
+                if let Some(new) = new {
+                    mapping.entry(c).or_insert(new as u16);
+                }
+            });
+        }
+    }
+    set_table(&mut tables, b"cmap", write_cmap(&mapping));
+    if let (Some(os2), Some(first), Some(last)) = (
+        table_mut(&mut tables, b"OS/2").filter(|os2| os2.len() >= 68),
+        mapping.keys().next(),
+        mapping.keys().next_back(),
+    ) {
+        os2[64..66].copy_from_slice(&(*first.min(&0xFFFF) as u16).to_be_bytes());
+        os2[66..68].copy_from_slice(&(*last.min(&0xFFFF) as u16).to_be_bytes());
+    }
+    if let Some(post) = sfnt.table(b"post") {
+        set_table(&mut tables, b"post", subset_post(post, &order)?);
+    }
+    if let Some(kern) = sfnt.table(b"kern") {
+        match subset_kern(kern, &order)? {
+            Some(kern) => set_table(&mut tables, b"kern", kern),
+            None => tables.retain(|(tag, _)| tag != b"kern"),
+        }
+    }
+    Ok(write_sfnt(sfnt.flavor, tables))
+}
+
+/// The ID of a kept glyph in the subset
+fn new_id(order: &[u16], glyph: u16) -> u16 {
+    order.binary_search(&glyph).unwrap_or_default() as u16
+}
+
+/// Keep the names of the kept glyphs in a version 2 post table. Other
+/// versions either have no names or the standard Macintosh order, which
+/// doesn't hold after subsetting, and become version 3
+fn subset_post(post: &[u8], order: &[u16]) -> Result<Vec<u8>, Error> {
+    let mut output = Reader::new(post).bytes(32)?.to_vec();
+    if Reader::new(post).u32()? != 0x0002_0000 {
+        output[..4].copy_from_slice(&0x0003_0000_u32.to_be_bytes());
+        return Ok(output);
+    }
+    let mut reader = Reader::at(post, 32);
+    let count = reader.u16()? as usize;
+    let indices = (0..count)
+        .map(|_| reader.u16())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut names = vec![];
+    while reader.pos < post.len() {
+        let length = reader.u8()? as usize;
+        names.push(reader.bytes(length)?);
+    }
+    output.extend_from_slice(&(order.len() as u16).to_be_bytes());
+    let mut kept_names = vec![];
+    for id in order.iter() {
+        // the first 258 indices are the standard Macintosh names
+        let index = match indices.get(*id as usize).copied().unwrap_or(0) {
+            index @ 0..258 => index,
+            index => {
+                let name = names
+                    .get(index as usize - 258)
+                    .ok_or_else(|| invalid("Glyph name index out of range"))?;
+                kept_names.push(*name);
+                257 + kept_names.len() as u16
+            }
+        };
+        output.extend_from_slice(&index.to_be_bytes());
+    }
+    for name in kept_names {
+        output.push(name.len() as u8);
+        output.extend_from_slice(name);
+    }
+    Ok(output)
+}
+
+/// Keep the pairs of kept glyphs in the format 0 subtables of a version 0
+/// kern table. Other subtables are dropped, as well as Apple kern tables and
+/// tables left without pairs
+fn subset_kern(kern: &[u8], order: &[u16]) -> Result<Option<Vec<u8>>, Error> {
+    let mut reader = Reader::new(kern);
+    if reader.u16()? != 0 {
+        return Ok(None);
+    }
+    let count = reader.u16()?;
+    let mut subtables = vec![];
+    for _ in 0..count {
+        let start = reader.pos;
+        let _version = reader.u16()?;
+        let length = reader.u16()? as usize;
+        let coverage = reader.u16()?;
+        if coverage >> 8 != 0 {
+            reader = Reader::at(kern, start + length);
+            continue;
+        }
+        // large format 0 subtables overflow their length, read the pairs
+        let count = reader.u16()?;
+        reader.bytes(6)?;
+        let mut pairs = vec![];
+        for _ in 0..count {
+            let (left, right, value) = (reader.u16()?, reader.u16()?, reader.i16()?);
+            if let (Ok(left), Ok(right)) = (order.binary_search(&left), order.binary_search(&right))
+            {
+                pairs.push((left as u16, right as u16, value));
+            }
+        }
+        if !pairs.is_empty() {
+            subtables.push((coverage, pairs));
+        }
+    }
+    if subtables.is_empty() {
+        return Ok(None);
+    }
+
+    let mut output = vec![0, 0];
+    output.extend_from_slice(&(subtables.len() as u16).to_be_bytes());
+    for (coverage, mut pairs) in subtables {
+        pairs.sort_unstable_by_key(|(left, right, _)| (*left, *right));
+        let count = pairs.len();
+        let entry_selector = count.ilog2() as usize;
+        let search_range = 6 << entry_selector;
+        // fields of large subtables saturate, readers count the pairs
+        let header = [
+            0,
+            14 + 6 * count,
+            coverage as usize,
+            count,
+            search_range,
+            entry_selector,
+            6 * count - search_range,
+        ];
+        for value in header {
+            output.extend_from_slice(&(value.min(u16::MAX as usize) as u16).to_be_bytes());
+        }
+        for (left, right, value) in pairs {
+            output.extend_from_slice(&left.to_be_bytes());
+            output.extend_from_slice(&right.to_be_bytes());
+            output.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+    Ok(Some(output))
+}
+
+/// Write a Windows Unicode cmap table, with a format 4 subtable for the BMP
+/// and a format 12 one when `mapping` goes beyond it. The format 4 subtable
+/// is left out when it would overflow
+fn write_cmap(mapping: &BTreeMap<u32, u16>) -> Vec<u8> {
+    // runs of consecutive characters mapped to consecutive glyphs
+    let mut runs: Vec<(u32, u32, u16)> = vec![];
+    for (c, glyph) in mapping.iter() {
+        match runs.last_mut() {
+            Some((start, end, first))
+                if *end + 1 == *c && *first as u32 + (*c - *start) == *glyph as u32 =>
+            {
+                *end = *c
+            }
+            _ => runs.push((*c, *c, *glyph)),
+        }
+    }
+
+    let mut segments = runs
+        .iter()
+        .filter(|(start, _, _)| *start < 0xFFFF)
+        .map(|(start, end, first)| {
+            let start = *start as u16;
+            (start, (*end).min(0xFFFE) as u16, first.wrapping_sub(start))
+        })
+        .collect::<Vec<_>>();
+    segments.push((0xFFFF, 0xFFFF, 1));
+    let mut subtables = vec![];
+    let length = 16 + 8 * segments.len();
+    if length <= u16::MAX as usize {
+        let seg_count = segments.len() as u16;
+        let entry_selector = seg_count.ilog2() as u16;
+        let search_range = 2 << entry_selector;
+        let mut format4 = vec![];
+        for value in [
+            4,
+            length as u16,
+            0,
+            seg_count * 2,
+            search_range,
+            entry_selector,
+            seg_count * 2 - search_range,
+        ] {
+            format4.extend_from_slice(&value.to_be_bytes());
+        }
+        for (_, end, _) in segments.iter() {
+            format4.extend_from_slice(&end.to_be_bytes());
+        }
+        format4.extend_from_slice(&[0, 0]);
+        for (start, _, _) in segments.iter() {
+            format4.extend_from_slice(&start.to_be_bytes());
+        }
+        for (_, _, delta) in segments.iter() {
+            format4.extend_from_slice(&delta.to_be_bytes());
+        }
+        format4.resize(length, 0);
+        subtables.push((1_u16, format4));
+    }
+    if runs.last().is_some_and(|(_, end, _)| *end > 0xFFFF) || subtables.is_empty() {
+        let mut format12 = vec![0, 12, 0, 0];
+        format12.extend_from_slice(&(16 + 12 * runs.len() as u32).to_be_bytes());
+        format12.extend_from_slice(&[0; 4]);
+        format12.extend_from_slice(&(runs.len() as u32).to_be_bytes());
+        for (start, end, first) in runs.iter() {
+            for value in [*start, *end, *first as u32] {
+                format12.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+        subtables.push((10, format12));
+    }
+
+    let mut cmap = vec![0, 0];
+    cmap.extend_from_slice(&(subtables.len() as u16).to_be_bytes());
+    let mut offset = 4 + 8 * subtables.len();
+    for (encoding, data) in subtables.iter() {
+        cmap.extend_from_slice(&3_u16.to_be_bytes());
+        cmap.extend_from_slice(&encoding.to_be_bytes());
+        cmap.extend_from_slice(&(offset as u32).to_be_bytes());
+        offset += data.len();
+    }
+    for (_, data) in subtables {
+        cmap.extend_from_slice(&data);
+    }
+    cmap
+}
@@ -1,4 +1,4 @@
-//! A pure-Rust converter from WOFF to OTF for display.
+//! A pure-Rust converter from WOFF to OTF for display, and back.
 //!
 //! The `woff2otf` script was used as a reference: `https://github.com/hanikesn/woff2otf`
 //!
@@ -8,9 +8,16 @@
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
 use std::io::{self, Error, Read, Seek, SeekFrom, Write};
 use std::mem;
 
+use super::sanitize::tag_name;
+use super::sfnt::{table_checksum, Sfnt};
+use super::WoffBlocks;
+use crate::Error as FontError;
+
 /// "WOFF Header", http://people.mozilla.org/~jkew/woff/woff-spec-latest.html
 #[allow(dead_code)]
 struct WoffHeader {
@@ -54,7 +61,12 @@ struct OtfTableDirectoryEntry {
     orig_length: u32,
 }
 
-pub fn convert_woff_to_otf<R, W>(mut woff_reader: R, mut otf_writer: &mut W) -> Result<(), Error>
+/// Decode a WOFF font. Table data must lie after the table directory without
+/// overlapping, and decompress to the lengths the directory gives
+pub fn convert_woff_to_otf<R, W>(
+    mut woff_reader: R,
+    mut otf_writer: &mut W,
+) -> Result<(), FontError>
 where
     R: Read + Seek,
     W: Write + Seek,
@@ -76,6 +88,8 @@ where
     }
 
     // Read in headers.
+    let woff_length = woff_reader.seek(SeekFrom::End(0))?;
+    woff_reader.seek(SeekFrom::Start(0))?;
     let woff_header = WoffHeader {
         signature: woff_reader.read_u32::<BigEndian>()?,
         flavor: woff_reader.read_u32::<BigEndian>()?,
@@ -92,6 +106,14 @@ where
         priv_length: woff_reader.read_u32::<BigEndian>()?,
     };
 
+    if woff_header.signature != u32::from_be_bytes(*b"wOFF") {
+        return Err(FontError::InvalidDirectory("Not a WOFF file"));
+    }
+    // the sfnt header fields would overflow with more tables
+    if woff_header.num_tables == 0 || woff_header.num_tables > 4095 {
+        return Err(FontError::InvalidDirectory("Invalid number of tables"));
+    }
+
     let mut woff_table_directory_entries = Vec::with_capacity(woff_header.num_tables as usize);
     for _ in 0..woff_header.num_tables {
         woff_table_directory_entries.push(WoffTableDirectoryEntry {
@@ -103,6 +125,41 @@ where
         })
     }
 
+    // Check that table data lies after the directory without overlapping.
+    let directory_end = 44 + 20 * woff_header.num_tables as u64;
+    let mut ranges = Vec::with_capacity(woff_table_directory_entries.len());
+    for entry in woff_table_directory_entries.iter() {
+        let tag = tag_name(&entry.tag.to_be_bytes());
+        let start = entry.offset as u64;
+        let end = start + entry.comp_length as u64;
+        if start < directory_end || end > woff_length {
+            return Err(FontError::TableOutOfBounds { tag });
+        }
+        if entry.comp_length > entry.orig_length {
+            return Err(FontError::InvalidTable {
+                tag,
+                reason: "Compressed data is longer than the table",
+            });
+        }
+        ranges.push((start..end, tag));
+    }
+    ranges.sort_by_key(|(range, _)| range.start);
+    for pair in ranges.windows(2) {
+        if pair[0].0.end > pair[1].0.start {
+            return Err(FontError::TablesOverlap {
+                tag: pair[0].1.clone(),
+                other: pair[1].1.clone(),
+            });
+        }
+    }
+    let total_length = woff_table_directory_entries
+        .iter()
+        .map(|entry| (entry.orig_length as u64).next_multiple_of(4))
+        .sum::<u64>();
+    if 12 + 16 * woff_header.num_tables as u64 + total_length > u32::MAX as u64 {
+        return Err(FontError::InvalidDirectory("Tables are too long"));
+    }
+
     // Write out headers.
     let num_tables_previous_power_of_two = previous_power_of_two(woff_header.num_tables);
     let otf_search_range = num_tables_previous_power_of_two * 16;
@@ -115,21 +172,11 @@ where
         range_shift: woff_header.num_tables * 16 - otf_search_range,
     };
 
-    otf_writer
-        .write_u32::<BigEndian>(otf_header.flavor)
-        .unwrap();
-    otf_writer
-        .write_u16::<BigEndian>(otf_header.num_tables)
-        .unwrap();
-    otf_writer
-        .write_u16::<BigEndian>(otf_header.search_range)
-        .unwrap();
-    otf_writer
-        .write_u16::<BigEndian>(otf_header.entry_selector)
-        .unwrap();
-    otf_writer
-        .write_u16::<BigEndian>(otf_header.range_shift)
-        .unwrap();
+    otf_writer.write_u32::<BigEndian>(otf_header.flavor)?;
+    otf_writer.write_u16::<BigEndian>(otf_header.num_tables)?;
+    otf_writer.write_u16::<BigEndian>(otf_header.search_range)?;
+    otf_writer.write_u16::<BigEndian>(otf_header.entry_selector)?;
+    otf_writer.write_u16::<BigEndian>(otf_header.range_shift)?;
 
     let mut otf_table_directory_entries = Vec::new();
     let mut otf_offset = tell(&mut otf_writer)? as u32
@@ -141,18 +188,10 @@ where
             offset: otf_offset,
             orig_length: woff_table_directory_entry.orig_length,
         };
-        otf_writer
-            .write_u32::<BigEndian>(otf_table_directory_entry.tag)
-            .unwrap();
-        otf_writer
-            .write_u32::<BigEndian>(otf_table_directory_entry.orig_checksum)
-            .unwrap();
-        otf_writer
-            .write_u32::<BigEndian>(otf_table_directory_entry.offset)
-            .unwrap();
-        otf_writer
-            .write_u32::<BigEndian>(otf_table_directory_entry.orig_length)
-            .unwrap();
+        otf_writer.write_u32::<BigEndian>(otf_table_directory_entry.tag)?;
+        otf_writer.write_u32::<BigEndian>(otf_table_directory_entry.orig_checksum)?;
+        otf_writer.write_u32::<BigEndian>(otf_table_directory_entry.offset)?;
+        otf_writer.write_u32::<BigEndian>(otf_table_directory_entry.orig_length)?;
 
         otf_offset += otf_table_directory_entry.orig_length;
         if otf_offset % 4 != 0 {
@@ -166,18 +205,25 @@ where
         .iter()
         .zip(otf_table_directory_entries.iter())
     {
-        debug_assert!(otf_table_directory_entry.offset as u64 == tell(&mut otf_writer)?);
         woff_reader.seek(SeekFrom::Start(woff_table_directory_entry.offset as u64))?;
-        if woff_table_directory_entry.comp_length != woff_table_directory_entry.orig_length {
-            let decoder = ZlibDecoder::new(woff_reader);
-            let mut decoder = decoder.take(woff_table_directory_entry.orig_length as u64);
-            io::copy(&mut decoder, &mut otf_writer)?;
-            woff_reader = decoder.into_inner().into_inner();
-        } else {
-            let mut limited_woff_reader =
-                (&mut woff_reader).take(woff_table_directory_entry.orig_length as u64);
-            io::copy(&mut limited_woff_reader, &mut otf_writer)?;
-        };
+        let written =
+            if woff_table_directory_entry.comp_length != woff_table_directory_entry.orig_length {
+                let decoder = ZlibDecoder::new(woff_reader);
+                let mut decoder = decoder.take(woff_table_directory_entry.orig_length as u64);
+                let written = io::copy(&mut decoder, &mut otf_writer)?;
+                woff_reader = decoder.into_inner().into_inner();
+                written
+            } else {
+                let mut limited_woff_reader =
+                    (&mut woff_reader).take(woff_table_directory_entry.orig_length as u64);
+                io::copy(&mut limited_woff_reader, &mut otf_writer)?
+            };
+        if written != woff_table_directory_entry.orig_length as u64 {
+            return Err(FontError::InvalidTable {
+                tag: tag_name(&woff_table_directory_entry.tag.to_be_bytes()),
+                reason: "Table data is shorter than its length",
+            });
+        }
         woff_reader.seek(SeekFrom::Start(
             (woff_table_directory_entry.offset + woff_table_directory_entry.comp_length) as u64,
         ))?;
@@ -194,3 +240,90 @@ where
 
     Ok(())
 }
+
+/// Encode a font into WOFF, compressing the tables that zlib makes smaller.
+/// The metadata block is always compressed
+pub fn convert_otf_to_woff(otf: &[u8], blocks: &WoffBlocks) -> Result<Vec<u8>, Error> {
+    let zlib = |data: &[u8]| {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(data)?;
+        encoder.finish()
+    };
+    let sfnt = Sfnt::parse(otf, 0)?;
+    let mut tables = Vec::with_capacity(sfnt.tables.len());
+    for (tag, data) in sfnt.tables.iter() {
+        let compressed = zlib(data)?;
+        let stored = if compressed.len() < data.len() {
+            compressed
+        } else {
+            data.to_vec()
+        };
+        tables.push((tag, data, stored));
+    }
+    let total_sfnt_size = 12
+        + 16 * tables.len()
+        + tables
+            .iter()
+            .map(|(_, data, _)| data.len().next_multiple_of(4))
+            .sum::<usize>();
+
+    // the header and the table directory
+    let mut offset = 44 + 20 * tables.len();
+    let mut directory = Vec::with_capacity(20 * tables.len());
+    for (tag, data, stored) in tables.iter() {
+        directory.write_all(&tag[..])?;
+        directory.write_u32::<BigEndian>(offset as u32)?;
+        directory.write_u32::<BigEndian>(stored.len() as u32)?;
+        directory.write_u32::<BigEndian>(data.len() as u32)?;
+        directory.write_u32::<BigEndian>(table_checksum(tag, data))?;
+        offset += stored.len().next_multiple_of(4);
+    }
+
+    // the metadata and private blocks follow the tables, 4-byte aligned
+    let metadata = blocks
+        .metadata
+        .as_ref()
+        .map(|metadata| zlib(metadata.as_bytes()))
+        .transpose()?;
+    let mut block_offsets = [0; 2];
+    let mut length = offset;
+    for (i, block) in [metadata.as_deref(), blocks.private.as_deref()]
+        .into_iter()
+        .enumerate()
+    {
+        if let Some(block) = block {
+            block_offsets[i] = length.next_multiple_of(4);
+            length = block_offsets[i] + block.len();
+        }
+    }
+
+    let mut woff = Vec::with_capacity(length);
+    woff.write_all(b"wOFF")?;
+    woff.write_u32::<BigEndian>(sfnt.flavor)?;
+    woff.write_u32::<BigEndian>(length as u32)?;
+    woff.write_u16::<BigEndian>(tables.len() as u16)?;
+    woff.write_u16::<BigEndian>(0)?;
+    woff.write_u32::<BigEndian>(total_sfnt_size as u32)?;
+    woff.write_u16::<BigEndian>(1)?;
+    woff.write_u16::<BigEndian>(0)?;
+    woff.write_u32::<BigEndian>(block_offsets[0] as u32)?;
+    woff.write_u32::<BigEndian>(metadata.as_ref().map_or(0, |m| m.len()) as u32)?;
+    woff.write_u32::<BigEndian>(blocks.metadata.as_ref().map_or(0, |m| m.len()) as u32)?;
+    woff.write_u32::<BigEndian>(block_offsets[1] as u32)?;
+    woff.write_u32::<BigEndian>(blocks.private.as_ref().map_or(0, |p| p.len()) as u32)?;
+    woff.write_all(&directory)?;
+    for (_, _, stored) in tables {
+        woff.write_all(&stored)?;
+        woff.resize(woff.len().next_multiple_of(4), 0);
+    }
+    for (offset, block) in block_offsets
+        .into_iter()
+        .zip([metadata.as_deref(), blocks.private.as_deref()])
+    {
+        if let Some(block) = block {
+            woff.resize(offset, 0);
+            woff.write_all(block)?;
+        }
+    }
+    Ok(woff)
+}
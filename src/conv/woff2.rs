@@ -0,0 +1,766 @@
+//! A pure-Rust converter from WOFF2 files to TTF, OTF or TTC.
+//!
+//! Both single fonts and collections are decoded, reconstructing the
+//! transformed `glyf`, `loca` and `hmtx` tables of every member. With the
+//! `encode` feature, single fonts are encoded into WOFF2 too, with their
+//! `glyf` and `loca` tables transformed.
+//!
+//! See the WOFF2 spec: `https://www.w3.org/TR/WOFF2/`
+
+use std::io::{Error, Read};
+
+#[cfg(feature = "encode")]
+use brotli::enc::backward_references::BrotliEncoderMode;
+
+use super::glyf::encode_points;
+#[cfg(feature = "encode")]
+use super::glyf::{glyph_ranges, Glyph};
+use super::sanitize::sanitize_woff2_directory;
+use super::sfnt::{invalid, table_checksum, Reader};
+#[cfg(feature = "encode")]
+use super::{sfnt::Sfnt, WoffBlocks};
+use crate::Error as FontError;
+
+const TTCF: u32 = u32::from_be_bytes(*b"ttcf");
+
+/// Most tables a font can list, so that its table directory fields fit in
+/// 16 bits
+const MAX_TABLES: usize = 4095;
+
+/// Largest decompressed table data accepted, whatever the header claims
+const MAX_DATA_SIZE: usize = 256 << 20;
+
+/// Tags of the table directory flags, "Known Table Tags"
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+impl Reader<'_> {
+    /// "UIntBase128 Data Type"
+    fn base128(&mut self) -> Result<u32, Error> {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let byte = self.u8()?;
+            if i == 0 && byte == 0x80 {
+                return Err(invalid("UIntBase128 has leading zeros"));
+            }
+            if value & 0xFE00_0000 != 0 {
+                return Err(invalid("UIntBase128 overflows"));
+            }
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("UIntBase128 is longer than 5 bytes"))
+    }
+
+    /// "255UInt16 Data Type"
+    fn u255(&mut self) -> Result<u16, Error> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => self.u8()? as u16 + 253 * 2,
+            255 => self.u8()? as u16 + 253,
+            code => code as u16,
+        })
+    }
+}
+
+struct TableEntry {
+    tag: [u8; 4],
+    transformed: bool,
+    /// Range of the table in the decompressed stream
+    offset: usize,
+    length: usize,
+}
+
+struct FontEntry {
+    flavor: u32,
+    tables: Vec<usize>,
+}
+
+/// Tables rebuilt from a transformed glyf table
+struct GlyfTables {
+    glyf: Vec<u8>,
+    loca: Vec<u8>,
+    /// xMin of every glyph, for the hmtx transform
+    x_mins: Vec<i16>,
+}
+
+/// Decode a WOFF2 file into a TrueType collection, or into a single font if
+/// the file doesn't hold a collection
+pub fn convert_woff2_to_ttc(buf: &[u8]) -> Result<Vec<u8>, FontError> {
+    let mut reader = Reader::new(buf);
+    if reader.bytes(4)? != b"wOF2" {
+        return Err(invalid("Not a WOFF2 file").into());
+    }
+    let flavor = reader.u32()?;
+    let _length = reader.u32()?;
+    let num_tables = reader.u16()? as usize;
+    let _reserved = reader.u16()?;
+    let _total_sfnt_size = reader.u32()?;
+    let total_compressed_size = reader.u32()? as usize;
+    // version, metadata and private data
+    reader.bytes(4 + 12 + 8)?;
+    if num_tables > MAX_TABLES {
+        return Err(invalid("WOFF2 file has too many tables").into());
+    }
+
+    let mut tables = vec![];
+    let mut offset: usize = 0;
+    for _ in 0..num_tables {
+        let flags = reader.u8()?;
+        let tag = match flags & 0x3F {
+            63 => reader.bytes(4)?.try_into().unwrap(),
+            index => *KNOWN_TAGS[index as usize],
+        };
+        let version = flags >> 6;
+        let orig_length = reader.base128()? as usize;
+        // glyf and loca use version 3 as the null transform, other tables
+        // version 0
+        let transformed = if &tag == b"glyf" || &tag == b"loca" {
+            version != 3
+        } else {
+            version != 0
+        };
+        let length = if transformed {
+            reader.base128()? as usize
+        } else {
+            orig_length
+        };
+        tables.push(TableEntry {
+            tag,
+            transformed,
+            offset,
+            length,
+        });
+        offset = offset
+            .checked_add(length)
+            .filter(|offset| *offset <= MAX_DATA_SIZE)
+            .ok_or_else(|| invalid("WOFF2 table data is too large"))?;
+    }
+
+    let (ttc_version, fonts) = if flavor == TTCF {
+        let ttc_version = reader.u32()?;
+        let num_fonts = reader.u255()?;
+        let mut fonts = vec![];
+        for _ in 0..num_fonts {
+            let num_tables = reader.u255()?;
+            if num_tables as usize > MAX_TABLES {
+                return Err(invalid("Collection font has too many tables").into());
+            }
+            let flavor = reader.u32()?;
+            let tables = (0..num_tables)
+                .map(|_| reader.u255().map(|index| index as usize))
+                .collect::<Result<Vec<_>, _>>()?;
+            fonts.push(FontEntry { flavor, tables });
+        }
+        (Some(ttc_version), fonts)
+    } else {
+        let tables = (0..tables.len()).collect();
+        (None, vec![FontEntry { flavor, tables }])
+    };
+    // nothing is decompressed before the directory is known to be sane
+    let font_tables = fonts.iter().map(|f| &f.tables[..]).collect::<Vec<_>>();
+    sanitize_woff2_directory(tables.len(), &font_tables)?;
+
+    // the declared lengths bound the decompressed stream, not the other way
+    let mut data = vec![];
+    brotli_decompressor::Decompressor::new(reader.bytes(total_compressed_size)?, 4096)
+        .take(offset as u64)
+        .read_to_end(&mut data)?;
+    if data.len() < offset {
+        return Err(invalid("WOFF2 table data is truncated").into());
+    }
+
+    // reconstruct the tables, transformed ones are rebuilt per font
+    let mut decoded: Vec<Option<Vec<u8>>> = tables
+        .iter()
+        .map(|t| (!t.transformed).then(|| data[t.offset..t.offset + t.length].to_vec()))
+        .collect();
+    for font in fonts.iter() {
+        let find = |tag: &[u8; 4]| font.tables.iter().copied().find(|i| &tables[*i].tag == tag);
+        let mut x_mins = None;
+        if let (Some(glyf), Some(loca)) = (find(b"glyf"), find(b"loca")) {
+            if tables[glyf].transformed {
+                let table = &tables[glyf];
+                let tables = decode_glyf(&data[table.offset..table.offset + table.length])?;
+                decoded[glyf] = Some(tables.glyf);
+                decoded[loca] = Some(tables.loca);
+                x_mins = Some(tables.x_mins);
+            }
+        }
+        if let Some(hmtx) = find(b"hmtx").filter(|i| tables[*i].transformed) {
+            let hhea = find(b"hhea")
+                .and_then(|i| decoded[i].as_ref())
+                .ok_or_else(|| invalid("Transformed hmtx without hhea"))?;
+            let x_mins = x_mins
+                .as_ref()
+                .ok_or_else(|| invalid("Transformed hmtx without transformed glyf"))?;
+            let num_h_metrics = Reader::new(hhea.get(34..).unwrap_or_default()).u16()?;
+            let table = &tables[hmtx];
+            decoded[hmtx] = Some(decode_hmtx(
+                &data[table.offset..table.offset + table.length],
+                num_h_metrics as usize,
+                x_mins,
+            )?);
+        }
+    }
+
+    // lay out the headers, then every table once, 4-byte aligned
+    let mut header_size = match ttc_version {
+        Some(version) if version >= 0x0002_0000 => 24 + 4 * fonts.len(),
+        Some(_) => 12 + 4 * fonts.len(),
+        None => 0,
+    };
+    let font_offsets = fonts
+        .iter()
+        .map(|f| {
+            let offset = header_size;
+            header_size += 12 + 16 * f.tables.len();
+            offset
+        })
+        .collect::<Vec<_>>();
+    let mut output = vec![0_u8; header_size];
+    let mut table_offsets = vec![None; tables.len()];
+    for index in fonts.iter().flat_map(|f| f.tables.iter()) {
+        if table_offsets[*index].is_some() {
+            continue;
+        }
+        let table = decoded[*index]
+            .as_ref()
+            .ok_or_else(|| invalid("Transformed table can't be reconstructed"))?;
+        table_offsets[*index] = Some(output.len());
+        output.extend_from_slice(table);
+        output.resize(output.len().next_multiple_of(4), 0);
+    }
+
+    if let Some(version) = ttc_version {
+        output[0..4].copy_from_slice(&TTCF.to_be_bytes());
+        output[4..8].copy_from_slice(&version.to_be_bytes());
+        output[8..12].copy_from_slice(&(fonts.len() as u32).to_be_bytes());
+        for (i, offset) in font_offsets.iter().enumerate() {
+            output[12 + i * 4..16 + i * 4].copy_from_slice(&(*offset as u32).to_be_bytes());
+        }
+    }
+    for (font, offset) in fonts.iter().zip(font_offsets) {
+        let mut indices = font.tables.clone();
+        indices.sort_by_key(|i| tables[*i].tag);
+        let num_tables = indices.len() as u16;
+        let entry_selector = num_tables.checked_ilog2().unwrap_or(0) as u16;
+        let search_range: u16 = (1 << entry_selector) * 16;
+        let mut header = vec![];
+        header.extend_from_slice(&font.flavor.to_be_bytes());
+        header.extend_from_slice(&num_tables.to_be_bytes());
+        header.extend_from_slice(&search_range.to_be_bytes());
+        header.extend_from_slice(&entry_selector.to_be_bytes());
+        // num_tables is at most MAX_TABLES, 16 times it fits in 16 bits. The
+        // search range exceeds it for fonts without tables
+        let range_shift = (num_tables * 16).saturating_sub(search_range);
+        header.extend_from_slice(&range_shift.to_be_bytes());
+        for index in indices {
+            let table = decoded[index].as_ref().unwrap();
+            header.extend_from_slice(&tables[index].tag);
+            header.extend_from_slice(&table_checksum(&tables[index].tag, table).to_be_bytes());
+            header.extend_from_slice(&(table_offsets[index].unwrap() as u32).to_be_bytes());
+            header.extend_from_slice(&(table.len() as u32).to_be_bytes());
+        }
+        output[offset..offset + header.len()].copy_from_slice(&header);
+    }
+    Ok(output)
+}
+
+/// Rebuild the glyf and loca tables from a transformed glyf table, "Transformed
+/// glyf table format"
+fn decode_glyf(data: &[u8]) -> Result<GlyfTables, FontError> {
+    let mut reader = Reader::new(data);
+    let _version = reader.u16()?;
+    let option_flags = reader.u16()?;
+    let num_glyphs = reader.u16()? as usize;
+    let index_format = reader.u16()?;
+    let mut sizes = [0; 7];
+    for size in sizes.iter_mut() {
+        *size = reader.u32()? as usize;
+    }
+    let mut streams = sizes
+        .iter()
+        .map(|size| reader.bytes(*size).map(Reader::new))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
+    let mut n_contours = streams.next().unwrap();
+    let mut n_points = streams.next().unwrap();
+    let mut flags = streams.next().unwrap();
+    let mut glyphs = streams.next().unwrap();
+    let mut composites = streams.next().unwrap();
+    let mut bboxes = streams.next().unwrap();
+    let mut instructions = streams.next().unwrap();
+    let bbox_bitmap = bboxes.bytes(((num_glyphs + 31) >> 5) << 2)?;
+    let overlap_bitmap = if option_flags & 1 != 0 {
+        Some(reader.bytes((num_glyphs + 7) >> 3)?)
+    } else {
+        None
+    };
+    let bit = |bitmap: &[u8], i: usize| bitmap[i >> 3] & (0x80 >> (i & 7)) != 0;
+
+    let mut glyf = vec![];
+    let mut offsets = vec![];
+    let mut x_mins = vec![];
+    for i in 0..num_glyphs {
+        offsets.push(glyf.len());
+        let has_bbox = bit(bbox_bitmap, i);
+        let contours = n_contours.i16()?;
+        if contours == 0 {
+            if has_bbox {
+                return Err(invalid("Empty glyph has a bounding box").into());
+            }
+            x_mins.push(0);
+            continue;
+        }
+        if contours > 0 {
+            let mut end_points = vec![];
+            let mut total: u16 = 0;
+            for _ in 0..contours {
+                total = total
+                    .checked_add(n_points.u255()?)
+                    .ok_or_else(|| invalid("Glyph has too many points"))?;
+                end_points.push(
+                    total
+                        .checked_sub(1)
+                        .ok_or_else(|| invalid("Contour has no points"))?,
+                );
+            }
+            let mut points = Vec::with_capacity(total as usize);
+            let (mut x, mut y) = (0_i32, 0_i32);
+            for _ in 0..total {
+                let flag = flags.u8()?;
+                let (dx, dy) = decode_triplet(flag & 0x7F, &mut glyphs)?;
+                x += dx;
+                y += dy;
+                points.push((x, y, flag & 0x80 == 0));
+            }
+            let instruction_length = glyphs.u255()?;
+            let instruction_data = instructions.bytes(instruction_length as usize)?;
+            let bbox = if has_bbox {
+                [bboxes.i16()?, bboxes.i16()?, bboxes.i16()?, bboxes.i16()?]
+            } else {
+                let xs = points.iter().map(|p| p.0);
+                let ys = points.iter().map(|p| p.1);
+                [
+                    xs.clone().min().unwrap_or(0) as i16,
+                    ys.clone().min().unwrap_or(0) as i16,
+                    xs.max().unwrap_or(0) as i16,
+                    ys.max().unwrap_or(0) as i16,
+                ]
+            };
+            x_mins.push(bbox[0]);
+            glyf.extend_from_slice(&contours.to_be_bytes());
+            bbox.iter()
+                .for_each(|v| glyf.extend_from_slice(&v.to_be_bytes()));
+            end_points
+                .iter()
+                .for_each(|v| glyf.extend_from_slice(&v.to_be_bytes()));
+            glyf.extend_from_slice(&instruction_length.to_be_bytes());
+            glyf.extend_from_slice(instruction_data);
+            let overlap = overlap_bitmap.is_some_and(|bitmap| bit(bitmap, i));
+            encode_points(&points, overlap, &mut glyf);
+        } else {
+            if !has_bbox {
+                return Err(invalid("Composite glyph has no bounding box").into());
+            }
+            let bbox = [bboxes.i16()?, bboxes.i16()?, bboxes.i16()?, bboxes.i16()?];
+            x_mins.push(bbox[0]);
+            let start = composites.pos;
+            let has_instructions = skip_components(&mut composites)?;
+            glyf.extend_from_slice(&(-1_i16).to_be_bytes());
+            bbox.iter()
+                .for_each(|v| glyf.extend_from_slice(&v.to_be_bytes()));
+            glyf.extend_from_slice(&composites.data[start..composites.pos]);
+            if has_instructions {
+                let instruction_length = glyphs.u255()?;
+                glyf.extend_from_slice(&instruction_length.to_be_bytes());
+                glyf.extend_from_slice(instructions.bytes(instruction_length as usize)?);
+            }
+        }
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+    }
+    offsets.push(glyf.len());
+
+    let loca = if index_format == 0 {
+        offsets
+            .iter()
+            .map(|o| u16::try_from(o / 2).map(u16::to_be_bytes))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| FontError::InvalidTable {
+                tag: "glyf".to_string(),
+                reason: "Glyph offsets don't fit the short loca format",
+            })?
+            .concat()
+    } else {
+        offsets
+            .iter()
+            .flat_map(|o| (*o as u32).to_be_bytes())
+            .collect()
+    };
+    Ok(GlyfTables { glyf, loca, x_mins })
+}
+
+/// Skip the components of a composite glyph, returning whether the glyph has
+/// instructions
+fn skip_components(reader: &mut Reader) -> Result<bool, Error> {
+    let mut has_instructions = false;
+    loop {
+        let flags = reader.u16()?;
+        let args = if flags & 0x0001 != 0 { 4 } else { 2 };
+        let transform = if flags & 0x0008 != 0 {
+            2
+        } else if flags & 0x0040 != 0 {
+            4
+        } else if flags & 0x0080 != 0 {
+            8
+        } else {
+            0
+        };
+        reader.bytes(2 + args + transform)?;
+        has_instructions |= flags & 0x0100 != 0;
+        if flags & 0x0020 == 0 {
+            return Ok(has_instructions);
+        }
+    }
+}
+
+/// "Triplet Encoding" of point coordinates
+fn decode_triplet(flag: u8, glyphs: &mut Reader) -> Result<(i32, i32), Error> {
+    let with_sign = |flag: u8, value: i32| if flag & 1 != 0 { value } else { -value };
+    let flag_value = flag as i32;
+    Ok(match flag {
+        0..=9 => {
+            let b = glyphs.u8()? as i32;
+            (0, with_sign(flag, ((flag_value & 14) << 7) + b))
+        }
+        10..=19 => {
+            let b = glyphs.u8()? as i32;
+            (with_sign(flag, (((flag_value - 10) & 14) << 7) + b), 0)
+        }
+        20..=83 => {
+            let b0 = flag_value - 20;
+            let b1 = glyphs.u8()? as i32;
+            (
+                with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)),
+                with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)),
+            )
+        }
+        84..=119 => {
+            let b0 = flag_value - 84;
+            let b = glyphs.bytes(2)?;
+            (
+                with_sign(flag, 1 + ((b0 / 12) << 8) + b[0] as i32),
+                with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b[1] as i32),
+            )
+        }
+        120..=123 => {
+            let b = glyphs.bytes(3)?;
+            (
+                with_sign(flag, ((b[0] as i32) << 4) + (b[1] as i32 >> 4)),
+                with_sign(flag >> 1, ((b[1] as i32 & 0x0F) << 8) + b[2] as i32),
+            )
+        }
+        _ => {
+            let b = glyphs.bytes(4)?;
+            (
+                with_sign(flag, ((b[0] as i32) << 8) + b[1] as i32),
+                with_sign(flag >> 1, ((b[2] as i32) << 8) + b[3] as i32),
+            )
+        }
+    })
+}
+
+/// Rebuild the hmtx table from a transformed one, "Transformed hmtx table
+/// format". Left side bearings left out equal the xMin of their glyph
+fn decode_hmtx(data: &[u8], num_h_metrics: usize, x_mins: &[i16]) -> Result<Vec<u8>, Error> {
+    let num_glyphs = x_mins.len();
+    if num_h_metrics == 0 || num_h_metrics > num_glyphs {
+        return Err(invalid("Invalid number of horizontal metrics"));
+    }
+    let mut reader = Reader::new(data);
+    let flags = reader.u8()?;
+    let advances = (0..num_h_metrics)
+        .map(|_| reader.u16())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut lsbs = Vec::with_capacity(num_glyphs);
+    for (range, absent) in [
+        (0..num_h_metrics, flags & 1 != 0),
+        (num_h_metrics..num_glyphs, flags & 2 != 0),
+    ] {
+        for i in range {
+            lsbs.push(if absent { x_mins[i] } else { reader.i16()? });
+        }
+    }
+    let mut hmtx = Vec::with_capacity(num_h_metrics * 4 + (num_glyphs - num_h_metrics) * 2);
+    for (i, lsb) in lsbs.iter().enumerate() {
+        if let Some(advance) = advances.get(i) {
+            hmtx.extend_from_slice(&advance.to_be_bytes());
+        }
+        hmtx.extend_from_slice(&lsb.to_be_bytes());
+    }
+    Ok(hmtx)
+}
+
+/// Write a "UIntBase128 Data Type"
+#[cfg(feature = "encode")]
+fn write_base128(value: u32, woff2: &mut Vec<u8>) {
+    let length = (1..5).find(|i| value >> (7 * i) == 0).unwrap_or(5);
+    for i in (0..length).rev() {
+        let byte = (value >> (7 * i)) as u8 & 0x7F;
+        woff2.push(if i > 0 { byte | 0x80 } else { byte });
+    }
+}
+
+/// Write a "255UInt16 Data Type"
+#[cfg(feature = "encode")]
+fn write_u255(value: u16, stream: &mut Vec<u8>) {
+    match value {
+        0..=252 => stream.push(value as u8),
+        253..=505 => stream.extend_from_slice(&[255, (value - 253) as u8]),
+        506..=761 => stream.extend_from_slice(&[254, (value - 506) as u8]),
+        _ => {
+            stream.push(253);
+            stream.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Encode a font into WOFF2. The glyf and loca tables are transformed, other
+/// tables are stored as they are
+#[cfg(feature = "encode")]
+pub fn convert_otf_to_woff2(otf: &[u8], blocks: &WoffBlocks) -> Result<Vec<u8>, Error> {
+    let sfnt = Sfnt::parse(otf, 0)?;
+    let glyf = match (
+        sfnt.table(b"glyf"),
+        sfnt.table(b"loca"),
+        sfnt.table(b"head"),
+        sfnt.table(b"maxp"),
+    ) {
+        (Some(glyf), Some(loca), Some(head), Some(maxp)) => {
+            let transformed = encode_glyf(glyf, loca, head, maxp)?;
+            // the lengths of the tables as decoders rebuild them
+            let tables = decode_glyf(&transformed).map_err(|e| Error::other(e.to_string()))?;
+            Some((transformed, tables.glyf.len(), tables.loca.len()))
+        }
+        _ => None,
+    };
+
+    // loca has to follow glyf when they are transformed
+    let mut order = sfnt.tables.iter().collect::<Vec<_>>();
+    if glyf.is_some() {
+        let loca = order.iter().position(|(tag, _)| tag == b"loca").unwrap();
+        let loca = order.remove(loca);
+        let glyf = order.iter().position(|(tag, _)| tag == b"glyf").unwrap();
+        order.insert(glyf + 1, loca);
+    }
+    let mut directory = vec![];
+    let mut data = vec![];
+    let mut total_sfnt_size = 12 + 16 * order.len();
+    for (tag, table) in order {
+        let known = KNOWN_TAGS.iter().position(|known| *known == tag);
+        let is_glyf = tag == b"glyf" || tag == b"loca";
+        let (version, orig_length, stored) = match &glyf {
+            Some((transformed, glyf_length, _)) if tag == b"glyf" => {
+                (0, *glyf_length, transformed.as_slice())
+            }
+            Some((_, _, loca_length)) if tag == b"loca" => (0, *loca_length, &[][..]),
+            // glyf and loca use version 3 as the null transform
+            _ if is_glyf => (3 << 6, table.len(), *table),
+            _ => (0, table.len(), *table),
+        };
+        directory.push(known.unwrap_or(63) as u8 | version);
+        if known.is_none() {
+            directory.extend_from_slice(tag);
+        }
+        write_base128(orig_length as u32, &mut directory);
+        if is_glyf && version == 0 {
+            write_base128(stored.len() as u32, &mut directory);
+        }
+        data.extend_from_slice(stored);
+        total_sfnt_size += orig_length.next_multiple_of(4);
+    }
+    let brotli = |data: &[u8], mode| {
+        let mut compressed = vec![];
+        let params = brotli::enc::BrotliEncoderParams {
+            mode,
+            ..Default::default()
+        };
+        brotli::BrotliCompress(&mut &data[..], &mut compressed, &params).map(|_| compressed)
+    };
+    let compressed = brotli(&data, BrotliEncoderMode::BROTLI_MODE_FONT)?;
+    let metadata = blocks
+        .metadata
+        .as_ref()
+        .map(|metadata| brotli(metadata.as_bytes(), BrotliEncoderMode::BROTLI_MODE_TEXT))
+        .transpose()?;
+
+    // the metadata and private blocks follow the font data, 4-byte aligned
+    let mut length = (48 + directory.len() + compressed.len()).next_multiple_of(4);
+    let mut block_offsets = [0; 2];
+    for (i, block) in [metadata.as_deref(), blocks.private.as_deref()]
+        .into_iter()
+        .enumerate()
+    {
+        if let Some(block) = block {
+            block_offsets[i] = length.next_multiple_of(4);
+            length = block_offsets[i] + block.len();
+        }
+    }
+
+    let mut woff2 = Vec::with_capacity(length);
+    woff2.extend_from_slice(b"wOF2");
+    woff2.extend_from_slice(&sfnt.flavor.to_be_bytes());
+    woff2.extend_from_slice(&(length as u32).to_be_bytes());
+    woff2.extend_from_slice(&(sfnt.tables.len() as u16).to_be_bytes());
+    woff2.extend_from_slice(&[0, 0]);
+    woff2.extend_from_slice(&(total_sfnt_size as u32).to_be_bytes());
+    woff2.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+    woff2.extend_from_slice(&[0, 1, 0, 0]);
+    for value in [
+        block_offsets[0],
+        metadata.as_ref().map_or(0, |m| m.len()),
+        blocks.metadata.as_ref().map_or(0, |m| m.len()),
+        block_offsets[1],
+        blocks.private.as_ref().map_or(0, |p| p.len()),
+    ] {
+        woff2.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+    woff2.extend_from_slice(&directory);
+    woff2.extend_from_slice(&compressed);
+    woff2.resize(woff2.len().next_multiple_of(4), 0);
+    for (offset, block) in block_offsets
+        .into_iter()
+        .zip([metadata.as_deref(), blocks.private.as_deref()])
+    {
+        if let Some(block) = block {
+            woff2.resize(offset, 0);
+            woff2.extend_from_slice(block);
+        }
+    }
+    Ok(woff2)
+}
+
+/// Transform the glyf and loca tables into a transformed glyf table,
+/// "Transformed glyf table format". Bounding boxes of simple glyphs are only
+/// stored when they differ from the one of their points
+#[cfg(feature = "encode")]
+fn encode_glyf(glyf: &[u8], loca: &[u8], head: &[u8], maxp: &[u8]) -> Result<Vec<u8>, Error> {
+    let index_format = Reader::new(head.get(50..).unwrap_or_default()).u16()?;
+    let num_glyphs = Reader::new(maxp.get(4..).unwrap_or_default()).u16()? as usize;
+    let ranges = glyph_ranges(loca, index_format != 0, num_glyphs, glyf.len())?;
+
+    let mut streams: [Vec<u8>; 7] = Default::default();
+    let [n_contours, n_points, flags, glyphs, composites, bboxes, instructions] = &mut streams;
+    let mut bbox_bitmap = vec![0; ((num_glyphs + 31) >> 5) << 2];
+    let mut overlap_bitmap = vec![0; (num_glyphs + 7) >> 3];
+    let set = |bitmap: &mut [u8], i: usize| bitmap[i >> 3] |= 0x80 >> (i & 7);
+    for (i, range) in ranges.into_iter().enumerate() {
+        let data = &glyf[range];
+        match Glyph::parse(data)? {
+            Glyph::Simple(glyph) if !glyph.points.is_empty() => {
+                n_contours.extend_from_slice(&(glyph.end_points.len() as i16).to_be_bytes());
+                for contour in glyph.contours() {
+                    write_u255(contour.len() as u16, n_points);
+                }
+                let (mut x, mut y) = (0, 0);
+                for (px, py, on_curve) in glyph.points.iter() {
+                    encode_triplet(px - x, py - y, *on_curve, flags, glyphs);
+                    (x, y) = (*px, *py);
+                }
+                write_u255(glyph.instructions.len() as u16, glyphs);
+                instructions.extend_from_slice(glyph.instructions);
+                let mut reader = Reader::new(&data[2..]);
+                let bbox = [reader.i16()?, reader.i16()?, reader.i16()?, reader.i16()?];
+                if bbox != glyph.bbox() {
+                    set(&mut bbox_bitmap, i);
+                    bbox.iter()
+                        .for_each(|v| bboxes.extend_from_slice(&v.to_be_bytes()));
+                }
+                if glyph.overlap {
+                    set(&mut overlap_bitmap, i);
+                }
+            }
+            Glyph::Composite(glyph) => {
+                n_contours.extend_from_slice(&(-1_i16).to_be_bytes());
+                set(&mut bbox_bitmap, i);
+                glyph
+                    .bbox
+                    .iter()
+                    .for_each(|v| bboxes.extend_from_slice(&v.to_be_bytes()));
+                // the components as they are stored, without the header and
+                // the instructions
+                let mut reader = Reader::new(&data[10..]);
+                skip_components(&mut reader)?;
+                composites.extend_from_slice(&data[10..10 + reader.pos]);
+                if let Some(data) = glyph.instructions {
+                    write_u255(data.len() as u16, glyphs);
+                    instructions.extend_from_slice(data);
+                }
+            }
+            _ => n_contours.extend_from_slice(&0_i16.to_be_bytes()),
+        }
+    }
+    bbox_bitmap.append(bboxes);
+
+    let has_overlap = overlap_bitmap.iter().any(|b| *b != 0);
+    let mut transformed = vec![];
+    transformed.extend_from_slice(&[0, 0]);
+    transformed.extend_from_slice(&(has_overlap as u16).to_be_bytes());
+    transformed.extend_from_slice(&(num_glyphs as u16).to_be_bytes());
+    transformed.extend_from_slice(&index_format.to_be_bytes());
+    streams[5] = bbox_bitmap;
+    for stream in streams.iter() {
+        transformed.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+    }
+    for stream in streams.iter() {
+        transformed.extend_from_slice(stream);
+    }
+    if has_overlap {
+        transformed.extend_from_slice(&overlap_bitmap);
+    }
+    Ok(transformed)
+}
+
+/// "Triplet Encoding" of a point delta, the inverse of [`decode_triplet`]
+#[cfg(feature = "encode")]
+fn encode_triplet(dx: i32, dy: i32, on_curve: bool, flags: &mut Vec<u8>, glyphs: &mut Vec<u8>) {
+    let on_curve_bit = if on_curve { 0 } else { 0x80 };
+    let (x, y) = (dx.unsigned_abs(), dy.unsigned_abs());
+    let x_sign = (dx >= 0) as u8;
+    let y_sign = (dy >= 0) as u8;
+    let signs = x_sign + 2 * y_sign;
+    let flag = if dx == 0 && y < 1280 {
+        glyphs.push(y as u8);
+        ((y & 0xF00) >> 7) as u8 + y_sign
+    } else if dy == 0 && x < 1280 {
+        glyphs.push(x as u8);
+        10 + ((x & 0xF00) >> 7) as u8 + x_sign
+    } else if x < 65 && y < 65 {
+        glyphs.push(((((x - 1) & 0x0F) << 4) | ((y - 1) & 0x0F)) as u8);
+        20 + ((x - 1) & 0x30) as u8 + (((y - 1) & 0x30) >> 2) as u8 + signs
+    } else if x < 769 && y < 769 {
+        glyphs.extend_from_slice(&[(x - 1) as u8, (y - 1) as u8]);
+        84 + 12 * (((x - 1) & 0x300) >> 8) as u8 + (((y - 1) & 0x300) >> 6) as u8 + signs
+    } else if x < 4096 && y < 4096 {
+        glyphs.extend_from_slice(&[
+            (x >> 4) as u8,
+            (((x & 0x0F) << 4) | (y >> 8)) as u8,
+            y as u8,
+        ]);
+        120 + signs
+    } else {
+        glyphs.extend_from_slice(&[(x >> 8) as u8, x as u8, (y >> 8) as u8, y as u8]);
+        124 + signs
+    };
+    flags.push(flag | on_curve_bit);
+}
@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+#[cfg(feature = "metrics")]
+use std::ops::Range;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "metrics")]
+use unicode_script::{Script, UnicodeScript};
+
+#[cfg(feature = "metrics")]
+use crate::{matching::QueryCandidate, FamilyMatch, FontKit, TextMetrics};
+use crate::{Font, FontKey};
+
+/// Code points per page of the [`CoverageIndex`]
+const PAGE_BITS: u32 = 8;
+
+/// Characters a font has glyphs for, as sorted and disjoint ranges of code
+/// points
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Coverage(Vec<(u32, u32)>);
+
+impl Coverage {
+    /// Read the Unicode subtables of the cmap of a face. Checking every
+    /// glyph ID would slow indexing down, so segments mapping characters to
+    /// `.notdef` still count as covered, except the `U+FFFF` terminator of
+    /// format 4 subtables
+    #[cfg(feature = "parse")]
+    pub(crate) fn from_face(face: &ttf_parser::Face) -> Self {
+        let mut codepoints = vec![];
+        for subtable in face
+            .tables()
+            .cmap
+            .into_iter()
+            .flat_map(|cmap| cmap.subtables.into_iter())
+            .filter(|subtable| subtable.is_unicode())
+        {
+            subtable.codepoints(|c| codepoints.push(c));
+        }
+        codepoints.retain(|c| *c != 0xFFFF);
+        codepoints.sort_unstable();
+        codepoints.dedup();
+        let mut ranges: Vec<(u32, u32)> = vec![];
+        for c in codepoints {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == c => *end = c,
+                _ => ranges.push((c, c)),
+            }
+        }
+        Coverage(ranges)
+    }
+
+    pub(crate) fn contains(&self, c: char) -> bool {
+        let c = c as u32;
+        self.0
+            .binary_search_by(|(start, end)| {
+                if *end < c {
+                    std::cmp::Ordering::Less
+                } else if *start > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+
+    /// Pages of [`PAGE_BITS`] code points holding covered characters
+    fn pages(&self) -> impl Iterator<Item = u32> + '_ {
+        self.0
+            .iter()
+            .flat_map(|(start, end)| (start >> PAGE_BITS)..=(end >> PAGE_BITS))
+    }
+}
+
+/// Secondary index of the registry, from pages of code points to the
+/// registry keys of the fonts covering characters of them, so that fallback
+/// only checks fonts that may cover a text
+#[derive(Default)]
+pub(crate) struct CoverageIndex {
+    pages: DashMap<u32, HashSet<FontKey>>,
+}
+
+impl CoverageIndex {
+    fn pages_of(font: &Font) -> HashSet<u32> {
+        font.coverage().iter().flat_map(Coverage::pages).collect()
+    }
+
+    pub(crate) fn insert(&self, key: &FontKey, font: &Font) {
+        for page in Self::pages_of(font) {
+            self.pages.entry(page).or_default().insert(key.clone());
+        }
+    }
+
+    pub(crate) fn remove(&self, key: &FontKey, font: &Font) {
+        for page in Self::pages_of(font) {
+            self.pages.remove_if_mut(&page, |_, keys| {
+                keys.remove(key);
+                keys.is_empty()
+            });
+        }
+    }
+
+    /// Registry keys of the fonts that may cover some of `chars`
+    #[cfg(feature = "metrics")]
+    fn get(&self, chars: impl Iterator<Item = char>) -> HashSet<FontKey> {
+        let pages = chars.map(|c| c as u32 >> PAGE_BITS).collect::<HashSet<_>>();
+        pages
+            .into_iter()
+            .filter_map(|page| self.pages.get(&page))
+            .flat_map(|keys| keys.iter().cloned().collect::<Vec<_>>())
+            .collect()
+    }
+}
+
+/// Characters of a text that the queried font lacks, and the font picked for
+/// them
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FallbackRun {
+    /// Range of the run, counted in chars of the text
+    pub range: Range<usize>,
+    /// Key of the font variant covering the run
+    pub key: FontKey,
+}
+
+/// Result of [`FontKit::resolve_fallback`]
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fallback {
+    /// Runs of the text rendered with another font, in text order
+    pub runs: Vec<FallbackRun>,
+    /// Characters no registered font covers, in text order without
+    /// duplicates
+    pub uncovered: Vec<char>,
+}
+
+#[cfg(feature = "metrics")]
+impl FontKit {
+    /// Pick fonts for the characters of `text` that the font answering `key`
+    /// lacks, using the cmap coverage of the registered fonts. Missing
+    /// characters are grouped into runs of a single script, and each run
+    /// goes to the font covering most of it, preferring the stretch, style
+    /// and weight of the original font. Characters left over are tried again
+    /// with other fonts. If `key` resolves to no font, every character is
+    /// missing
+    pub fn resolve_fallback(&self, key: &FontKey, text: &str) -> Fallback {
+        let chars = text.chars().collect::<Vec<_>>();
+        let face = self.query(key);
+        let missing = chars
+            .iter()
+            .map(|c| !c.is_control() && !face.as_ref().is_some_and(|f| f.has_glyph(*c)))
+            .collect::<Vec<_>>();
+        let style = face.map(|f| f.key()).unwrap_or_else(|| key.clone());
+        self.plan_fallback(&style, &chars, &missing)
+    }
+
+    /// Fill the missing characters of `metrics`, measured with the font
+    /// answering `key`, from the fonts [`FontKit::resolve_fallback`] picks
+    pub(crate) fn fill_from_coverage(&self, key: &FontKey, metrics: &TextMetrics) {
+        let (chars, missing): (Vec<_>, Vec<_>) = {
+            let positions = metrics.positions.read().unwrap();
+            positions
+                .iter()
+                .map(|p| (p.metrics.c, p.metrics.missing && !p.metrics.c.is_control()))
+                .unzip()
+        };
+        let style = self
+            .query(key)
+            .map(|f| f.key())
+            .unwrap_or_else(|| key.clone());
+        let fallback = self.plan_fallback(&style, &chars, &missing);
+        let content_height = metrics.content_height();
+        let mut positions = metrics.positions.write().unwrap();
+        for run in fallback.runs {
+            let Some(face) = self.query(&run.key) else {
+                continue;
+            };
+            for index in run.range {
+                if let Some(mut m) = face.measure_char(chars[index]) {
+                    m.mul_factor(content_height as f32 / m.height as f32);
+                    positions[index].metrics = m;
+                    positions[index].kerning = 0;
+                }
+            }
+        }
+    }
+
+    fn plan_fallback(&self, style: &FontKey, chars: &[char], missing: &[bool]) -> Fallback {
+        let mut fallback = Fallback::default();
+        for mut pending in script_runs(chars, missing) {
+            while let Some((key, covered)) = self.best_coverage(style, &pending, chars) {
+                let mut rest = vec![];
+                for (index, covered) in pending.into_iter().zip(covered) {
+                    if !covered {
+                        rest.push(index);
+                        continue;
+                    }
+                    match fallback.runs.last_mut() {
+                        Some(run) if run.range.end == index && run.key == key => run.range.end += 1,
+                        _ => fallback.runs.push(FallbackRun {
+                            range: index..index + 1,
+                            key: key.clone(),
+                        }),
+                    }
+                }
+                pending = rest;
+            }
+            for index in pending {
+                if !fallback.uncovered.contains(&chars[index]) {
+                    fallback.uncovered.push(chars[index]);
+                }
+            }
+        }
+        fallback.runs.sort_by_key(|run| run.range.start);
+        fallback
+    }
+
+    /// The variant covering most of the pending characters, with which of
+    /// them it covers. Ties prefer the variant closest to `style`
+    fn best_coverage(
+        &self,
+        style: &FontKey,
+        pending: &[usize],
+        chars: &[char],
+    ) -> Option<(FontKey, Vec<bool>)> {
+        let mut best: Option<(usize, QueryCandidate, Vec<bool>)> = None;
+        let fonts = self.coverage_index.get(pending.iter().map(|i| chars[*i]));
+        for font_key in fonts {
+            let Some(font) = self.fonts.get(&font_key) else {
+                continue;
+            };
+            for (index, coverage) in font.coverage().iter().enumerate() {
+                let covered = pending
+                    .iter()
+                    .map(|i| coverage.contains(chars[*i]))
+                    .collect::<Vec<_>>();
+                let count = covered.iter().filter(|c| **c).count();
+                if count == 0 {
+                    continue;
+                }
+                for variant in font.variants().iter().filter(|v| v.index == index as u32) {
+                    let candidate =
+                        QueryCandidate::new(style, variant.key.clone(), FamilyMatch::Exact);
+                    let better = match best.as_ref() {
+                        None => true,
+                        Some((best_count, best_candidate, _)) => count
+                            .cmp(best_count)
+                            .then_with(|| best_candidate.rank().cmp(&candidate.rank()))
+                            .then_with(|| {
+                                best_candidate
+                                    .key
+                                    .partial_cmp(&candidate.key)
+                                    .unwrap_or(std::cmp::Ordering::Equal)
+                            })
+                            .is_gt(),
+                    };
+                    if better {
+                        best = Some((count, candidate, covered.clone()));
+                    }
+                }
+            }
+        }
+        best.map(|(_, candidate, covered)| (candidate.key, covered))
+    }
+}
+
+/// Split the indices of missing characters into runs of consecutive
+/// characters of one script. Common and inherited characters, such as
+/// punctuation and combining marks, join the run around them
+#[cfg(feature = "metrics")]
+fn script_runs(chars: &[char], missing: &[bool]) -> Vec<Vec<usize>> {
+    let mut runs: Vec<Vec<usize>> = vec![];
+    let mut script = None;
+    for (index, c) in chars.iter().enumerate() {
+        if !missing[index] {
+            continue;
+        }
+        let current = c.script();
+        let neutral = matches!(
+            current,
+            Script::Common | Script::Inherited | Script::Unknown
+        );
+        match runs.last_mut() {
+            Some(run)
+                if run.last() == Some(&(index - 1))
+                    && (neutral || script.is_none() || script == Some(current)) =>
+            {
+                run.push(index)
+            }
+            _ => {
+                runs.push(vec![index]);
+                script = None;
+            }
+        }
+        if !neutral {
+            script = Some(current);
+        }
+    }
+    runs
+}
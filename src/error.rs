@@ -1,3 +1,5 @@
+use std::path::{Path, PathBuf};
+
 use thiserror::Error;
 
 use crate::PositionedChar;
@@ -16,9 +18,6 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("Glyph {c} not found in font")]
     GlyphNotFound { c: char },
-    #[cfg(feature = "woff2-patched")]
-    #[error(transparent)]
-    Woff2(#[from] woff2_patched::decode::DecodeError),
     #[error("Metrics mismatch: values {value:?} metrics {metrics:?}")]
     MetricsMismatch {
         value: Vec<char>,
@@ -32,4 +31,108 @@ pub enum Error {
     PngDocde(#[from] png::DecodingError),
     #[error(transparent)]
     Utf8(#[from] std::string::FromUtf8Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "watch")]
+    #[error(transparent)]
+    Watch(#[from] notify::Error),
+    #[cfg(feature = "archive")]
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[error("{0:?} is not a supported archive")]
+    UnsupportedArchive(std::path::PathBuf),
+    #[error("{member} not found in archive {archive:?}")]
+    ArchiveMemberNotFound {
+        archive: std::path::PathBuf,
+        member: String,
+    },
+    #[error("{member} of archive {archive:?} has an absolute path or leaves the archive")]
+    UnsafeArchiveMember {
+        archive: std::path::PathBuf,
+        member: String,
+    },
+    #[cfg(feature = "fontconfig")]
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    #[error("Font collection has no face {0}")]
+    CollectionIndex(u32),
+    #[error("Aliasing {0} would make it resolve to itself")]
+    AliasCycle(String),
+    #[error("Index version {found} is not supported, expecting {expected}")]
+    IndexVersion { found: u32, expected: u32 },
+    #[error("Invalid table directory: {0}")]
+    InvalidDirectory(&'static str),
+    #[error("Table {tag} lies outside of the font data")]
+    TableOutOfBounds { tag: String },
+    #[error("Tables {tag} and {other} overlap")]
+    TablesOverlap { tag: String, other: String },
+    #[error("Checksum of table {tag} is {found:#010x}, expecting {expected:#010x}")]
+    TableChecksum {
+        tag: String,
+        found: u32,
+        expected: u32,
+    },
+    #[error("Invalid {tag} table: {reason}")]
+    InvalidTable { tag: String, reason: &'static str },
+    #[error("Can't access {path:?}: {reason}")]
+    FileAccess {
+        path: PathBuf,
+        reason: std::io::Error,
+    },
+    #[error("Face {index} of font {path:?} can't be parsed: {reason}")]
+    FaceParsing {
+        path: Option<PathBuf>,
+        index: u32,
+        reason: ttf_parser::FaceParsingError,
+    },
+    #[error("Font {path:?} has no faces")]
+    NoFaces { path: Option<PathBuf> },
+    #[error("Entry {entry} of the font data is invalid: {reason}")]
+    InvalidFontData { entry: usize, reason: String },
+}
+
+impl Error {
+    /// Wrap the IO errors of an operation on `path`
+    pub(crate) fn file_access(path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.into();
+        move |reason| Error::FileAccess { path, reason }
+    }
+
+    /// Tell the path of the font errors of a font loaded from `path`
+    pub(crate) fn with_path(self, path: Option<&Path>) -> Self {
+        let path = path.map(Path::to_path_buf);
+        match self {
+            Error::FaceParsing {
+                path: None,
+                index,
+                reason,
+            } => Error::FaceParsing {
+                path,
+                index,
+                reason,
+            },
+            Error::NoFaces { path: None } => Error::NoFaces { path },
+            error => error,
+        }
+    }
+
+    /// The file an error is about, if any
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::FileAccess { path, .. } => Some(path),
+            Error::FaceParsing { path, .. } | Error::NoFaces { path } => path.as_deref(),
+            Error::UnsupportedArchive(path) => Some(path),
+            Error::ArchiveMemberNotFound { archive, .. }
+            | Error::UnsafeArchiveMember { archive, .. } => Some(archive),
+            _ => None,
+        }
+    }
+
+    /// The face of a font collection an error is about, if any
+    pub fn face_index(&self) -> Option<u32> {
+        match self {
+            Error::FaceParsing { index, .. } | Error::CollectionIndex(index) => Some(*index),
+            _ => None,
+        }
+    }
 }
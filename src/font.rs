@@ -5,17 +5,21 @@ use byteorder::{BigEndian, ReadBytesExt};
 use indexmap::IndexMap;
 use ordered_float::OrderedFloat;
 use ouroboros::self_referencing;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::hash::Hash;
 #[cfg(feature = "parse")]
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 pub use ttf_parser::LineMetrics;
-use ttf_parser::{Face, Tag, Width as ParserWidth};
+use ttf_parser::{name_id, Face, Tag, Width as ParserWidth};
 
-use crate::{Error, Filter};
+use crate::coverage::Coverage;
+use crate::language::{bcp47_tag, language_matches};
+use crate::matching::{css_match, normalize_name, variation_matches, QueryCandidate};
+use crate::{Error, FamilyMatch, FileSource, Filter, FontSource, MatchMode, NameMatching};
 
 pub fn str_width_to_number(width: &str) -> u16 {
     match width {
@@ -33,6 +37,25 @@ pub fn str_width_to_number(width: &str) -> u16 {
     .to_number()
 }
 
+/// Values of the wdth axis for each stretch class, ref: https://learn.microsoft.com/en-us/typography/opentype/spec/dvaraxistag_wdth
+const STRETCH_WIDTHS: [f32; 9] = [50.0, 62.5, 75.0, 87.5, 100.0, 112.5, 125.0, 150.0, 200.0];
+
+/// The stretch class nearest to a value of the wdth axis
+pub(crate) fn width_to_stretch(width: f32) -> u16 {
+    let index = STRETCH_WIDTHS
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| (*a - width).abs().total_cmp(&(*b - width).abs()))
+        .map(|(index, _)| index)
+        .unwrap_or(4);
+    index as u16 + 1
+}
+
+/// The value of the wdth axis for a stretch class
+pub(crate) fn stretch_to_width(stretch: u16) -> f32 {
+    STRETCH_WIDTHS[stretch.clamp(1, 9) as usize - 1]
+}
+
 pub fn number_width_to_str(width: u16) -> String {
     match width {
         1 => "ultra-condensed",
@@ -60,6 +83,11 @@ pub struct FontKey {
     /// Font family string
     pub family: String,
     pub variations: Vec<(String, f32)>,
+    /// Tells apart fonts registered under otherwise equal keys with
+    /// [`crate::CollisionPolicy::KeepBoth`]. Queries only find such a font
+    /// by its handle
+    #[serde(default)]
+    pub handle: Option<u32>,
 }
 
 impl Eq for FontKey {}
@@ -75,6 +103,7 @@ impl Hash for FontKey {
             .map(|(s, v)| (s, OrderedFloat(*v)))
             .collect::<Vec<_>>()
             .hash(state);
+        self.handle.hash(state);
     }
 }
 
@@ -86,6 +115,7 @@ impl FontKey {
             stretch: Some(5),
             family,
             variations: vec![],
+            handle: None,
         }
     }
 }
@@ -104,8 +134,72 @@ impl fmt::Display for FontKey {
 pub(super) struct Name {
     pub id: u16,
     pub name: String,
-    #[allow(unused)]
     pub language_id: u16,
+    #[serde(default)]
+    pub platform_id: u16,
+}
+
+impl Name {
+    /// BCP-47 tag of the language of this name, if known
+    pub fn language(&self) -> Option<&'static str> {
+        bcp47_tag(self.platform_id, self.language_id)
+    }
+
+    #[cfg(feature = "parse")]
+    fn from_record(name: ttf_parser::name::Name) -> Option<Self> {
+        Some(Name {
+            id: name.name_id,
+            name: decode_name(&name)?,
+            language_id: name.language_id,
+            platform_id: name.platform_id as u16,
+        })
+    }
+}
+
+/// Decode a name record according to its platform and encoding, ref:
+/// https://learn.microsoft.com/en-us/typography/opentype/spec/name#platform-specific-encoding-and-language-ids-macintosh-platform-platform-id--1
+#[cfg(feature = "parse")]
+fn decode_name(name: &ttf_parser::name::Name) -> Option<String> {
+    use encoding_rs::{Encoding, BIG5, EUC_KR, GBK, MACINTOSH, SHIFT_JIS};
+    use ttf_parser::PlatformId;
+
+    if let Some(name) = name.to_string() {
+        return Some(name);
+    }
+    let encoding: &'static Encoding = match (name.platform_id, name.encoding_id) {
+        // UCS-4 records only hold UTF-16 data, like UCS-2 ones
+        (PlatformId::Windows, 10) => {
+            let units = name
+                .name
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect::<Vec<_>>();
+            return String::from_utf16(&units).ok();
+        }
+        (PlatformId::Macintosh, 0) => MACINTOSH,
+        (PlatformId::Macintosh, 1) | (PlatformId::Windows, 2) => SHIFT_JIS,
+        (PlatformId::Macintosh, 2) | (PlatformId::Windows, 4) => BIG5,
+        (PlatformId::Macintosh, 3) | (PlatformId::Windows, 5) => EUC_KR,
+        (PlatformId::Macintosh, 25) | (PlatformId::Windows, 3) => GBK,
+        // try to force unicode encoding
+        _ => return Some(std::str::from_utf8(name.name).ok()?.to_string()),
+    };
+    let bytes = if name.platform_id == PlatformId::Windows {
+        // Windows records store the multi-byte sequences in 16-bit units,
+        // single-byte characters have a zero high byte
+        name.name
+            .chunks(2)
+            .flat_map(|c| match c {
+                [0, low] => vec![*low],
+                _ => c.to_vec(),
+            })
+            .collect::<Vec<_>>()
+    } else {
+        name.name.to_vec()
+    };
+    encoding
+        .decode_without_bom_handling_and_without_replacement(&bytes)
+        .map(|name| name.into_owned())
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -115,6 +209,21 @@ pub(super) struct FvarInstance {
     pub(super) postscript: Name,
 }
 
+/// Range of a variation axis of a variable font
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct AxisRange {
+    pub tag: String,
+    pub min: f32,
+    pub default: f32,
+    pub max: f32,
+}
+
+impl AxisRange {
+    fn contains(&self, value: f32) -> bool {
+        self.min <= value && value <= self.max
+    }
+}
+
 /// Returns whether a buffer is WOFF font data.
 pub fn is_woff(buf: &[u8]) -> bool {
     buf.len() > 4 && buf[0] == 0x77 && buf[1] == 0x4F && buf[2] == 0x46 && buf[3] == 0x46
@@ -145,21 +254,37 @@ pub fn is_otf(buf: &[u8]) -> bool {
         && buf[4] == 0x00
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// Returns whether a buffer is a TrueType or OpenType font collection.
+pub fn is_collection(buf: &[u8]) -> bool {
+    buf.len() > 4 && buf[0] == 0x74 && buf[1] == 0x74 && buf[2] == 0x63 && buf[3] == 0x66
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub(crate) struct VariationData {
     pub key: FontKey,
     pub names: Vec<Name>,
     pub variation_names: Vec<FvarInstance>,
     pub style_names: Vec<Name>,
     pub index: u32,
+    /// Axes of the fvar table, empty for fonts that aren't variable
+    #[serde(default)]
+    pub axes: Vec<AxisRange>,
 }
 
 impl VariationData {
+    /// Variants of the face at `index`, with the characters the face covers
     #[cfg(feature = "parse")]
-    fn parse_buffer_with_index(buffer: &[u8], index: u32) -> Result<Vec<VariationData>, Error> {
-        use ttf_parser::{name_id, Fixed, VariationAxis};
+    fn parse_buffer_with_index(
+        buffer: &[u8],
+        index: u32,
+    ) -> Result<(Vec<VariationData>, Coverage), Error> {
+        use ttf_parser::{Fixed, VariationAxis};
 
-        let face = Face::parse(buffer, index)?;
+        let face = Face::parse(buffer, index).map_err(|reason| Error::FaceParsing {
+            path: None,
+            index,
+            reason,
+        })?;
         let axes: Vec<VariationAxis> = face
             .tables()
             .fvar
@@ -172,23 +297,13 @@ impl VariationData {
         let mut instances: IndexMap<Vec<OrderedFloat<f32>>, Vec<FvarInstance>> = IndexMap::new();
         if let (Some(_), Some(name_table)) = (face.tables().fvar, face.tables().name) {
             // currently ttf-parser is missing `fvar`'s instance records, we parse them
-            // directly from `RawFace`
+            // directly from `RawFace`, after checking that they fit in the table
             let data: &[u8] = face
                 .raw_face()
                 .table(ttf_parser::Tag::from_bytes(b"fvar"))
-                .unwrap();
-            let mut raw = &*data;
-            let _version = raw.read_u32::<BigEndian>()?;
-            let axis_offset = raw.read_u16::<BigEndian>()?;
-            let _ = raw.read_u16::<BigEndian>()?;
-            let axis_count = raw.read_u16::<BigEndian>()?;
-            let axis_size = raw.read_u16::<BigEndian>()?;
-            let instance_count = raw.read_u16::<BigEndian>()?;
-            let instance_size = raw.read_u16::<BigEndian>()?;
-
-            let data = &data[(axis_offset as usize + (axis_count as usize * axis_size as usize))..];
-            for i in 0..instance_count {
-                let mut raw = &data[(i as usize * instance_size as usize)..];
+                .unwrap_or_default();
+            let (axis_count, records) = crate::conv::sanitize::fvar_instances(data)?;
+            for mut raw in records {
                 let sub_family_name_id = raw.read_u16::<BigEndian>()?;
                 let _ = raw.read_u16::<BigEndian>()?;
                 let coords = (0..axis_count)
@@ -208,30 +323,12 @@ impl VariationData {
                     .names
                     .into_iter()
                     .find(|name| name.name_id == sub_family_name_id)
-                    .and_then(|name| {
-                        Some(Name {
-                            id: name.name_id,
-                            name: name.to_string().or_else(|| {
-                                // try to force unicode encoding
-                                Some(std::str::from_utf8(name.name).ok()?.to_string())
-                            })?,
-                            language_id: name.language_id,
-                        })
-                    });
+                    .and_then(Name::from_record);
                 let postscript = name_table
                     .names
                     .into_iter()
                     .find(|name| Some(name.name_id) == postscript_name_id)
-                    .and_then(|name| {
-                        Some(Name {
-                            id: name.name_id,
-                            name: name.to_string().or_else(|| {
-                                // try to force unicode encoding
-                                Some(std::str::from_utf8(name.name).ok()?.to_string())
-                            })?,
-                            language_id: name.language_id,
-                        })
-                    });
+                    .and_then(Name::from_record);
                 if let (Some(sub_family), Some(postscript)) = (sub_family, postscript) {
                     instances.entry(coords).or_default().push(FvarInstance {
                         sub_family,
@@ -255,15 +352,13 @@ impl VariationData {
             .into_iter()
             .filter_map(|name| {
                 let id = name.name_id;
-                let mut name_str = name.to_string().or_else(|| {
-                    // try to force unicode encoding
-                    Some(std::str::from_utf8(name.name).ok()?.to_string())
-                })?;
+                let mut name_str = decode_name(&name)?;
                 if id == name_id::TYPOGRAPHIC_SUBFAMILY {
                     style_names.push(Name {
                         id,
                         name: name_str.clone(),
                         language_id: name.language_id,
+                        platform_id: name.platform_id as u16,
                     });
                 }
                 if id == name_id::FAMILY
@@ -278,6 +373,7 @@ impl VariationData {
                         id,
                         name: name_str,
                         language_id: name.language_id,
+                        platform_id: name.platform_id as u16,
                     })
                 } else {
                     None
@@ -309,6 +405,18 @@ impl VariationData {
                     name
                 }
             });
+        let coverage = Coverage::from_face(&face);
+        let axis_ranges = axes
+            .iter()
+            .map(|axis| {
+                Ok(AxisRange {
+                    tag: String::from_utf8(axis.tag.to_bytes().to_vec())?,
+                    min: axis.min_value,
+                    default: axis.def_value,
+                    max: axis.max_value,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
         let mut results = vec![];
         let key = FontKey {
             weight: Some(face.weight().to_number()),
@@ -316,6 +424,7 @@ impl VariationData {
             stretch: Some(face.width().to_number()),
             family: ascii_name.clone().unwrap_or_else(|| names[0].name.clone()),
             variations: vec![],
+            handle: None,
         };
         for (coords, variation_names) in instances {
             let mut key = key.clone();
@@ -327,10 +436,10 @@ impl VariationData {
                 .position(|axis| axis.tag == ttf_parser::Tag::from_bytes(b"wght"));
             if let Some(value) = width_axis_index.and_then(|i| coords.get(i)) {
                 // mapping wdth to usWidthClass, ref: https://learn.microsoft.com/en-us/typography/opentype/spec/dvaraxistag_wdth
-                key.stretch = Some(((value.0 / 100.0) * 5.0).round().min(1.0).max(9.0) as u16);
+                key.stretch = Some(width_to_stretch(value.0));
             }
             if let Some(value) = weight_axis_index.and_then(|i| coords.get(i)) {
-                key.weight = Some(value.0 as u16);
+                key.weight = Some(value.0.round() as u16);
             }
             for (coord, axis) in coords.iter().zip(axes.iter()) {
                 key.variations
@@ -342,6 +451,7 @@ impl VariationData {
                 style_names: style_names.clone(),
                 variation_names,
                 index,
+                axes: axis_ranges.clone(),
             });
         }
         if results.is_empty() {
@@ -352,55 +462,217 @@ impl VariationData {
                 variation_names: vec![],
                 style_names,
                 index,
+                axes: axis_ranges,
             })
         }
-        Ok(results)
+        Ok((results, coverage))
     }
 
     fn is_variable(&self) -> bool {
         !self.key.variations.is_empty()
     }
 
-    fn fulfils(&self, query: &Filter) -> bool {
-        match *query {
-            Filter::Family(name) => {
-                if self.key.family == name {
-                    return true;
-                }
-                if self.names.iter().any(|n| n.name == name) {
-                    return true;
-                }
-                if self.is_variable() {
-                    use inflections::Inflect;
-                    return self.variation_names.iter().any(|n| {
-                        n.postscript.name == name
-                            || n.postscript
-                                .name
-                                .replace(&n.sub_family.name, &n.sub_family.name.to_pascal_case())
-                                == name
-                    });
-                }
+    fn axis(&self, tag: &str) -> Option<&AxisRange> {
+        self.axes.iter().find(|axis| axis.tag == tag)
+    }
 
-                false
+    /// The key of the face this variant renders for `key`. Weight, stretch
+    /// and variations of `key` that the named instance doesn't have replace
+    /// its coordinates, clamped to the ranges of the axes. Variants queried
+    /// by the name of their instance keep the weight and stretch of the
+    /// instance
+    pub(crate) fn instance_key(&self, key: &FontKey, name_matching: NameMatching) -> FontKey {
+        let mut instance = self.key.clone();
+        if self.axes.is_empty() {
+            return instance;
+        }
+        if instance.variations.is_empty() {
+            instance.variations = self
+                .axes
+                .iter()
+                .map(|axis| (axis.tag.clone(), axis.default))
+                .collect();
+        }
+        let mut set = |tag: &str, value: f32| {
+            let Some(axis) = self.axis(tag) else {
+                return;
+            };
+            let value = value.clamp(axis.min, axis.max);
+            match instance.variations.iter_mut().find(|(t, _)| t == tag) {
+                Some((_, v)) => *v = value,
+                None => instance.variations.push((tag.to_string(), value)),
             }
+        };
+        let by_instance_name =
+            self.family_match(&key.family, name_matching) == Some(FamilyMatch::InstanceName);
+        if !by_instance_name {
+            if let Some(weight) = key.weight.filter(|w| Some(*w) != self.key.weight) {
+                set("wght", weight as f32);
+            }
+            if let Some(stretch) = key.stretch.filter(|s| Some(*s) != self.key.stretch) {
+                set("wdth", stretch_to_width(stretch));
+            }
+        }
+        for (tag, value) in key.variations.iter() {
+            set(tag, *value);
+        }
+        for (tag, value) in instance.variations.iter() {
+            match tag.as_str() {
+                "wght" => instance.weight = Some(value.round() as u16),
+                "wdth" => instance.stretch = Some(width_to_stretch(*value)),
+                _ => {}
+            }
+        }
+        instance
+    }
+
+    /// Which of the names of this variant equals `name`, if any
+    pub(crate) fn family_match(
+        &self,
+        name: &str,
+        name_matching: NameMatching,
+    ) -> Option<FamilyMatch> {
+        let normalized = match name_matching {
+            NameMatching::Strict => None,
+            NameMatching::Normalized => Some(normalize_name(name)),
+        };
+        let equals = |candidate: &str| match normalized.as_ref() {
+            Some(normalized) => normalize_name(candidate) == *normalized,
+            None => candidate == name,
+        };
+        if equals(&self.key.family) {
+            return Some(FamilyMatch::Exact);
+        }
+        if self.names.iter().any(|n| equals(&n.name)) {
+            return Some(FamilyMatch::Name);
+        }
+        if self.is_variable() {
+            use inflections::Inflect;
+            let found = self.variation_names.iter().any(|n| {
+                equals(&n.postscript.name)
+                    || equals(
+                        &n.postscript
+                            .name
+                            .replace(&n.sub_family.name, &n.sub_family.name.to_pascal_case()),
+                    )
+            });
+            if found {
+                return Some(FamilyMatch::InstanceName);
+            }
+        }
+        None
+    }
+
+    /// Whether one of the names of this variant equals `name` and is in
+    /// `language`
+    pub(crate) fn has_localized_name(
+        &self,
+        name: &str,
+        language: &str,
+        name_matching: NameMatching,
+    ) -> bool {
+        let normalized = normalize_name(name);
+        self.names.iter().any(|n| {
+            let equal = match name_matching {
+                NameMatching::Strict => n.name == name,
+                NameMatching::Normalized => normalize_name(&n.name) == normalized,
+            };
+            equal && n.language().is_some_and(|l| language_matches(l, language))
+        })
+    }
+
+    /// Family names of this variant, grouped by BCP-47 language tag. Names of
+    /// unknown language are listed under `und`
+    pub(crate) fn family_names_by_language(&self) -> BTreeMap<String, Vec<String>> {
+        let mut result: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for name in &self.names {
+            if name.id != name_id::FAMILY && name.id != name_id::TYPOGRAPHIC_FAMILY {
+                continue;
+            }
+            let names = result
+                .entry(name.language().unwrap_or("und").to_string())
+                .or_default();
+            if !names.contains(&name.name) {
+                names.push(name.name.clone());
+            }
+        }
+        result
+    }
+
+    /// Use the family name in `language` as the family of the key, preferring
+    /// the typographic family. The key is left as is without such a name
+    fn localize_family(&mut self, language: &str) {
+        let localized = [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]
+            .iter()
+            .find_map(|id| {
+                self.names.iter().find(|n| {
+                    n.id == *id && n.language().is_some_and(|l| language_matches(l, language))
+                })
+            });
+        if let Some(name) = localized {
+            self.key.family = name.name.clone();
+        }
+    }
+
+    /// Every name `family_match` accepts
+    pub(crate) fn lookup_names(&self) -> Vec<String> {
+        use inflections::Inflect;
+
+        let mut names = vec![self.key.family.clone()];
+        names.extend(self.names.iter().map(|n| n.name.clone()));
+        if self.is_variable() {
+            for n in &self.variation_names {
+                names.push(n.postscript.name.clone());
+                names.push(
+                    n.postscript
+                        .name
+                        .replace(&n.sub_family.name, &n.sub_family.name.to_pascal_case()),
+                );
+            }
+        }
+        names
+    }
+
+    pub(crate) fn fulfils(&self, query: &Filter) -> bool {
+        match *query {
+            Filter::Family(name, name_matching) => self.family_match(name, name_matching).is_some(),
             Filter::Italic(i) => self.key.italic.unwrap_or_default() == i,
-            Filter::Stretch(s) => self.key.stretch.unwrap_or(5) == s,
-            Filter::Weight(w) => w == 0 || self.key.weight.unwrap_or(400) == w,
-            Filter::Variations(v) => v.iter().all(|(s, v)| {
+            Filter::Stretch(s) => {
+                self.key.stretch.unwrap_or(5) == s
+                    || self
+                        .axis("wdth")
+                        .is_some_and(|axis| axis.contains(stretch_to_width(s)))
+            }
+            Filter::Weight(w) => {
+                w == 0
+                    || self.key.weight.unwrap_or(400) == w
+                    || self
+                        .axis("wght")
+                        .is_some_and(|axis| axis.contains(w as f32))
+            }
+            Filter::Variations(v) => v.iter().all(|variation| {
                 self.key
                     .variations
                     .iter()
-                    .any(|(ss, sv)| ss == s && v == sv)
+                    .any(|kv| variation_matches(kv, variation))
+                    || self
+                        .axis(&variation.0)
+                        .is_some_and(|axis| axis.contains(variation.1))
             }),
         }
     }
 }
 
 pub(crate) struct Font {
-    path: Option<PathBuf>,
-    buffer: ArcSwap<Vec<u8>>,
+    source: Option<Arc<dyn FontSource>>,
+    buffer: ArcSwap<FontBuffer>,
+    /// Whether the data of a local file source is mapped instead of read, see
+    /// [`crate::FontKit::set_memory_mapping`]
+    map_file: bool,
     /// [Font variation](https://learn.microsoft.com/en-us/typography/opentype/spec/fvar) and font collection data
     variants: Vec<VariationData>,
+    /// Characters each face covers, indexed by face index
+    coverage: Vec<Coverage>,
     hit_counter: Arc<AtomicU32>,
     pub(crate) hit_index: AtomicU32,
 }
@@ -410,57 +682,104 @@ impl Font {
         self.variants.iter().any(|v| v.fulfils(query))
     }
 
+    pub(crate) fn localize_family(&mut self, language: &str) {
+        for variant in self.variants.iter_mut() {
+            variant.localize_family(language);
+        }
+    }
+
+    /// Set the handle of the keys, see [`crate::CollisionPolicy::KeepBoth`]
+    pub(crate) fn set_handle(&mut self, handle: u32) {
+        for variant in self.variants.iter_mut() {
+            variant.key.handle = Some(handle);
+        }
+    }
+
     pub fn first_key(&self) -> FontKey {
         self.variants[0].key.clone()
     }
 
     pub fn set_path(&mut self, path: PathBuf) {
-        self.path = Some(path);
+        self.set_source(Arc::new(FileSource::new(path)));
+    }
+
+    pub fn set_source(&mut self, source: Arc<dyn FontSource>) {
+        self.source = Some(source);
+    }
+
+    pub fn source(&self) -> Option<&Arc<dyn FontSource>> {
+        self.source.as_ref()
+    }
+
+    pub(crate) fn set_map_file(&mut self, map_file: bool) {
+        self.map_file = map_file;
     }
 
+    /// Parse a font buffer. With `strict`, the sfnt structure is sanitized
+    /// before, see [`crate::FontKit::set_strict_validation`]
     #[cfg(feature = "parse")]
     pub(super) fn from_buffer(
-        mut buffer: Vec<u8>,
+        buffer: Vec<u8>,
         hit_counter: Arc<AtomicU32>,
+        strict: bool,
     ) -> Result<Self, Error> {
-        let mut variants = vec![0];
-        if is_otf(&buffer) {
-            variants = (0..ttf_parser::fonts_in_collection(&buffer).unwrap_or(1)).collect();
-        }
-        #[cfg(feature = "woff2-patched")]
-        if is_woff2(&buffer) {
-            buffer = woff2_patched::convert_woff2_to_ttf(&mut buffer.as_slice())?;
-        }
-        #[cfg(feature = "parse")]
-        if is_woff(&buffer) {
-            use std::io::Cursor;
+        Self::from_font_buffer(FontBuffer::from_vec(buffer)?, hit_counter, strict)
+    }
 
-            let reader = Cursor::new(buffer);
-            let mut otf_buf = Cursor::new(Vec::new());
-            crate::conv::woff::convert_woff_to_otf(reader, &mut otf_buf)?;
-            buffer = otf_buf.into_inner();
-        }
+    #[cfg(feature = "parse")]
+    pub(super) fn from_source(
+        source: Arc<dyn FontSource>,
+        hit_counter: Arc<AtomicU32>,
+        strict: bool,
+        map_file: bool,
+    ) -> Result<Self, Error> {
+        let buffer = FontBuffer::from_source(&*source, map_file)?;
+        let mut font = Self::from_font_buffer(buffer, hit_counter, strict)
+            .map_err(|e| e.with_path(source.path()))?;
+        font.set_source(source);
+        font.set_map_file(map_file);
+        Ok(font)
+    }
+
+    #[cfg(feature = "parse")]
+    fn from_font_buffer(
+        buffer: FontBuffer,
+        hit_counter: Arc<AtomicU32>,
+        strict: bool,
+    ) -> Result<Self, Error> {
         if buffer.is_empty() {
             return Err(Error::UnsupportedMIME("unknown"));
         }
-        let variants = variants
-            .into_iter()
+        if strict {
+            crate::conv::sanitize::sanitize(&buffer)?;
+        }
+        let count = if is_collection(&buffer) {
+            ttf_parser::fonts_in_collection(&buffer).unwrap_or(1)
+        } else {
+            1
+        };
+        let (variants, coverage): (Vec<_>, Vec<_>) = (0..count)
             .map(|v| VariationData::parse_buffer_with_index(&buffer, v))
             .collect::<Result<Vec<_>, _>>()?
             .into_iter()
-            .flatten()
-            .collect::<Vec<_>>();
+            .unzip();
+        let variants = variants.into_iter().flatten().collect::<Vec<_>>();
+        if variants.is_empty() {
+            return Err(Error::NoFaces { path: None });
+        }
         Ok(Font {
-            path: None,
+            source: None,
             buffer: ArcSwap::new(Arc::new(buffer)),
+            map_file: false,
             variants,
+            coverage,
             hit_index: AtomicU32::default(),
             hit_counter,
         })
     }
 
     pub fn unload(&self) {
-        if self.path.is_some() {
+        if self.source.is_some() {
             self.buffer.swap(Arc::default());
         }
     }
@@ -472,38 +791,92 @@ impl Font {
         let hit_index = self.hit_counter.fetch_add(1, Ordering::SeqCst);
         self.hit_index.store(hit_index, Ordering::SeqCst);
         #[cfg(feature = "parse")]
-        if let Some(path) = self.path.as_ref() {
-            let mut buffer = Vec::new();
-            let mut file = std::fs::File::open(path)?;
-            file.read_to_end(&mut buffer).unwrap();
-
-            #[cfg(feature = "woff2-patched")]
-            if is_woff2(&buffer) {
-                buffer = woff2_patched::convert_woff2_to_ttf(&mut buffer.as_slice())?;
-            }
-            #[cfg(feature = "parse")]
-            if is_woff(&buffer) {
-                use std::io::Cursor;
-
-                let reader = Cursor::new(buffer);
-                let mut otf_buf = Cursor::new(Vec::new());
-                crate::conv::woff::convert_woff_to_otf(reader, &mut otf_buf)?;
-                buffer = otf_buf.into_inner();
-            }
-            self.buffer.swap(Arc::new(buffer));
+        if let Some(source) = self.source.as_ref() {
+            self.buffer
+                .swap(Arc::new(FontBuffer::from_source(&**source, self.map_file)?));
         }
         Ok(())
     }
 
-    pub fn path(&self) -> Option<&PathBuf> {
-        self.path.as_ref()
+    pub fn path(&self) -> Option<&Path> {
+        self.source.as_ref()?.path()
+    }
+
+    pub fn face(
+        &self,
+        key: &FontKey,
+        match_mode: MatchMode,
+        name_matching: NameMatching,
+    ) -> Result<StaticFace, Error> {
+        let variants = self.variants.iter().collect();
+        self.face_among(key, variants, match_mode, name_matching)
     }
 
-    pub fn face(&self, key: &FontKey) -> Result<StaticFace, Error> {
+    /// Like [`Font::face`], only considering the face at `index` of a font
+    /// collection
+    pub fn collection_face(
+        &self,
+        key: &FontKey,
+        index: u32,
+        match_mode: MatchMode,
+        name_matching: NameMatching,
+    ) -> Result<StaticFace, Error> {
+        let variants = self
+            .variants
+            .iter()
+            .filter(|v| v.index == index)
+            .collect::<Vec<_>>();
+        if variants.is_empty() {
+            return Err(Error::CollectionIndex(index));
+        }
+        self.face_among(key, variants, match_mode, name_matching)
+    }
+
+    fn face_among(
+        &self,
+        key: &FontKey,
+        variants: Vec<&VariationData>,
+        match_mode: MatchMode,
+        name_matching: NameMatching,
+    ) -> Result<StaticFace, Error> {
         self.load()?;
         let buffer = self.buffer.load_full();
-        let filters = Filter::from_key(key);
-        let mut queue = self.variants.iter().collect::<Vec<_>>();
+        let variant = match match_mode {
+            MatchMode::Filter => Self::filter_variant(key, name_matching, variants),
+            MatchMode::Css => Self::css_match_variant(key, name_matching, variants),
+        }
+        .ok_or_else(|| Error::NoFaces {
+            path: self.path().map(Path::to_path_buf),
+        })?;
+        let key = variant.instance_key(key, name_matching);
+        let mut face = StaticFaceTryBuilder {
+            key: key.clone(),
+            index: variant.index,
+            // path: self.path.clone().unwrap_or_default(),
+            buffer,
+            face_builder: |buf| Face::parse(buf, variant.index),
+        }
+        .try_build()
+        .map_err(|reason| Error::FaceParsing {
+            path: self.path().map(Path::to_path_buf),
+            index: variant.index,
+            reason,
+        })?;
+        face.with_face_mut(|face| {
+            for (coord, axis) in &key.variations {
+                face.set_variation(Tag::from_bytes_lossy(coord.as_bytes()), *axis);
+            }
+        });
+        Ok(face)
+    }
+
+    fn filter_variant<'a>(
+        key: &FontKey,
+        name_matching: NameMatching,
+        variants: Vec<&'a VariationData>,
+    ) -> Option<&'a VariationData> {
+        let filters = Filter::from_key(key, name_matching);
+        let mut queue = variants;
         for filter in filters {
             let mut q = queue.clone();
             q.retain(|v| v.fulfils(&filter));
@@ -516,36 +889,52 @@ impl Font {
                 queue = q;
             }
         }
-        let variant = queue[0];
-        let mut face = StaticFaceTryBuilder {
-            key: variant.key.clone(),
-            // path: self.path.clone().unwrap_or_default(),
-            buffer,
-            face_builder: |buf| Face::parse(buf, variant.index),
-        }
-        .try_build()
-        .unwrap();
-        face.with_face_mut(|face| {
-            for (coord, axis) in &variant.key.variations {
-                face.set_variation(Tag::from_bytes_lossy(coord.as_bytes()), *axis);
-            }
+        // several variants of a variable font may cover the query, start
+        // from the named instance closest to it
+        queue
+            .into_iter()
+            .min_by_key(|v| QueryCandidate::new(key, v.key.clone(), FamilyMatch::Exact).rank())
+    }
+
+    fn css_match_variant<'a>(
+        key: &FontKey,
+        name_matching: NameMatching,
+        variants: Vec<&'a VariationData>,
+    ) -> Option<&'a VariationData> {
+        let family = Filter::Family(&key.family, name_matching);
+        let mut candidates = variants
+            .iter()
+            .filter(|v| v.fulfils(&family))
+            .map(|v| (*v, v.instance_key(key, name_matching)))
+            .collect::<Vec<_>>();
+        // named instances tied after instancing prefer the closest one
+        candidates.sort_by_cached_key(|(v, _)| {
+            QueryCandidate::new(key, v.key.clone(), FamilyMatch::Exact).rank()
         });
-        Ok(face)
+        css_match(key, candidates).or(variants.first().copied())
     }
 
     pub fn variants(&self) -> &[VariationData] {
         &self.variants
     }
 
+    /// Characters covered by each face, indexed by face index
+    pub(crate) fn coverage(&self) -> &[Coverage] {
+        &self.coverage
+    }
+
     pub(super) fn new(
         path: Option<PathBuf>,
         variants: Vec<VariationData>,
+        coverage: Vec<Coverage>,
         hit_counter: Arc<AtomicU32>,
     ) -> Self {
         Font {
-            path,
+            source: path.map(|p| Arc::new(FileSource::new(p)) as Arc<dyn FontSource>),
             variants,
+            coverage,
             buffer: ArcSwap::default(),
+            map_file: false,
             hit_index: AtomicU32::default(),
             hit_counter,
         }
@@ -554,12 +943,103 @@ impl Font {
     pub(super) fn buffer_size(&self) -> usize {
         self.buffer.load().len()
     }
+
+    pub(super) fn mapped_size(&self) -> usize {
+        let buffer = self.buffer.load();
+        if buffer.is_mapped() {
+            buffer.len()
+        } else {
+            0
+        }
+    }
+}
+
+/// Data of a loaded font. Once enabled with
+/// [`crate::FontKit::set_memory_mapping`], uncompressed font files are mapped
+/// into memory instead of being read, so that they don't take resident memory
+/// until their pages are used.
+///
+/// Mapping is only sound while the files aren't modified in place: a file
+/// truncated while mapped makes reads of the mapping fault (`SIGBUS` on
+/// Unix). Callers of the setter take on this requirement
+pub(crate) enum FontBuffer {
+    Heap(Vec<u8>),
+    #[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+    Mapped(memmap2::Mmap),
+}
+
+impl FontBuffer {
+    /// Take a raw font buffer, decompressing WOFF and WOFF2 data
+    #[cfg(feature = "parse")]
+    pub(crate) fn from_vec(mut buffer: Vec<u8>) -> Result<Self, Error> {
+        if is_woff2(&buffer) {
+            buffer = crate::conv::woff2::convert_woff2_to_ttc(&buffer)?;
+        }
+        if is_woff(&buffer) {
+            use std::io::Cursor;
+
+            let reader = Cursor::new(buffer);
+            let mut otf_buf = Cursor::new(Vec::new());
+            crate::conv::woff::convert_woff_to_otf(reader, &mut otf_buf)?;
+            buffer = otf_buf.into_inner();
+        }
+        Ok(FontBuffer::Heap(buffer))
+    }
+
+    /// Fetch the data of a source. With `map_file`, sources backed by a local
+    /// file are mapped
+    #[cfg(feature = "parse")]
+    pub(crate) fn from_source(source: &dyn FontSource, map_file: bool) -> Result<Self, Error> {
+        #[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+        if let Some(path) = source.file().filter(|_| map_file) {
+            let file = std::fs::File::open(path).map_err(Error::file_access(path))?;
+            // Safety: the mapping is read-only, but it is only valid while the
+            // file isn't truncated or rewritten in place, which callers of the
+            // unsafe `FontKit::set_memory_mapping` guarantee
+            let map = unsafe { memmap2::Mmap::map(&file) }.map_err(Error::file_access(path))?;
+            if is_woff(&map) || is_woff2(&map) {
+                return Self::from_vec(map.to_vec());
+            }
+            return Ok(FontBuffer::Mapped(map));
+        }
+        // mapping can only be enabled with the `mmap` feature
+        #[cfg(not(all(feature = "mmap", not(target_arch = "wasm32"))))]
+        let _ = map_file;
+        Self::from_vec(source.read()?)
+    }
+
+    pub(crate) fn is_mapped(&self) -> bool {
+        match self {
+            FontBuffer::Heap(_) => false,
+            #[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+            FontBuffer::Mapped(_) => true,
+        }
+    }
+}
+
+impl Default for FontBuffer {
+    fn default() -> Self {
+        FontBuffer::Heap(Vec::new())
+    }
+}
+
+impl std::ops::Deref for FontBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FontBuffer::Heap(buffer) => buffer,
+            #[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+            FontBuffer::Mapped(map) => map,
+        }
+    }
 }
 
 #[self_referencing]
 pub struct StaticFace {
     key: FontKey,
-    pub(crate) buffer: Arc<Vec<u8>>,
+    index: u32,
+    pub(crate) buffer: Arc<FontBuffer>,
     #[borrows(buffer)]
     #[not_covariant]
     pub(crate) face: Face<'this>,
@@ -571,11 +1051,15 @@ impl StaticFace {
     }
 
     pub fn ascender(&self) -> i16 {
-        self.with_face(|f| f.ascender())
+        self.with_face(|f| vertical_metrics(f).0)
     }
 
     pub fn descender(&self) -> i16 {
-        self.with_face(|f| f.descender())
+        self.with_face(|f| vertical_metrics(f).1)
+    }
+
+    pub fn line_gap(&self) -> i16 {
+        self.with_face(|f| vertical_metrics(f).2)
     }
 
     pub fn units_per_em(&self) -> u16 {
@@ -593,4 +1077,59 @@ impl StaticFace {
     pub fn key(&self) -> FontKey {
         self.borrow_key().clone()
     }
+
+    /// Index of this face in its font collection, 0 for fonts that aren't
+    /// collections
+    pub fn collection_index(&self) -> u32 {
+        *self.borrow_index()
+    }
+}
+
+/// Ascender, descender and line gap of a face, picked from hhea and OS/2 like
+/// [`Face::ascender`] does. Like HarfBuzz, MVAR deltas are rounded rather
+/// than truncated, and also apply when the values come from hhea
+pub(crate) fn vertical_metrics(face: &Face) -> (i16, i16, i16) {
+    let hhea = face.tables().hhea;
+    let os2 = face.tables().os2;
+    let vary = |tag: &[u8; 4], value: i16| {
+        let delta = face
+            .tables()
+            .mvar
+            .filter(|_| face.is_variable())
+            .and_then(|mvar| mvar.metric_offset(Tag::from_bytes(tag), face.variation_coordinates()))
+            .unwrap_or(0.0);
+        (value as f32 + delta).round() as i16
+    };
+    if let Some(os2) = os2.filter(|os2| os2.use_typographic_metrics()) {
+        return (
+            vary(b"hasc", os2.typographic_ascender()),
+            vary(b"hdsc", os2.typographic_descender()),
+            vary(b"hlgp", os2.typographic_line_gap()),
+        );
+    }
+    let ascender = match os2 {
+        Some(os2) if hhea.ascender == 0 && os2.typographic_ascender() == 0 => {
+            vary(b"hcla", os2.windows_ascender())
+        }
+        Some(os2) if hhea.ascender == 0 => vary(b"hasc", os2.typographic_ascender()),
+        _ => vary(b"hasc", hhea.ascender),
+    };
+    let descender = match os2 {
+        Some(os2) if hhea.descender == 0 && os2.typographic_descender() == 0 => {
+            vary(b"hcld", os2.windows_descender())
+        }
+        Some(os2) if hhea.descender == 0 => vary(b"hdsc", os2.typographic_descender()),
+        _ => vary(b"hdsc", hhea.descender),
+    };
+    let line_gap = match os2 {
+        Some(os2) if hhea.ascender == 0 || hhea.descender == 0 => {
+            if os2.typographic_ascender() != 0 || os2.typographic_descender() != 0 {
+                vary(b"hlgp", os2.typographic_line_gap())
+            } else {
+                0
+            }
+        }
+        _ => vary(b"hlgp", hhea.line_gap),
+    };
+    (ascender, descender, line_gap)
 }
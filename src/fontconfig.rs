@@ -0,0 +1,285 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use roxmltree::{Document, Node, ParsingOptions};
+
+use crate::alias::GENERIC_FAMILIES;
+use crate::{AliasTable, Error, FontKey, FontKit, SearchOptions, SearchReport};
+
+/// An `<alias>` rule. When `family` is requested, the `prefer` families are
+/// tried before it, and the `accept` and `default` families after it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FontconfigAlias {
+    pub family: String,
+    pub prefer: Vec<String>,
+    pub accept: Vec<String>,
+    pub default: Vec<String>,
+}
+
+impl FontconfigAlias {
+    /// The families this rule falls back to, in the order fontconfig tries
+    /// them
+    pub fn families(&self) -> impl Iterator<Item = &str> {
+        self.prefer
+            .iter()
+            .chain(self.accept.iter())
+            .chain(self.default.iter())
+            .map(|f| f.as_str())
+    }
+}
+
+/// Font directories and alias rules read from fontconfig XML configuration
+/// files. Only the parts relevant to discovery are understood, `<match>` and
+/// `<selectfont>` rules are ignored. libfontconfig is not needed
+#[derive(Debug, Clone, Default)]
+pub struct Fontconfig {
+    /// Font directories in the order they are declared. Directories that
+    /// don't exist are kept, fontconfig configurations list them routinely
+    pub dirs: Vec<PathBuf>,
+    pub aliases: Vec<FontconfigAlias>,
+}
+
+impl Fontconfig {
+    /// Load the configuration of the system, from `$FONTCONFIG_FILE`,
+    /// `$FONTCONFIG_PATH/fonts.conf` or `/etc/fonts/fonts.conf`
+    pub fn system() -> Result<Self, Error> {
+        let path = match std::env::var_os("FONTCONFIG_FILE") {
+            Some(file) => PathBuf::from(file),
+            None => std::env::var_os("FONTCONFIG_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("/etc/fonts"))
+                .join("fonts.conf"),
+        };
+        Self::from_file(path)
+    }
+
+    /// Load a configuration file and everything it includes
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut config = Fontconfig::default();
+        let mut visited = HashSet::new();
+        config.load_file(path.as_ref(), &mut visited)?;
+        Ok(config)
+    }
+
+    /// Fallback families of a family, merged from every alias rule naming
+    /// it. Family names are compared case-insensitively
+    pub fn fallbacks(&self, family: &str) -> Vec<String> {
+        let mut result: Vec<String> = vec![];
+        let rules = self
+            .aliases
+            .iter()
+            .filter(|a| a.family.eq_ignore_ascii_case(family))
+            .collect::<Vec<_>>();
+        let lists = [
+            rules
+                .iter()
+                .flat_map(|a| a.prefer.iter())
+                .collect::<Vec<_>>(),
+            rules.iter().flat_map(|a| a.accept.iter()).collect(),
+            rules.iter().flat_map(|a| a.default.iter()).collect(),
+        ];
+        for name in lists.into_iter().flatten() {
+            if !name.eq_ignore_ascii_case(family)
+                && !result.iter().any(|f| f.eq_ignore_ascii_case(name))
+            {
+                result.push(name.clone());
+            }
+        }
+        result
+    }
+
+    /// Families configured for the generic names, e.g. `sans-serif`
+    pub fn generic_families(&self) -> HashMap<String, Vec<String>> {
+        GENERIC_FAMILIES
+            .iter()
+            .map(|generic| (generic.to_string(), self.fallbacks(generic)))
+            .filter(|(_, families)| !families.is_empty())
+            .collect()
+    }
+
+    /// An alias table with the fallbacks of every family named by an alias
+    /// rule. Unlike fontconfig, the requested family is always tried before
+    /// its `prefer` families
+    pub fn alias_table(&self) -> AliasTable {
+        let mut table = AliasTable::new();
+        for alias in self.aliases.iter() {
+            // rules closing a loop are dropped, fontconfig ignores them too
+            let _ = table.add(&alias.family, None, self.fallbacks(&alias.family));
+        }
+        table
+    }
+
+    fn load_file(&mut self, path: &Path, visited: &mut HashSet<PathBuf>) -> Result<(), Error> {
+        let real_path = std::fs::canonicalize(path)?;
+        if !visited.insert(real_path.clone()) {
+            return Ok(());
+        }
+        if real_path.is_dir() {
+            return self.load_dir(&real_path, visited);
+        }
+        let text = std::fs::read_to_string(&real_path)?;
+        // fontconfig files always declare the fonts.dtd doctype
+        let options = ParsingOptions {
+            allow_dtd: true,
+            ..ParsingOptions::default()
+        };
+        let doc = Document::parse_with_options(&text, options)?;
+        let base = real_path.parent().unwrap_or(Path::new("/"));
+        for node in doc.root_element().children().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                // like fontconfig, relative dirs without a prefix are taken
+                // from the current directory
+                "dir" => {
+                    let cwd = std::env::current_dir()?;
+                    if let Some(dir) = resolve_path(node, &cwd, base, xdg_data_home) {
+                        self.dirs.push(dir);
+                    }
+                }
+                "include" => {
+                    let include = match resolve_path(node, base, base, xdg_config_home) {
+                        Some(p) => p,
+                        None => continue,
+                    };
+                    let ignore_missing = node.attribute("ignore_missing") == Some("yes");
+                    match self.load_file(&include, visited) {
+                        Err(Error::Io(e))
+                            if e.kind() == std::io::ErrorKind::NotFound && ignore_missing => {}
+                        Err(e) => {
+                            log::warn!("Failed including fontconfig file {:?}: {:?}", include, e)
+                        }
+                        Ok(_) => {}
+                    }
+                }
+                "alias" => self.load_alias(node),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Included directories load their `*.conf` files starting with a digit,
+    /// sorted by name, like the `conf.d` directory of fontconfig
+    fn load_dir(&mut self, path: &Path, visited: &mut HashSet<PathBuf>) -> Result<(), Error> {
+        let mut files = std::fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with(|c: char| c.is_ascii_digit()) && n.ends_with(".conf"))
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        for file in files {
+            if let Err(e) = self.load_file(&file, visited) {
+                log::warn!("Failed loading fontconfig file {:?}: {:?}", file, e);
+            }
+        }
+        Ok(())
+    }
+
+    fn load_alias(&mut self, node: Node) {
+        let families = |tag: &str| {
+            node.children()
+                .filter(|n| n.has_tag_name(tag))
+                .flat_map(|n| n.children().filter(|n| n.has_tag_name("family")))
+                .filter_map(|n| n.text())
+                .map(|t| t.trim().to_string())
+                .collect::<Vec<_>>()
+        };
+        let prefer = families("prefer");
+        let accept = families("accept");
+        let default = families("default");
+        // an alias with several <family> elements applies to each of them
+        for family in node.children().filter(|n| n.has_tag_name("family")) {
+            if let Some(family) = family.text() {
+                self.aliases.push(FontconfigAlias {
+                    family: family.trim().to_string(),
+                    prefer: prefer.clone(),
+                    accept: accept.clone(),
+                    default: default.clone(),
+                });
+            }
+        }
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+fn xdg_data_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|h| h.join(".local/share")))
+}
+
+fn xdg_config_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|h| h.join(".config")))
+}
+
+/// Resolve the path in a `<dir>` or `<include>` element, following its
+/// `prefix` attribute. `prefix="relative"` resolves against `config_dir`,
+/// the directory of the configuration file, and relative paths without a
+/// prefix against `base`
+fn resolve_path(
+    node: Node,
+    base: &Path,
+    config_dir: &Path,
+    xdg_home: fn() -> Option<PathBuf>,
+) -> Option<PathBuf> {
+    let text = node.text()?.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let path = match node.attribute("prefix") {
+        Some("xdg") => xdg_home()?.join(text),
+        Some("relative") => config_dir.join(text),
+        Some("default") | Some("cwd") => std::env::current_dir().ok()?.join(text),
+        _ if text == "~" => home_dir()?,
+        _ => match text.strip_prefix("~/") {
+            Some(rest) => home_dir()?.join(rest),
+            None => base.join(text),
+        },
+    };
+    Some(path)
+}
+
+impl FontKit {
+    /// Scan the font directories of a fontconfig configuration. Directories
+    /// that don't exist are skipped
+    pub fn search_fonts_from_fontconfig(
+        &self,
+        config: &Fontconfig,
+        options: &SearchOptions,
+    ) -> SearchReport {
+        let dirs = config.dirs.iter().filter(|d| d.exists());
+        self.search_fonts_from_paths(dirs, options)
+    }
+
+    /// Use the alias rules of a fontconfig configuration as fallback. A
+    /// family falls back to the first family of its alias rules, which falls
+    /// back to the next one, and so on
+    pub fn set_fallback_from_fontconfig(&mut self, config: &Fontconfig) {
+        let mut next = HashMap::new();
+        for alias in config.aliases.iter() {
+            let families = std::iter::once(alias.family.as_str())
+                .chain(alias.families())
+                .collect::<Vec<_>>();
+            for pair in families.windows(2) {
+                next.entry(pair[0].to_lowercase())
+                    .or_insert_with(|| pair[1].to_string());
+            }
+        }
+        self.set_fallback(move |key: FontKey| {
+            let family = next.get(&key.family.to_lowercase())?;
+            Some(FontKey {
+                family: family.clone(),
+                ..key
+            })
+        });
+    }
+}
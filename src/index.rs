@@ -0,0 +1,204 @@
+use std::fs::Metadata;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::coverage::Coverage;
+use crate::{AliasTable, Error, Font, FontKit, SearchOptions, SearchReport, VariationData};
+
+/// Version of the index format written by [`FontKit::save_index`]. Bump it
+/// whenever the serialized layout of [`VariationData`] or of the coverage
+/// changes
+pub const INDEX_VERSION: u32 = 6;
+
+/// Coarsest modification time granularity of common file systems (FAT).
+/// A file modified this close to the save of the index may have changed
+/// without its modification time changing
+const MTIME_GRANULARITY: Duration = Duration::from_secs(2);
+
+/// Summary of a [`FontKit::load_index`] call
+#[derive(Debug, Default)]
+pub struct IndexReport {
+    /// Fonts restored from the index without touching the font file
+    pub restored: Vec<PathBuf>,
+    /// Result of rescanning the files that changed since the index was saved
+    pub rescanned: SearchReport,
+    /// Files recorded in the index that no longer exist
+    pub removed: Vec<PathBuf>,
+    /// Fonts of the index left out because the [`crate::CollisionPolicy`]
+    /// keeps a font registered under the same key
+    pub shadowed: Vec<PathBuf>,
+}
+
+/// Size and modification time of a font file, used to detect stale entries
+/// and the files [`FontKit::sync_path`] has to load again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct FileStamp {
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+}
+
+impl FileStamp {
+    pub(crate) fn from_metadata(meta: &Metadata) -> Self {
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        FileStamp {
+            size: meta.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+        }
+    }
+
+    fn modified(&self) -> Duration {
+        Duration::new(self.modified_secs, self.modified_nanos)
+    }
+}
+
+/// 64-bit FNV-1a hash of the content of a file, stable across builds
+fn file_hash(path: &Path) -> Result<u64, Error> {
+    let buffer = std::fs::read(path)?;
+    Ok(buffer.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    }))
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    path: PathBuf,
+    stamp: FileStamp,
+    hash: u64,
+    variants: Vec<VariationData>,
+    /// Characters each face covers, indexed by face index
+    coverage: Vec<Coverage>,
+}
+
+impl IndexEntry {
+    /// Whether the file still has the content the entry was saved from. The
+    /// content is only hashed again when the file was modified too close to
+    /// `saved` for its modification time to tell a later change
+    fn is_fresh(&self, meta: &Metadata, saved: Duration) -> bool {
+        if FileStamp::from_metadata(meta) != self.stamp {
+            return false;
+        }
+        if self.stamp.modified() + MTIME_GRANULARITY < saved {
+            return true;
+        }
+        file_hash(&self.path).is_ok_and(|hash| hash == self.hash)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    /// When the index was saved, since the Unix epoch
+    #[serde(default)]
+    saved: Duration,
+    fonts: Vec<IndexEntry>,
+    #[serde(default)]
+    aliases: Option<AliasTable>,
+}
+
+impl FontKit {
+    /// Save the registry into an index file, which could be restored by
+    /// [`FontKit::load_index`] without parsing the fonts again. Only fonts
+    /// backed by a local file are saved, fonts added from buffers without a
+    /// `cache_path` or from other sources are skipped, as well as fonts whose
+    /// file can't be read anymore. The alias table is saved along
+    pub fn save_index(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let saved = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut fonts = vec![];
+        for font in self.fonts.iter() {
+            let font_path = match font.source().and_then(|s| s.file()) {
+                Some(p) => p,
+                None => continue,
+            };
+            let stamped = std::fs::metadata(font_path)
+                .map_err(Error::from)
+                .and_then(|meta| Ok((FileStamp::from_metadata(&meta), file_hash(font_path)?)));
+            let (stamp, hash) = match stamped {
+                Ok(stamped) => stamped,
+                Err(e) => {
+                    log::warn!("Failed indexing font {:?}: {:?}", font_path, e);
+                    continue;
+                }
+            };
+            fonts.push(IndexEntry {
+                path: font_path.to_path_buf(),
+                stamp,
+                hash,
+                variants: font.variants().to_vec(),
+                coverage: font.coverage().to_vec(),
+            });
+        }
+        // keep the output stable for the same registry
+        fonts.sort_by(|a, b| a.path.cmp(&b.path));
+        let index = IndexFile {
+            version: INDEX_VERSION,
+            saved,
+            fonts,
+            aliases: Some(AliasTable::clone(&self.aliases.load())),
+        };
+        let writer = BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(writer, &index)?;
+        Ok(())
+    }
+
+    /// Load an index file written by [`FontKit::save_index`]. Entries whose
+    /// file changed size, modification time or, for files modified around
+    /// the save, content are parsed again, entries whose file is gone are
+    /// dropped. The alias table of the index, if any,
+    /// replaces the current one
+    pub fn load_index(&self, path: impl AsRef<Path>) -> Result<IndexReport, Error> {
+        let reader = BufReader::new(std::fs::File::open(path)?);
+        let index: IndexFile = serde_json::from_reader(reader)?;
+        if index.version != INDEX_VERSION {
+            return Err(Error::IndexVersion {
+                found: index.version,
+                expected: INDEX_VERSION,
+            });
+        }
+        if let Some(aliases) = index.aliases {
+            self.set_aliases(aliases);
+        }
+        let mut report = IndexReport::default();
+        let mut stale = vec![];
+        for entry in index.fonts {
+            match std::fs::metadata(&entry.path) {
+                Ok(meta) if entry.is_fresh(&meta, index.saved) && !entry.variants.is_empty() => {
+                    let font = Font::new(
+                        Some(entry.path.clone()),
+                        entry.variants,
+                        entry.coverage,
+                        self.hit_counter.clone(),
+                    );
+                    self.stamps.insert(entry.path.clone(), entry.stamp);
+                    if self.insert_font(font).is_empty() {
+                        report.shadowed.push(entry.path);
+                    } else {
+                        report.restored.push(entry.path);
+                    }
+                }
+                Ok(_) => stale.push(entry.path),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    report.removed.push(entry.path)
+                }
+                Err(e) => report.rescanned.failed.push((entry.path, e.into())),
+            }
+        }
+        let options = SearchOptions {
+            threads: 1,
+            ..Default::default()
+        };
+        self.load_files(stale, &options, &mut report.rescanned);
+        self.check_lru();
+        Ok(report)
+    }
+}
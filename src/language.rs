@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use crate::{Filter, FontKey, FontKit, StaticFace};
+
+/// Language IDs of the Windows platform, ref: https://learn.microsoft.com/en-us/typography/opentype/spec/name#windows-language-ids
+const WINDOWS_LANGUAGES: &[(u16, &str)] = &[
+    (0x0401, "ar-SA"),
+    (0x0402, "bg-BG"),
+    (0x0403, "ca-ES"),
+    (0x0404, "zh-TW"),
+    (0x0405, "cs-CZ"),
+    (0x0406, "da-DK"),
+    (0x0407, "de-DE"),
+    (0x0408, "el-GR"),
+    (0x0409, "en-US"),
+    (0x040A, "es-ES"),
+    (0x040B, "fi-FI"),
+    (0x040C, "fr-FR"),
+    (0x040D, "he-IL"),
+    (0x040E, "hu-HU"),
+    (0x040F, "is-IS"),
+    (0x0410, "it-IT"),
+    (0x0411, "ja-JP"),
+    (0x0412, "ko-KR"),
+    (0x0413, "nl-NL"),
+    (0x0414, "nb-NO"),
+    (0x0415, "pl-PL"),
+    (0x0416, "pt-BR"),
+    (0x0418, "ro-RO"),
+    (0x0419, "ru-RU"),
+    (0x041A, "hr-HR"),
+    (0x041B, "sk-SK"),
+    (0x041D, "sv-SE"),
+    (0x041E, "th-TH"),
+    (0x041F, "tr-TR"),
+    (0x0421, "id-ID"),
+    (0x0422, "uk-UA"),
+    (0x0424, "sl-SI"),
+    (0x0425, "et-EE"),
+    (0x0426, "lv-LV"),
+    (0x0427, "lt-LT"),
+    (0x042A, "vi-VN"),
+    (0x042D, "eu-ES"),
+    (0x0439, "hi-IN"),
+    (0x043E, "ms-MY"),
+    (0x0456, "gl-ES"),
+    (0x0804, "zh-CN"),
+    (0x0807, "de-CH"),
+    (0x0809, "en-GB"),
+    (0x080A, "es-MX"),
+    (0x080C, "fr-BE"),
+    (0x0810, "it-CH"),
+    (0x0813, "nl-BE"),
+    (0x0814, "nn-NO"),
+    (0x0816, "pt-PT"),
+    (0x0C04, "zh-HK"),
+    (0x0C07, "de-AT"),
+    (0x0C09, "en-AU"),
+    (0x0C0A, "es-ES"),
+    (0x0C0C, "fr-CA"),
+    (0x1004, "zh-SG"),
+    (0x1009, "en-CA"),
+    (0x1404, "zh-MO"),
+];
+
+/// Language IDs of the Macintosh platform, indexed by ID
+const MAC_LANGUAGES: &[&str] = &[
+    "en", "fr", "de", "it", "nl", "sv", "es", "da", "pt", "nb", "he", "ja", "ar", "fi", "el", "is",
+    "mt", "tr", "hr", "zh-Hant", "ur", "hi", "th", "ko", "lt", "pl", "hu", "et", "lv", "se", "fo",
+    "fa", "ru", "zh-Hans", "nl-BE", "ga", "sq", "ro", "cs", "sk", "sl", "yi", "sr", "mk", "bg",
+    "uk", "be", "uz", "kk",
+];
+
+/// BCP-47 tag of a name record, or `None` when the language is unknown or
+/// the record is language-neutral
+pub(crate) fn bcp47_tag(platform_id: u16, language_id: u16) -> Option<&'static str> {
+    match platform_id {
+        // Macintosh
+        1 => MAC_LANGUAGES.get(language_id as usize).copied(),
+        // Windows, unknown sub-languages fall back to the primary language
+        3 => WINDOWS_LANGUAGES
+            .iter()
+            .find(|(id, _)| *id == language_id)
+            .or_else(|| {
+                let primary = language_id & 0x3FF;
+                WINDOWS_LANGUAGES
+                    .iter()
+                    .find(|(id, _)| id & 0x3FF == primary)
+            })
+            .map(|(id, tag)| {
+                if *id == language_id {
+                    tag
+                } else {
+                    tag.split('-').next().unwrap_or(tag)
+                }
+            }),
+        _ => None,
+    }
+}
+
+/// Whether a language tag satisfies a requested one. Matching is
+/// case-insensitive, and a request also covers more specific tags, so "zh"
+/// matches "zh-CN"
+pub(crate) fn language_matches(tag: &str, requested: &str) -> bool {
+    tag.eq_ignore_ascii_case(requested)
+        || (tag.len() > requested.len()
+            && tag.as_bytes()[requested.len()] == b'-'
+            && tag[..requested.len()].eq_ignore_ascii_case(requested))
+}
+
+impl FontKit {
+    /// Query a font by one of its localized names. Fonts carrying the name in
+    /// `language`, a BCP-47 tag such as `zh` or `zh-TW`, are preferred over
+    /// fonts carrying it in other languages. Aliases follow the rules of
+    /// `language` too
+    pub fn query_localized(&self, key: &FontKey, language: &str) -> Option<StaticFace> {
+        let config = self.config.load();
+        let result = self.resolve_alias(key, Some(language), |key| {
+            let candidates = self.named_fonts(key);
+            let localized = candidates
+                .iter()
+                .filter(|font_key| {
+                    self.fonts.get(*font_key).is_some_and(|font| {
+                        font.variants().iter().any(|v| {
+                            v.has_localized_name(&key.family, language, config.name_matching)
+                        })
+                    })
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            let candidates = if localized.is_empty() {
+                candidates
+            } else {
+                localized
+            };
+            let font_key = self.query_font_among(key, candidates)?;
+            self.fonts
+                .get(&font_key)?
+                .face(key, config.match_mode, config.name_matching)
+                .ok()
+        });
+        self.check_lru();
+        result
+    }
+
+    /// Family names of the font a query resolves to, grouped by BCP-47
+    /// language tag. Names whose language is unknown are listed under `und`
+    pub fn localized_family_names(&self, key: &FontKey) -> Option<BTreeMap<String, Vec<String>>> {
+        let config = self.config.load();
+        self.resolve_alias(key, config.locale.as_deref(), |key| {
+            let font = self.fonts.get(&self.query_font_unaliased(key)?)?;
+            let family = Filter::Family(&key.family, config.name_matching);
+            let variant = font
+                .variants()
+                .iter()
+                .find(|v| v.fulfils(&family))
+                .unwrap_or(&font.variants()[0]);
+            Some(variant.family_names_by_language())
+        })
+    }
+}
@@ -6,25 +6,61 @@ use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 pub use ttf_parser::LineMetrics;
 
+mod alias;
+#[cfg(feature = "archive")]
+mod archive;
 #[cfg(all(target_arch = "wasm32", feature = "wit"))]
 mod bindings;
 mod conv;
+mod coverage;
 mod error;
 mod font;
+#[cfg(feature = "fontconfig")]
+mod fontconfig;
+#[cfg(feature = "parse")]
+mod index;
+mod language;
+mod matching;
 #[cfg(feature = "metrics")]
 mod metrics;
+mod name_index;
 #[cfg(feature = "ras")]
 mod ras;
+#[cfg(feature = "parse")]
+mod search;
+mod source;
+#[cfg(feature = "watch")]
+mod watch;
 #[cfg(all(target_arch = "wasm32", feature = "wit"))]
 mod wit;
 
+pub use alias::{AliasTable, GENERIC_FAMILIES};
+#[cfg(feature = "archive")]
+pub use archive::ArchiveSource;
+#[cfg(feature = "parse")]
+pub use conv::{FontFormat, WoffBlocks};
+#[cfg(feature = "metrics")]
+pub use coverage::{Fallback, FallbackRun};
 pub use error::Error;
 pub use font::*;
+#[cfg(feature = "fontconfig")]
+pub use fontconfig::{Fontconfig, FontconfigAlias};
+#[cfg(feature = "parse")]
+pub use index::{IndexReport, INDEX_VERSION};
+pub use matching::{normalize_name, FamilyMatch, MatchMode, NameMatching, QueryCandidate};
 #[cfg(feature = "metrics")]
 pub use metrics::*;
+pub use name_index::CollisionPolicy;
 #[cfg(feature = "ras")]
 pub use ras::*;
+#[cfg(feature = "parse")]
+pub use search::{
+    FontChanges, SearchOptions, SearchProgress, SearchProgressCallback, SearchReport,
+};
+pub use source::{FileSource, FontSource, LoaderSource, MemorySource};
 pub use tiny_skia_path::{self, PathSegment};
+#[cfg(feature = "watch")]
+pub use watch::FontWatcher;
 
 #[cfg(all(target_arch = "wasm32", feature = "wit"))]
 pub use bindings::exports::alibaba::fontkit::fontkit_interface::TextMetrics;
@@ -33,16 +69,32 @@ pub use bindings::exports::alibaba::fontkit::fontkit_interface::TextMetrics;
 #[global_allocator]
 static ALLOCATOR: talc::TalckWasm = unsafe { talc::TalckWasm::new_global() };
 
+#[derive(Clone)]
 struct Config {
     pub lru_limit: u32,
     pub cache_path: Option<String>,
+    pub match_mode: MatchMode,
+    pub name_matching: NameMatching,
+    pub family_language: Option<String>,
+    pub locale: Option<String>,
+    pub strict_validation: bool,
+    pub collision_policy: CollisionPolicy,
+    pub coverage_fallback: bool,
+    pub memory_mapping: bool,
 }
 
 pub struct FontKit {
     fonts: dashmap::DashMap<font::FontKey, Font>,
+    name_index: name_index::NameIndex,
+    coverage_index: coverage::CoverageIndex,
+    aliases: ArcSwap<AliasTable>,
     fallback_font_key: Box<dyn Fn(font::FontKey) -> Option<font::FontKey> + Send + Sync>,
     pub(crate) config: ArcSwap<Config>,
     hit_counter: Arc<AtomicU32>,
+    /// Size and modification time of the files fonts were loaded from, when
+    /// they were read
+    #[cfg(feature = "parse")]
+    stamps: dashmap::DashMap<std::path::PathBuf, index::FileStamp>,
 }
 
 impl FontKit {
@@ -50,12 +102,25 @@ impl FontKit {
     pub fn new() -> Self {
         FontKit {
             fonts: dashmap::DashMap::new(),
+            name_index: name_index::NameIndex::default(),
+            coverage_index: coverage::CoverageIndex::default(),
+            aliases: ArcSwap::new(Arc::new(AliasTable::new())),
             fallback_font_key: Box::new(|_| None),
             config: ArcSwap::new(Arc::new(Config {
                 lru_limit: 0,
                 cache_path: None,
+                match_mode: MatchMode::default(),
+                name_matching: NameMatching::default(),
+                family_language: None,
+                locale: None,
+                strict_validation: false,
+                collision_policy: CollisionPolicy::default(),
+                coverage_fallback: false,
+                memory_mapping: false,
             })),
             hit_counter: Arc::default(),
+            #[cfg(feature = "parse")]
+            stamps: dashmap::DashMap::new(),
         }
     }
 
@@ -63,6 +128,94 @@ impl FontKit {
         self.fonts.len()
     }
 
+    /// Choose how queries pick a font among the fonts of a family
+    pub fn set_match_mode(&self, match_mode: MatchMode) {
+        self.config.rcu(|config| Config {
+            match_mode,
+            ..Config::clone(config)
+        });
+    }
+
+    /// Choose how family names in queries are compared with font names
+    pub fn set_name_matching(&self, name_matching: NameMatching) {
+        self.config.rcu(|config| Config {
+            name_matching,
+            ..Config::clone(config)
+        });
+    }
+
+    /// Build the family of font keys from the family name in a BCP-47
+    /// language, e.g. `zh` or `ja-JP`, when the font has one. Only fonts
+    /// added afterwards are affected. By default the shortest ASCII name is
+    /// used
+    pub fn set_family_language(&self, language: Option<&str>) {
+        let family_language = language.map(|l| l.to_string());
+        self.config.rcu(|config| Config {
+            family_language: family_language.clone(),
+            ..Config::clone(config)
+        });
+    }
+
+    /// Set the BCP-47 language whose alias rules queries follow, e.g. `zh`
+    /// to resolve `sans-serif` to a Chinese font
+    pub fn set_locale(&self, locale: Option<&str>) {
+        let locale = locale.map(|l| l.to_string());
+        self.config.rcu(|config| Config {
+            locale: locale.clone(),
+            ..Config::clone(config)
+        });
+    }
+
+    /// Sanitize fonts added afterwards before parsing them: their table
+    /// directory, table bounds, checksums and fvar records are checked, and
+    /// fonts failing the checks are rejected with the error found
+    pub fn set_strict_validation(&self, strict_validation: bool) {
+        self.config.rcu(|config| Config {
+            strict_validation,
+            ..Config::clone(config)
+        });
+    }
+
+    /// Choose what adding a font does when a font from another file is
+    /// already registered under its key
+    pub fn set_collision_policy(&self, collision_policy: CollisionPolicy) {
+        self.config.rcu(|config| Config {
+            collision_policy,
+            ..Config::clone(config)
+        });
+    }
+
+    /// Let [`FontKit::measure`] fill the characters left missing after the
+    /// fallback set by [`FontKit::set_fallback`] from the fonts
+    /// [`FontKit::resolve_fallback`] picks. Off by default
+    #[cfg(feature = "metrics")]
+    pub fn set_coverage_fallback(&self, coverage_fallback: bool) {
+        self.config.rcu(|config| Config {
+            coverage_fallback,
+            ..Config::clone(config)
+        });
+    }
+
+    /// Map the files of fonts added afterwards into memory instead of reading
+    /// them, so that they don't take resident memory until their pages are
+    /// used. Off by default
+    ///
+    /// # Safety
+    ///
+    /// Mapped files must not be truncated or rewritten in place while the
+    /// registry holds their fonts: reads of the mapping would then fault
+    /// (`SIGBUS` on Unix) or see the data change under parsed faces. Files
+    /// replaced by renaming a new file over them are fine. This rules out
+    /// directories where fonts are edited in place, even when a `FontWatcher`
+    /// or [`FontKit::sync_path`] follows them
+    #[cfg(all(feature = "mmap", not(target_arch = "wasm32")))]
+    pub unsafe fn set_memory_mapping(&self, memory_mapping: bool) {
+        self.config.rcu(|config| Config {
+            memory_mapping,
+            ..Config::clone(config)
+        });
+    }
+
     /// Setup a font as fallback. When measure fails, FontKit will use this
     /// fallback to measure, if possible
     pub fn set_fallback(
@@ -72,6 +225,10 @@ impl FontKit {
         self.fallback_font_key = Box::new(callback);
     }
 
+    /// Measure a text. Characters the font lacks are measured with the
+    /// fallback set by [`FontKit::set_fallback`], and then, if enabled by
+    /// [`FontKit::set_coverage_fallback`], with the fonts
+    /// [`FontKit::resolve_fallback`] picks for them
     #[cfg(feature = "metrics")]
     pub fn measure(&self, font_key: &font::FontKey, text: &str) -> Option<metrics::TextMetrics> {
         let mut used_keys = HashSet::new();
@@ -112,13 +269,20 @@ impl FontKit {
                 None => break,
             }
         }
+        if let Some(metrics) = current_metrics
+            .as_ref()
+            .filter(|m| self.config.load().coverage_fallback && m.has_missing())
+        {
+            self.fill_from_coverage(font_key, metrics);
+        }
         current_metrics
     }
 
     pub fn remove(&self, key: font::FontKey) {
-        self.fonts.remove(&key);
+        self.remove_font(&key);
     }
 
+    /// Total size of loaded font buffers, including memory-mapped ones
     pub fn buffer_size(&self) -> usize {
         self.fonts
             .iter()
@@ -126,23 +290,34 @@ impl FontKit {
             .sum::<usize>()
     }
 
+    /// Size of font buffers mapped from files. These pages are backed by the
+    /// files and not counted by the LRU limit
+    pub fn mapped_buffer_size(&self) -> usize {
+        self.fonts
+            .iter()
+            .map(|font| font.mapped_size())
+            .sum::<usize>()
+    }
+
+    /// Size of font buffers allocated on the heap
+    pub fn heap_buffer_size(&self) -> usize {
+        self.buffer_size() - self.mapped_buffer_size()
+    }
+
     pub fn check_lru(&self) {
         let limit = self.config.load().lru_limit as usize * 1024;
         if limit == 0 {
             return;
         }
-        let mut current_size = self.buffer_size();
-        let mut loaded_fonts = self.fonts.iter().filter(|f| f.buffer_size() > 0).count();
+        let in_heap = |f: &Font| f.buffer_size() > f.mapped_size();
+        let mut current_size = self.heap_buffer_size();
+        let mut loaded_fonts = self.fonts.iter().filter(|f| in_heap(f)).count();
         while current_size > limit && loaded_fonts > 1 {
-            let font = self
-                .fonts
-                .iter()
-                .filter(|f| f.buffer_size() > 0)
-                .min_by(|a, b| {
-                    b.hit_index
-                        .load(Ordering::SeqCst)
-                        .cmp(&a.hit_index.load(Ordering::SeqCst))
-                });
+            let font = self.fonts.iter().filter(|f| in_heap(f)).min_by(|a, b| {
+                b.hit_index
+                    .load(Ordering::SeqCst)
+                    .cmp(&a.hit_index.load(Ordering::SeqCst))
+            });
 
             let hit_index = font
                 .as_ref()
@@ -151,117 +326,150 @@ impl FontKit {
             if let Some(f) = font {
                 f.unload();
             }
-            if current_size == self.buffer_size() {
+            if current_size == self.heap_buffer_size() {
                 break;
             }
-            current_size = self.buffer_size();
+            current_size = self.heap_buffer_size();
             self.hit_counter.fetch_sub(hit_index, Ordering::SeqCst);
             for f in self.fonts.iter() {
                 f.hit_index.fetch_sub(hit_index, Ordering::SeqCst);
             }
-            loaded_fonts = self.fonts.iter().filter(|f| f.buffer_size() > 0).count();
+            loaded_fonts = self.fonts.iter().filter(|f| in_heap(f)).count();
         }
     }
 
     /// Add fonts from a buffer. This will load the fonts and store the buffer
     /// in FontKit. Type information is inferred from the magic number using
-    /// `infer` crate.
+    /// `infer` crate. Return the keys of the registered fonts, none if the
+    /// [`CollisionPolicy`] keeps a registered font instead
+    #[cfg(feature = "parse")]
+    pub fn add_font_from_buffer(&self, buffer: Vec<u8>) -> Result<Vec<FontKey>, Error> {
+        let strict = self.config.load().strict_validation;
+        let mut font = Font::from_buffer(buffer.clone(), self.hit_counter.clone(), strict)?;
+        self.prepare_font(&mut font);
+        let first_key = font.first_key();
+        let registered = self.fonts.get(&first_key).and_then(|v| v.source().cloned());
+        // the collision is resolved before caching, so that the cache file
+        // is named after the key the font gets
+        let keys = self.register_font(font);
+        let Some(key) = keys.first() else {
+            return Ok(keys);
+        };
+        // a font replacing a registered one takes over its source
+        let source = registered.filter(|_| *key == first_key);
+        if let (Some(source), Some(mut font)) = (source.as_ref(), self.fonts.get_mut(key)) {
+            font.set_source(source.clone());
+        }
+        match self.cache_buffer(key, source.as_deref(), &buffer) {
+            Ok(Some(path)) => {
+                if let Some(mut font) = self.fonts.get_mut(key) {
+                    font.set_path(path);
+                    font.unload();
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                self.remove_font(key);
+                return Err(e);
+            }
+        }
+        self.check_lru();
+        Ok(keys)
+    }
+
+    /// Dump the buffer of the font registered under `key` into the cache
+    /// path, if any, returning the file it was written to
     #[cfg(feature = "parse")]
-    pub fn add_font_from_buffer(&self, buffer: Vec<u8>) -> Result<(), Error> {
+    fn cache_buffer(
+        &self,
+        key: &FontKey,
+        source: Option<&dyn FontSource>,
+        buffer: &[u8],
+    ) -> Result<Option<std::path::PathBuf>, Error> {
         use std::fs::File;
         use std::io::Write;
         use std::path::PathBuf;
         use std::str::FromStr;
 
-        let mut font = Font::from_buffer(buffer.clone(), self.hit_counter.clone())?;
-        let key = font.first_key();
-        if let Some(v) = self.fonts.get(&key) {
-            if let Some(path) = v.path().cloned() {
-                font.set_path(path);
-            }
-        }
         let cache_path = self.config.load().cache_path.clone();
-        if let Some(mut path) = cache_path.and_then(|p| PathBuf::from_str(&p).ok()) {
-            if let Some(original_path) = font.path() {
-                let relative_path = if original_path.is_absolute()
-                    && !std::fs::exists(original_path.clone()).unwrap_or(false)
-                {
-                    format!(".{}", original_path.display())
-                } else {
-                    format!("{}", original_path.display())
-                };
-                path.push(relative_path);
-                let mut dir = path.clone();
-                dir.pop();
-                std::fs::create_dir_all(dir)?;
+        let Some(mut path) = cache_path.and_then(|p| PathBuf::from_str(&p).ok()) else {
+            return Ok(None);
+        };
+        if let Some(original_path) = source.and_then(|s| s.path()) {
+            let relative_path = if original_path.is_absolute()
+                && !std::fs::exists(original_path).unwrap_or(false)
+            {
+                format!(".{}", original_path.display())
             } else {
-                path.push(format!(
-                    "{}_{}_{}_{}.ttf",
-                    key.family.replace(['.', ' '], "_"),
-                    key.italic.unwrap_or_default(),
-                    key.stretch.unwrap_or(5),
-                    key.weight.unwrap_or(400)
-                ));
-            }
-            let mut f = File::create(&path)?;
-            f.write_all(&buffer)?;
-            font.set_path(path);
-            font.unload();
+                format!("{}", original_path.display())
+            };
+            path.push(relative_path);
+            let mut dir = path.clone();
+            dir.pop();
+            std::fs::create_dir_all(&dir).map_err(Error::file_access(dir))?;
+        } else {
+            path.push(format!(
+                "{}_{}_{}_{}{}.ttf",
+                key.family.replace(['.', ' '], "_"),
+                key.italic.unwrap_or_default(),
+                key.stretch.unwrap_or(5),
+                key.weight.unwrap_or(400),
+                key.handle.map(|h| format!("_{}", h)).unwrap_or_default()
+            ));
         }
-        self.fonts.insert(key, font);
+        File::create(&path)
+            .and_then(|mut f| f.write_all(buffer))
+            .map_err(Error::file_access(&path))?;
+        Ok(Some(path))
+    }
+
+    /// Register a font whose data is fetched from `source`. The font is
+    /// parsed once for indexing, and then unloaded until it is queried.
+    /// Return the keys of the registered fonts, like
+    /// [`FontKit::add_font_from_buffer`]
+    #[cfg(feature = "parse")]
+    pub fn add_font_from_source(
+        &self,
+        source: impl FontSource + 'static,
+    ) -> Result<Vec<FontKey>, Error> {
+        let config = self.config.load();
+        let font = Font::from_source(
+            Arc::new(source),
+            self.hit_counter.clone(),
+            config.strict_validation,
+            config.memory_mapping,
+        )?;
+        font.unload();
+        let keys = self.insert_font(font);
         self.check_lru();
-        Ok(())
+        Ok(keys)
     }
 
     /// Recursively scan a local path for fonts, this method will not store the
-    /// font buffer to reduce memory consumption
+    /// font buffer to reduce memory consumption. Symlinks are followed, and
+    /// per-file failures are collected in the returned report instead of
+    /// aborting the scan
     #[cfg(feature = "parse")]
-    pub fn search_fonts_from_path(&self, path: impl AsRef<Path>) -> Result<(), Error> {
-        use std::io::Read;
-        // if path.as_ref().is_dir() {
-        //     let mut result = vec![];
-        //     if let Ok(data) = fs::read_dir(path) {
-        //         for entry in data {
-        //             if let Ok(entry) = entry {
-        //
-        // result.extend(load_font_from_path(&entry.path()).into_iter().flatten());
-        //             }
-        //         }
-        //     }
-        //     return Some(result);
-        // }
-
-        let mut buffer = Vec::new();
+    pub fn search_fonts_from_path(&self, path: impl AsRef<Path>) -> Result<SearchReport, Error> {
         let path = path.as_ref();
-        let ext = path
-            .extension()
-            .and_then(|s| s.to_str())
-            .map(|s| s.to_lowercase());
-        let ext = ext.as_deref();
-        let ext = match ext {
-            Some(e) => e,
-            None => return Ok(()),
-        };
-        match ext {
-            "ttf" | "otf" | "ttc" | "woff2" | "woff" => {
-                let mut file = std::fs::File::open(&path).unwrap();
-                buffer.clear();
-                file.read_to_end(&mut buffer).unwrap();
-                let mut font = match Font::from_buffer(buffer, self.hit_counter.clone()) {
-                    Ok(f) => f,
-                    Err(e) => {
-                        log::warn!("Failed loading font {:?}: {:?}", path, e);
-                        return Ok(());
-                    }
-                };
-                font.set_path(path.to_path_buf());
-                font.unload();
-                self.fonts.insert(font.first_key(), font);
-                self.check_lru();
-            }
-            _ => {}
+        let mut report = SearchReport::default();
+        let mut files = vec![];
+        if std::fs::metadata(path)
+            .map_err(Error::file_access(path))?
+            .is_dir()
+        {
+            let mut visited = HashSet::new();
+            self.collect_dir(path, &mut visited, &mut files, &mut report);
+        } else {
+            self.collect_file(path, &mut files, &mut report);
         }
-        Ok(())
+        let options = SearchOptions {
+            threads: 1,
+            ..Default::default()
+        };
+        self.load_files(files, &options, &mut report);
+        self.check_lru();
+        Ok(report)
     }
 
     pub fn exact_match(&self, key: &font::FontKey) -> Option<StaticFace> {
@@ -283,32 +491,94 @@ impl FontKit {
         }
     }
 
+    /// Query a font. Families are resolved through the alias table, so
+    /// generic families such as `sans-serif` find an installed font
     pub fn query(&self, key: &font::FontKey) -> Option<StaticFace> {
-        let result = self.fonts.get(&self.query_font(key)?)?.face(key).ok();
+        let config = self.config.load();
+        let result = self.query_font(key).and_then(|(font_key, key)| {
+            self.fonts
+                .get(&font_key)?
+                .face(&key, config.match_mode, config.name_matching)
+                .ok()
+        });
         self.check_lru();
         result
     }
 
-    pub(crate) fn query_font(&self, key: &font::FontKey) -> Option<font::FontKey> {
-        let mut search_results = self
-            .fonts
-            .iter()
-            .map(|item| item.key().clone())
-            .collect::<HashSet<_>>();
-        let filters = Filter::from_key(key);
-        for filter in filters {
+    /// Query the face at `index` of a font collection, among the fonts the
+    /// family of `key` resolves to. Other fields of `key` pick among the
+    /// variation instances of that face
+    pub fn query_collection_face(&self, key: &font::FontKey, index: u32) -> Option<StaticFace> {
+        let config = self.config.load();
+        let result = self.resolve_alias(key, config.locale.as_deref(), |key| {
+            let family = Filter::Family(&key.family, config.name_matching);
+            self.named_fonts(key)
+                .into_iter()
+                .filter_map(|font_key| self.fonts.get(&font_key))
+                .find(|font| {
+                    font.variants()
+                        .iter()
+                        .any(|v| v.index == index && v.fulfils(&family))
+                })?
+                .collection_face(key, index, config.match_mode, config.name_matching)
+                .ok()
+        });
+        self.check_lru();
+        result
+    }
+
+    /// Resolve a query through the alias table to the registry key of the
+    /// font answering it, along with the query for the family it was found by
+    pub(crate) fn query_font(
+        &self,
+        key: &font::FontKey,
+    ) -> Option<(font::FontKey, font::FontKey)> {
+        let locale = self.config.load().locale.clone();
+        self.resolve_alias(key, locale.as_deref(), |key| {
+            Some((self.query_font_unaliased(key)?, key.clone()))
+        })
+    }
+
+    pub(crate) fn query_font_unaliased(&self, key: &font::FontKey) -> Option<font::FontKey> {
+        // only fonts carrying the family name can pass the family filter
+        self.query_font_among(key, self.named_fonts(key))
+    }
+
+    /// Pick the font answering a query among the given registry keys
+    fn query_font_among(
+        &self,
+        key: &font::FontKey,
+        candidates: Vec<font::FontKey>,
+    ) -> Option<font::FontKey> {
+        let config = self.config.load();
+        match config.match_mode {
+            MatchMode::Filter => self.filter_font(key, config.name_matching, candidates),
+            MatchMode::Css => self.css_match_font(key, config.name_matching, candidates),
+        }
+    }
+
+    fn filter_font(
+        &self,
+        key: &font::FontKey,
+        name_matching: NameMatching,
+        candidates: Vec<font::FontKey>,
+    ) -> Option<font::FontKey> {
+        let filters = Filter::from_key(key, name_matching);
+        // evaluate every filter once per font up front
+        let mut search_results = candidates
+            .into_iter()
+            .filter_map(|font_key| {
+                let font = self.fonts.get(&font_key)?;
+                let passed = filters.iter().map(|f| font.fulfils(f)).collect::<Vec<_>>();
+                Some((font_key, passed))
+            })
+            .collect::<Vec<_>>();
+        for (i, filter) in filters.iter().enumerate() {
             let mut s = search_results.clone();
-            let is_family = if let Filter::Family(_) = filter {
-                true
-            } else {
-                false
-            };
-            s.retain(|key| {
-                let font = self.fonts.get(key).unwrap();
-                font.fulfils(&filter)
-            });
+            let is_family = matches!(filter, Filter::Family(..));
+            s.retain(|(_, passed)| passed[i]);
             match s.len() {
-                1 => return s.iter().next().cloned(),
+                1 => return s.pop().map(|(key, _)| key),
                 0 if is_family => return None,
                 0 => {}
                 _ => search_results = s,
@@ -317,6 +587,29 @@ impl FontKit {
         None
     }
 
+    fn css_match_font(
+        &self,
+        key: &font::FontKey,
+        name_matching: NameMatching,
+        font_keys: Vec<font::FontKey>,
+    ) -> Option<font::FontKey> {
+        let family = Filter::Family(&key.family, name_matching);
+        let mut candidates = vec![];
+        for font_key in font_keys {
+            let font = match self.fonts.get(&font_key) {
+                Some(font) => font,
+                None => continue,
+            };
+            for variant in font.variants().iter().filter(|v| v.fulfils(&family)) {
+                let instance = variant.instance_key(key, name_matching);
+                candidates.push((font_key.clone(), instance));
+            }
+        }
+        // DashMap iterates in arbitrary order, keep ties deterministic
+        candidates.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        matching::css_match(key, candidates)
+    }
+
     pub fn keys(&self) -> Vec<FontKey> {
         self.fonts
             .iter()
@@ -332,7 +625,7 @@ impl FontKit {
 }
 
 enum Filter<'a> {
-    Family(&'a str),
+    Family(&'a str, NameMatching),
     Italic(bool),
     Weight(u16),
     Stretch(u16),
@@ -340,8 +633,8 @@ enum Filter<'a> {
 }
 
 impl<'a> Filter<'a> {
-    pub fn from_key(key: &'a FontKey) -> Vec<Filter<'a>> {
-        let mut filters = vec![Filter::Family(&key.family)];
+    pub fn from_key(key: &'a FontKey, name_matching: NameMatching) -> Vec<Filter<'a>> {
+        let mut filters = vec![Filter::Family(&key.family, name_matching)];
         if let Some(italic) = key.italic {
             filters.push(Filter::Italic(italic));
         }
@@ -0,0 +1,368 @@
+use std::cmp::Ordering;
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::font::VariationData;
+use crate::{FontKey, FontKit};
+
+/// How [`FontKit::query`](crate::FontKit::query) picks a font among the
+/// fonts of a family
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchMode {
+    /// Narrow the candidates with exact-equality filters on italic, weight,
+    /// stretch and variations, succeeding only when a single font is left
+    #[default]
+    Filter,
+    /// The [CSS Fonts Level 4](https://www.w3.org/TR/css-fonts-4/#font-style-matching)
+    /// font matching algorithm: nearest stretch, then style, then weight.
+    /// Faces with a slant are indexed as italic, so oblique requests are
+    /// served by italic faces
+    Css,
+}
+
+/// How family names in queries are compared with the names of fonts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameMatching {
+    /// Names must be equal
+    Strict,
+    /// Names are compared after [`normalize_name`], so "open sans",
+    /// "OpenSans" and "Open-Sans" all match "Open Sans"
+    #[default]
+    Normalized,
+}
+
+/// Normalize a font name for matching: Unicode NFKC, case folding, and
+/// whitespace and hyphens removed
+pub fn normalize_name(name: &str) -> String {
+    name.nfkc()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect()
+}
+
+/// How the family of a [`QueryCandidate`] matched the requested family
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FamilyMatch {
+    /// The family of the font key, compared according to the
+    /// [`NameMatching`] of the registry
+    Exact,
+    /// Another name of the font, e.g. its full name or PostScript name
+    Name,
+    /// The name of a named instance of a variable font
+    InstanceName,
+    /// None of the names match, but one is a few edits away once normalized
+    Similar { edits: u32 },
+}
+
+impl FamilyMatch {
+    fn edits(&self) -> u32 {
+        match self {
+            FamilyMatch::Similar { edits } => *edits,
+            _ => 0,
+        }
+    }
+}
+
+/// A font variant returned by [`FontKit::query_candidates`], with the
+/// distances it was ranked by. Fields of the query that are `None` always
+/// match
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryCandidate {
+    /// Key of the variant, which [`FontKit::exact_match`] resolves
+    pub key: FontKey,
+    /// Why the variant was kept as a candidate
+    pub family_match: FamilyMatch,
+    /// Difference between the requested and the actual stretch class
+    pub stretch_distance: u16,
+    pub italic_matches: bool,
+    /// Difference between the requested and the actual weight
+    pub weight_distance: u16,
+    /// Whether the variant has every requested variation axis value
+    pub variations_match: bool,
+}
+
+impl QueryCandidate {
+    pub(crate) fn new(query: &FontKey, key: FontKey, family_match: FamilyMatch) -> Self {
+        let stretch_distance = query
+            .stretch
+            .map(|s| s.abs_diff(key.stretch.unwrap_or(5)))
+            .unwrap_or(0);
+        let italic_matches = query
+            .italic
+            .map(|i| i == key.italic.unwrap_or(false))
+            .unwrap_or(true);
+        let weight_distance = query
+            .weight
+            .map(|w| w.abs_diff(key.weight.unwrap_or(400)))
+            .unwrap_or(0);
+        let variations_match = query
+            .variations
+            .iter()
+            .all(|v| key.variations.iter().any(|kv| variation_matches(kv, v)));
+        QueryCandidate {
+            key,
+            family_match,
+            stretch_distance,
+            italic_matches,
+            weight_distance,
+            variations_match,
+        }
+    }
+
+    /// Whether every field of the query matches exactly
+    pub fn is_exact(&self) -> bool {
+        self.family_match.edits() == 0
+            && self.stretch_distance == 0
+            && self.italic_matches
+            && self.weight_distance == 0
+            && self.variations_match
+    }
+
+    pub(crate) fn rank(&self) -> (u32, u16, bool, u16, bool) {
+        (
+            self.family_match.edits(),
+            self.stretch_distance,
+            !self.italic_matches,
+            self.weight_distance,
+            !self.variations_match,
+        )
+    }
+
+    /// Sort key of the candidate, following the preferences of
+    /// [`css_match`]: stretch on the preferred side first, then style, then
+    /// weight in the asymmetric CSS order. Unset fields of the query take
+    /// their CSS initial value
+    fn css_rank(&self, query: &FontKey) -> (u32, (u8, u16), bool, (u8, u16), bool) {
+        let italic = self.key.italic.unwrap_or(false);
+        (
+            self.family_match.edits(),
+            stretch_rank(query.stretch.unwrap_or(5), self.key.stretch.unwrap_or(5)),
+            italic != query.italic.unwrap_or(false),
+            weight_rank(query.weight.unwrap_or(400), self.key.weight.unwrap_or(400)),
+            !self.variations_match,
+        )
+    }
+}
+
+impl FontKit {
+    /// List every font variant that could answer a query, best first. Unlike
+    /// [`FontKit::query`], this neither fails on ambiguous queries nor picks
+    /// for the caller. Variants are ranked by family, then in the order CSS
+    /// font matching prefers them, so that the first exact family candidate
+    /// is the one [`MatchMode::Css`] picks. Families a few typos away are
+    /// included, for "did you mean" suggestions. The candidates of the
+    /// families the requested family is aliased to follow, in the order
+    /// [`FontKit::query`] tries them
+    pub fn query_candidates(&self, key: &FontKey) -> Vec<QueryCandidate> {
+        let config = self.config.load();
+        let families = self
+            .aliases
+            .load()
+            .resolve(&key.family, config.locale.as_deref());
+        let mut candidates: Vec<QueryCandidate> = vec![];
+        for (i, family) in families.into_iter().enumerate() {
+            let key = FontKey {
+                family,
+                ..key.clone()
+            };
+            for candidate in self.family_candidates(&key, config.name_matching, i == 0) {
+                if candidates.iter().all(|c| c.key != candidate.key) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Candidates of the family of `key`, ranked. With `similar`, families a
+    /// few typos away are included
+    fn family_candidates(
+        &self,
+        key: &FontKey,
+        name_matching: NameMatching,
+        similar: bool,
+    ) -> Vec<QueryCandidate> {
+        let family = normalize_name(&key.family);
+        let max_edits = (family.chars().count() as u32 / 4).max(1);
+        let similar_match = |variant: &VariationData| {
+            if !similar {
+                return None;
+            }
+            let edits = std::iter::once(&variant.key.family)
+                .chain(variant.names.iter().map(|n| &n.name))
+                .map(|name| edit_distance(&family, &normalize_name(name)))
+                .min()?;
+            (edits <= max_edits).then_some(FamilyMatch::Similar { edits })
+        };
+        let mut candidates = vec![];
+        let mut push = |variant: &VariationData, family_match| {
+            let instance = variant.instance_key(key, name_matching);
+            candidates.push(QueryCandidate::new(key, instance, family_match));
+        };
+
+        // only the fonts carrying the family among their names can match it
+        let named = self.named_fonts(key);
+        for font in named.iter().filter_map(|k| self.fonts.get(k)) {
+            for variant in font.variants() {
+                let family_match = variant
+                    .family_match(&key.family, name_matching)
+                    .or_else(|| similar_match(variant));
+                if let Some(family_match) = family_match {
+                    push(variant, family_match);
+                }
+            }
+        }
+        // typos can only be found by comparing with every font
+        if similar {
+            for font in self.fonts.iter() {
+                if font.key().handle != key.handle || named.contains(font.key()) {
+                    continue;
+                }
+                for variant in font.variants() {
+                    if let Some(family_match) = similar_match(variant) {
+                        push(variant, family_match);
+                    }
+                }
+            }
+        }
+        candidates.sort_by(|a, b| {
+            a.css_rank(key)
+                .cmp(&b.css_rank(key))
+                .then_with(|| a.key.partial_cmp(&b.key).unwrap_or(Ordering::Equal))
+        });
+        candidates
+    }
+}
+
+/// Whether two axis coordinates are equal, up to the precision of the 16.16
+/// fixed point numbers fonts store them in, and the rounding of callers
+pub(crate) fn variation_matches(a: &(String, f32), b: &(String, f32)) -> bool {
+    a.0 == b.0 && (a.1 - b.1).abs() < VARIATION_TOLERANCE
+}
+
+/// Largest difference between axis coordinates still considered equal
+const VARIATION_TOLERANCE: f32 = 0.01;
+
+/// Levenshtein distance between two strings, counted in chars
+fn edit_distance(a: &str, b: &str) -> u32 {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len() as u32).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i as u32 + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != *cb) as u32;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Select a candidate the way browsers select a face of a family. Unset
+/// fields of `key` take their CSS initial value. Candidates still tied after
+/// stretch, style and weight prefer matching variations, then come in the
+/// given order
+pub(crate) fn css_match<T>(key: &FontKey, candidates: Vec<(T, FontKey)>) -> Option<T> {
+    let stretch = key.stretch.unwrap_or(5);
+    let italic = key.italic.unwrap_or(false);
+    let weight = key.weight.unwrap_or(400);
+
+    let values = |c: &[(T, FontKey)], f: fn(&FontKey) -> u16| {
+        c.iter().map(|(_, k)| f(k)).collect::<Vec<_>>()
+    };
+    let stretch_of = |k: &FontKey| k.stretch.unwrap_or(5);
+    let weight_of = |k: &FontKey| k.weight.unwrap_or(400);
+
+    let mut candidates = candidates;
+    let chosen = nearest_stretch(stretch, &values(&candidates, stretch_of))?;
+    candidates.retain(|(_, k)| stretch_of(k) == chosen);
+
+    let has_style = candidates
+        .iter()
+        .any(|(_, k)| k.italic.unwrap_or(false) == italic);
+    let chosen = if has_style { italic } else { !italic };
+    candidates.retain(|(_, k)| k.italic.unwrap_or(false) == chosen);
+
+    let chosen = nearest_weight(weight, &values(&candidates, weight_of))?;
+    candidates.retain(|(_, k)| weight_of(k) == chosen);
+
+    let index = candidates
+        .iter()
+        .position(|(_, k)| {
+            key.variations
+                .iter()
+                .all(|v| k.variations.iter().any(|kv| variation_matches(kv, v)))
+        })
+        .unwrap_or(0);
+    Some(candidates.swap_remove(index).0)
+}
+
+/// Returns `desired` if available, otherwise the closest value on one side
+/// of it, falling back to the closest value on the other side
+fn closest(desired: u16, available: &[u16], below_first: bool) -> Option<u16> {
+    if available.contains(&desired) {
+        return Some(desired);
+    }
+    let below = available.iter().filter(|v| **v < desired).max();
+    let above = available.iter().filter(|v| **v > desired).min();
+    if below_first {
+        below.or(above).copied()
+    } else {
+        above.or(below).copied()
+    }
+}
+
+/// Order in which [`nearest_stretch`] considers `actual`: the desired value,
+/// then values on the preferred side, then on the other side, nearest first
+fn stretch_rank(desired: u16, actual: u16) -> (u8, u16) {
+    match actual.cmp(&desired) {
+        Ordering::Equal => (0, 0),
+        Ordering::Less if desired <= 5 => (1, desired - actual),
+        Ordering::Greater if desired > 5 => (1, actual - desired),
+        _ => (2, actual.abs_diff(desired)),
+    }
+}
+
+/// Order in which [`nearest_weight`] considers `actual`
+fn weight_rank(desired: u16, actual: u16) -> (u8, u16) {
+    if actual == desired {
+        return (0, 0);
+    }
+    if (400..=500).contains(&desired) {
+        return match actual {
+            a if a > desired && a <= 500 => (1, a - desired),
+            a if a < desired => (2, desired - a),
+            a => (3, a - desired),
+        };
+    }
+    match (actual < desired, desired < 400) {
+        (true, true) | (false, false) => (1, actual.abs_diff(desired)),
+        _ => (2, actual.abs_diff(desired)),
+    }
+}
+
+/// Narrower widths are checked first for normal and condensed requests,
+/// wider ones first for expanded requests
+fn nearest_stretch(desired: u16, available: &[u16]) -> Option<u16> {
+    closest(desired, available, desired <= 5)
+}
+
+/// Weights between 400 and 500 check heavier weights up to 500 first, then
+/// lighter weights, then weights over 500. Lighter requests look for lighter
+/// weights first, heavier requests for heavier weights first
+fn nearest_weight(desired: u16, available: &[u16]) -> Option<u16> {
+    if (400..=500).contains(&desired) {
+        let up_to_500 = available
+            .iter()
+            .filter(|v| **v >= desired && **v <= 500)
+            .min();
+        if let Some(weight) = up_to_500 {
+            return Some(*weight);
+        }
+        let below = available.iter().filter(|v| **v < desired).max();
+        let over_500 = available.iter().filter(|v| **v > 500).min();
+        return below.or(over_500).copied();
+    }
+    closest(desired, available, desired < 400)
+}
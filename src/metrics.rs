@@ -1,3 +1,4 @@
+use crate::font::vertical_metrics;
 use crate::{Error, StaticFace};
 pub use compose::*;
 use std::borrow::Cow;
@@ -68,7 +69,8 @@ impl StaticFace {
             let levels = value.nfc().map(|_| Level::ltr()).collect();
             (value, levels)
         };
-        let (height, line_gap) = self.with_face(|f| (f.height(), f.line_gap()));
+        let (ascender, descender, line_gap) = self.with_face(vertical_metrics);
+        let height = ascender - descender;
         for (char_code, level) in value.nfc().zip(levels.into_iter()) {
             if char_code == '\n' {
                 continue;
@@ -102,18 +104,20 @@ impl StaticFace {
             positions: Arc::new(RwLock::new(positions)),
             line_gap,
             content_height: height,
-            ascender: self.ascender(),
+            ascender,
             units: self.units_per_em(),
         })
     }
 
     /// Measure the metrics of a single unicode charactor
-    pub(crate) fn measure_char(&self, c: char) -> Option<CharMetrics> {
+    pub fn measure_char(&self, c: char) -> Option<CharMetrics> {
         self.with_face(|f| {
-            let height = f.height();
+            let (ascender, descender, _) = vertical_metrics(f);
+            let height = ascender - descender;
             let units = f.units_per_em() as f32;
             let glyph_id = f.glyph_index(c)?;
-            let bbox = f.glyph_bounding_box(glyph_id).or_else(|| {
+            let outline_bbox = f.glyph_bounding_box(glyph_id);
+            let bbox = outline_bbox.or_else(|| {
                 Some(Rect {
                     x_min: 0,
                     y_min: 0,
@@ -121,10 +125,33 @@ impl StaticFace {
                     y_max: units as i16,
                 })
             })?;
-            let lsb = f.glyph_hor_side_bearing(glyph_id).unwrap_or(0);
-            // ttf-parser added phantom points support, so the original fix is no
-            // longer needed. Check history for details.
-            let advanced_x = f.glyph_hor_advance(glyph_id)?;
+            // hmtx side bearings only follow variations with an HVAR side bearing
+            // mapping, the varied outline and phantom points always do. Phantom
+            // points only hold the deltas of the origin, at xMin - lsb by default
+            let varied_lsb = outline_bbox.filter(|_| f.is_variable()).map(|bbox| {
+                let default_origin = f
+                    .tables()
+                    .glyf
+                    .and_then(|glyf| glyf.bbox(glyph_id))
+                    .zip(f.tables().hmtx.and_then(|hmtx| hmtx.side_bearing(glyph_id)))
+                    .map_or(0.0, |(bbox, lsb)| (bbox.x_min - lsb) as f32);
+                let shift = f.glyph_phantom_points(glyph_id).map_or(0.0, |p| p.left.x);
+                (bbox.x_min as f32 - default_origin - shift).round() as i16
+            });
+            let lsb = varied_lsb
+                .or_else(|| f.glyph_hor_side_bearing(glyph_id))
+                .unwrap_or(0);
+            // without HVAR, ttf-parser only applies the delta of the right phantom
+            // point, while the advance runs from the left one to it
+            let phantom_advance = f
+                .glyph_phantom_points(glyph_id)
+                .filter(|_| f.is_variable() && f.tables().hvar.is_none())
+                .zip(f.tables().hmtx.and_then(|hmtx| hmtx.advance(glyph_id)))
+                .map(|(p, advance)| (advance as f32 + p.right.x - p.left.x).round() as u16);
+            let advanced_x = match phantom_advance {
+                Some(advance) => advance,
+                None => f.glyph_hor_advance(glyph_id)?,
+            };
             Some(CharMetrics {
                 c,
                 glyph_id,
@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+
+use crate::{normalize_name, Font, FontKey, FontKit};
+
+/// Secondary index of the registry, from every name a query could use to
+/// reach a font (family, full name, PostScript name and variation instance
+/// names) to the registry keys of the fonts carrying it. Lookups return a
+/// superset of the fonts `Filter::Family` accepts, so queries only need to
+/// check those fonts. Names are stored normalized, which covers both strict
+/// and normalized matching
+#[derive(Default)]
+pub(crate) struct NameIndex {
+    names: DashMap<String, HashSet<FontKey>>,
+}
+
+impl NameIndex {
+    fn names_of(font: &Font) -> HashSet<String> {
+        font.variants()
+            .iter()
+            .flat_map(|v| v.lookup_names())
+            .map(|name| normalize_name(&name))
+            .collect()
+    }
+
+    fn insert(&self, key: &FontKey, font: &Font) {
+        for name in Self::names_of(font) {
+            self.names.entry(name).or_default().insert(key.clone());
+        }
+    }
+
+    fn remove(&self, key: &FontKey, font: &Font) {
+        for name in Self::names_of(font) {
+            self.names.remove_if_mut(&name, |_, keys| {
+                keys.remove(key);
+                keys.is_empty()
+            });
+        }
+    }
+
+    /// Registry keys of the fonts that may be named `name`
+    pub(crate) fn get(&self, name: &str) -> Vec<FontKey> {
+        self.names
+            .get(&normalize_name(name))
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// What adding a font does when a font from another file is already
+/// registered under its key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionPolicy {
+    /// Keep the registered font, the new font isn't added
+    Keep,
+    /// Replace the registered font with the new one
+    #[default]
+    Replace,
+    /// Register both fonts. The keys of the new font get the first free
+    /// [`FontKey::handle`] from 2, so that its keys address it while queries
+    /// without a handle keep finding the registered font
+    KeepBoth,
+}
+
+impl FontKit {
+    /// Insert a font into the registry under its first key, keeping the
+    /// secondary indexes in sync, and return the keys of its variants. A font
+    /// already registered under the key is handled with the configured
+    /// [`CollisionPolicy`], none are returned if it is kept instead. The
+    /// family of its keys follows the configured family language
+    pub(crate) fn insert_font(&self, mut font: Font) -> Vec<FontKey> {
+        self.prepare_font(&mut font);
+        self.register_font(font)
+    }
+
+    /// Localize the keys of a font, apply the memory mapping setting, and
+    /// drop the font previously loaded from its file under another key
+    pub(crate) fn prepare_font(&self, font: &mut Font) {
+        let config = self.config.load();
+        if let Some(language) = config.family_language.as_deref() {
+            font.localize_family(language);
+        }
+        font.set_map_file(config.memory_mapping);
+        let key = font.first_key();
+        // reloading a file replaces the font registered from it, whatever
+        // key it got
+        if let Some(path) = font.path() {
+            let reloaded = self.name_index.get(&key.family).into_iter().find(|k| {
+                self.fonts
+                    .get(k)
+                    .is_some_and(|f| k != &key && f.path() == Some(path))
+            });
+            if let Some(reloaded) = reloaded {
+                self.remove_font(&reloaded);
+            }
+        }
+    }
+
+    /// Insert a prepared font under its first key. A collision with the font
+    /// registered under the key is resolved while holding its entry, so that
+    /// concurrent insertions can't both take the key. Other variants sharing
+    /// a key with a variant of a registered font are resolved the same way,
+    /// before the entry is taken
+    pub(crate) fn register_font(&self, mut font: Font) -> Vec<FontKey> {
+        let policy = self.config.load().collision_policy;
+        loop {
+            let key = font.first_key();
+            let keys = font.variants().iter().map(|v| v.key.clone()).collect();
+            let colliding = self.colliding_fonts(&key, &font);
+            if !colliding.is_empty() {
+                match policy {
+                    CollisionPolicy::Keep => {
+                        log::warn!(
+                            "Variants of font {:?} from {:?} are shadowed by fonts {:?}",
+                            key,
+                            font.path(),
+                            colliding
+                        );
+                        return vec![];
+                    }
+                    CollisionPolicy::Replace => {
+                        log::warn!(
+                            "Fonts {:?} are replaced by the font from {:?}, which shares \
+                             variants with them",
+                            colliding,
+                            font.path()
+                        );
+                        for colliding in colliding.iter() {
+                            self.remove_font(colliding);
+                        }
+                    }
+                    CollisionPolicy::KeepBoth => {
+                        let handle = key.handle.map_or(2, |h| h + 1);
+                        log::warn!(
+                            "Variants of font {:?} from {:?} collide with fonts {:?}, \
+                             trying handle {}",
+                            key,
+                            font.path(),
+                            colliding,
+                            handle
+                        );
+                        font.set_handle(handle);
+                        continue;
+                    }
+                }
+            }
+            let mut entry = match self.fonts.entry(key.clone()) {
+                Entry::Vacant(entry) => {
+                    self.index_font(&key, &font);
+                    entry.insert(font);
+                    return keys;
+                }
+                Entry::Occupied(entry) => entry,
+            };
+            let registered = entry.get().path().map(|p| p.to_path_buf());
+            // a font reloaded from its file isn't a collision
+            let reloaded = registered.is_some() && registered.as_deref() == font.path();
+            match policy {
+                _ if reloaded => {}
+                CollisionPolicy::Keep => {
+                    log::warn!(
+                        "Font {:?} from {:?} is shadowed by the font registered from {:?}",
+                        key,
+                        font.path(),
+                        registered
+                    );
+                    return vec![];
+                }
+                CollisionPolicy::Replace => {
+                    log::warn!(
+                        "Font {:?} from {:?} is replaced by the font from {:?}",
+                        key,
+                        registered,
+                        font.path()
+                    );
+                }
+                CollisionPolicy::KeepBoth => {
+                    // release the entry before trying the next handle, which
+                    // may lie in the same shard
+                    drop(entry);
+                    let handle = key.handle.map_or(2, |h| h + 1);
+                    log::warn!(
+                        "Font {:?} from {:?} collides with the font registered from {:?}, \
+                         trying handle {}",
+                        key,
+                        font.path(),
+                        registered,
+                        handle
+                    );
+                    font.set_handle(handle);
+                    continue;
+                }
+            }
+            let old = entry.insert(font);
+            self.unindex_font(&key, &old);
+            self.index_font(&key, entry.get());
+            return keys;
+        }
+    }
+
+    /// Registry keys of the fonts other than the one registered under `key`
+    /// with a variant sharing a key with a variant of `font`. Fonts reloaded
+    /// from the file of `font` don't collide with it
+    fn colliding_fonts(&self, key: &FontKey, font: &Font) -> Vec<FontKey> {
+        let mut colliding = vec![];
+        for variant in font.variants() {
+            for registered in self.named_fonts(&variant.key) {
+                if &registered == key || colliding.contains(&registered) {
+                    continue;
+                }
+                let Some(other) = self.fonts.get(&registered) else {
+                    continue;
+                };
+                let reloaded = other.path().is_some() && other.path() == font.path();
+                if !reloaded && other.variants().iter().any(|v| v.key == variant.key) {
+                    colliding.push(registered);
+                }
+            }
+        }
+        colliding
+    }
+
+    /// Registry keys of the fonts a query of `key` may find: the fonts
+    /// carrying its family among their names and registered with its handle
+    pub(crate) fn named_fonts(&self, key: &FontKey) -> Vec<FontKey> {
+        let mut keys = self.name_index.get(&key.family);
+        keys.retain(|k| k.handle == key.handle);
+        keys
+    }
+
+    pub(crate) fn remove_font(&self, key: &FontKey) -> Option<Font> {
+        let (key, font) = self.fonts.remove(key)?;
+        self.unindex_font(&key, &font);
+        Some(font)
+    }
+
+    fn index_font(&self, key: &FontKey, font: &Font) {
+        self.name_index.insert(key, font);
+        self.coverage_index.insert(key, font);
+    }
+
+    fn unindex_font(&self, key: &FontKey, font: &Font) {
+        self.name_index.remove(key, font);
+        self.coverage_index.remove(key, font);
+    }
+}
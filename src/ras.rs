@@ -44,8 +44,7 @@ impl StaticFace {
             return None;
         }
         self.with_face(|f| {
-            let a = f.ascender();
-            let d = f.descender();
+            let (a, d, _) = font::vertical_metrics(f);
             let units = f.units_per_em() as f32;
             let factor = font_size / units;
             let glyph_id = f.glyph_index(c)?;
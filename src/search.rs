@@ -0,0 +1,377 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "archive")]
+use crate::archive::is_archive_path;
+use crate::index::FileStamp;
+use crate::{Error, FileSource, Font, FontKey, FontKit};
+
+/// Summary of a [`FontKit::search_fonts_from_path`] call
+#[derive(Debug, Default)]
+pub struct SearchReport {
+    /// Font files indexed into the registry
+    pub loaded: Vec<PathBuf>,
+    /// Files ignored because their extension is not a font type
+    pub skipped: Vec<PathBuf>,
+    /// Font files left out because the [`crate::CollisionPolicy`] keeps a
+    /// font registered under the same key
+    pub shadowed: Vec<PathBuf>,
+    /// Files or directories that could not be read or parsed
+    pub failed: Vec<(PathBuf, Error)>,
+    /// Whether the scan was stopped through [`SearchOptions::cancel`] before
+    /// every file was processed
+    pub cancelled: bool,
+}
+
+impl SearchReport {
+    fn merge(&mut self, other: SearchReport) {
+        self.loaded.extend(other.loaded);
+        self.skipped.extend(other.skipped);
+        self.shadowed.extend(other.shadowed);
+        self.failed.extend(other.failed);
+    }
+}
+
+/// Fonts affected by [`FontKit::sync_path`], listed by the keys of their
+/// variants
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FontChanges {
+    pub added: Vec<FontKey>,
+    pub updated: Vec<FontKey>,
+    pub removed: Vec<FontKey>,
+}
+
+impl FontChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Progress of a running scan, passed to [`SearchOptions::progress`]
+#[derive(Debug)]
+pub struct SearchProgress<'a> {
+    /// The file that was just processed
+    pub path: &'a Path,
+    /// Number of font files processed so far, including this one
+    pub done: usize,
+    /// Number of font files found in the search paths
+    pub total: usize,
+}
+
+pub type SearchProgressCallback = Box<dyn Fn(SearchProgress) + Send + Sync>;
+
+/// Options of [`FontKit::search_fonts_from_paths`]
+#[derive(Default)]
+pub struct SearchOptions {
+    /// Number of worker threads reading and parsing fonts, `0` means using
+    /// the available parallelism of the machine
+    pub threads: usize,
+    /// Called from the worker threads after each font file is processed
+    pub progress: Option<SearchProgressCallback>,
+    /// Set this flag to stop scanning. Fonts already parsed stay in the
+    /// registry
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+impl SearchOptions {
+    fn worker_count(&self) -> usize {
+        if self.threads > 0 {
+            return self.threads;
+        }
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .map(|c| c.load(Ordering::SeqCst))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(not(feature = "archive"))]
+fn is_archive_path(_: &Path) -> bool {
+    false
+}
+
+/// Returns whether the path has an extension `search_fonts_from_path` can
+/// load
+pub(crate) fn is_font_path(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_lowercase());
+    matches!(
+        ext.as_deref(),
+        Some("ttf" | "otf" | "ttc" | "otc" | "woff2" | "woff")
+    )
+}
+
+impl FontKit {
+    /// Scan several paths for fonts, reading and parsing files on a pool of
+    /// worker threads. Unlike [`FontKit::search_fonts_from_path`], missing
+    /// search paths are reported in [`SearchReport::failed`]
+    pub fn search_fonts_from_paths<P: AsRef<Path>>(
+        &self,
+        paths: impl IntoIterator<Item = P>,
+        options: &SearchOptions,
+    ) -> SearchReport {
+        let mut report = SearchReport::default();
+        let mut visited = HashSet::new();
+        let mut files = vec![];
+        for path in paths {
+            let path = path.as_ref();
+            match std::fs::metadata(path) {
+                Ok(meta) if meta.is_dir() => {
+                    self.collect_dir(path, &mut visited, &mut files, &mut report)
+                }
+                Ok(_) => self.collect_file(path, &mut files, &mut report),
+                Err(e) => report.failed.push((path.to_path_buf(), e.into())),
+            }
+        }
+        self.load_files(files, options, &mut report);
+        self.check_lru();
+        report
+    }
+
+    /// Bring the registry in sync with a file or directory that changed on
+    /// disk. Files whose size and modification time are the same as when
+    /// they were loaded are left alone. Changed and new files are loaded
+    /// again, replacing the fonts registered from them in place, and fonts
+    /// whose file is gone or no longer loads are removed
+    pub fn sync_path(&self, path: impl AsRef<Path>) -> FontChanges {
+        let path = path.as_ref();
+        let mut report = SearchReport::default();
+        let mut files = vec![];
+        match std::fs::metadata(path) {
+            Ok(meta) if meta.is_dir() => {
+                self.collect_dir(path, &mut HashSet::new(), &mut files, &mut report)
+            }
+            Ok(_) => self.collect_file(path, &mut files, &mut report),
+            Err(_) => {}
+        }
+        let (unchanged, changed): (Vec<_>, Vec<_>) = files.into_iter().partition(|file| {
+            let stamp = std::fs::metadata(file).map(|meta| FileStamp::from_metadata(&meta));
+            matches!((stamp, self.stamps.get(file)), (Ok(a), Some(b)) if a == *b)
+        });
+        let unchanged = unchanged.into_iter().collect::<HashSet<_>>();
+        // fonts of archive members are unchanged if their archive is
+        let is_unchanged = |font: &Font| {
+            font.path()
+                .is_some_and(|p| p.ancestors().any(|a| unchanged.contains(a)))
+        };
+        let mut before = HashSet::new();
+        let mut unchanged_keys = HashSet::new();
+        let mut outdated = vec![];
+        for entry in self.fonts.iter() {
+            let (key, font) = entry.pair();
+            let source = match font.source() {
+                Some(source) if font.path().is_some_and(|p| p.starts_with(path)) => source,
+                _ => continue,
+            };
+            let keys = font.variants().iter().map(|v| v.key.clone());
+            if is_unchanged(font) {
+                unchanged_keys.extend(keys.clone());
+            } else {
+                outdated.push((key.clone(), source.clone()));
+            }
+            before.extend(keys);
+        }
+
+        self.load_files(changed, &SearchOptions::default(), &mut report);
+        // fonts still holding the source they had before weren't loaded again
+        for (key, source) in outdated {
+            let stale = self.fonts.get(&key).is_some_and(|font| {
+                font.source()
+                    .is_some_and(|current| Arc::ptr_eq(current, &source))
+            });
+            if stale {
+                self.remove_font(&key);
+            }
+        }
+        self.stamps
+            .retain(|file, _| !file.starts_with(path) || std::fs::metadata(file).is_ok());
+        self.check_lru();
+
+        let after = self
+            .fonts
+            .iter()
+            .filter(|f| f.path().is_some_and(|p| p.starts_with(path)))
+            .flat_map(|f| {
+                f.variants()
+                    .iter()
+                    .map(|v| v.key.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<HashSet<_>>();
+        let mut changes = FontChanges::default();
+        for key in after.iter() {
+            if !before.contains(key) {
+                changes.added.push(key.clone());
+            } else if !unchanged_keys.contains(key) {
+                changes.updated.push(key.clone());
+            }
+        }
+        changes.removed = before.difference(&after).cloned().collect();
+        changes
+    }
+
+    /// Walk a directory recursively. Directories are canonicalized before
+    /// entering so that symlink cycles are only visited once
+    pub(crate) fn collect_dir(
+        &self,
+        path: &Path,
+        visited: &mut HashSet<PathBuf>,
+        files: &mut Vec<PathBuf>,
+        report: &mut SearchReport,
+    ) {
+        let real_path = match std::fs::canonicalize(path) {
+            Ok(p) => p,
+            Err(e) => {
+                report.failed.push((path.to_path_buf(), e.into()));
+                return;
+            }
+        };
+        if !visited.insert(real_path) {
+            return;
+        }
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                report.failed.push((path.to_path_buf(), e.into()));
+                return;
+            }
+        };
+        let mut entries = entries
+            .filter_map(|entry| match entry {
+                Ok(entry) => Some(entry.path()),
+                Err(e) => {
+                    report.failed.push((path.to_path_buf(), e.into()));
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        // keep the scanning order stable across platforms
+        entries.sort();
+        for entry in entries {
+            // `metadata` follows symlinks, broken links are reported as failures
+            match std::fs::metadata(&entry) {
+                Ok(meta) if meta.is_dir() => self.collect_dir(&entry, visited, files, report),
+                Ok(_) => self.collect_file(&entry, files, report),
+                Err(e) => report.failed.push((entry, e.into())),
+            }
+        }
+    }
+
+    pub(crate) fn collect_file(
+        &self,
+        path: &Path,
+        files: &mut Vec<PathBuf>,
+        report: &mut SearchReport,
+    ) {
+        if is_font_path(path) || is_archive_path(path) {
+            files.push(path.to_path_buf());
+        } else {
+            report.skipped.push(path.to_path_buf());
+        }
+    }
+
+    /// Load font files into the registry. Workers pull the next file from a
+    /// shared cursor, so at most `options.threads` files are in memory at a
+    /// time
+    pub(crate) fn load_files(
+        &self,
+        files: Vec<PathBuf>,
+        options: &SearchOptions,
+        report: &mut SearchReport,
+    ) {
+        let total = files.len();
+        let threads = options.worker_count().min(total).max(1);
+        let cursor = AtomicUsize::new(0);
+        let done = AtomicUsize::new(0);
+        let results = Mutex::new(SearchReport::default());
+        let work = || loop {
+            if options.is_cancelled() {
+                break;
+            }
+            let path = match files.get(cursor.fetch_add(1, Ordering::SeqCst)) {
+                Some(path) => path,
+                None => break,
+            };
+            self.load_path(path, &results);
+            let done = done.fetch_add(1, Ordering::SeqCst) + 1;
+            if let Some(progress) = options.progress.as_ref() {
+                progress(SearchProgress { path, done, total });
+            }
+        };
+        if threads == 1 {
+            work();
+        } else {
+            std::thread::scope(|s| {
+                for _ in 0..threads {
+                    s.spawn(work);
+                }
+            });
+        }
+        let mut results = results.into_inner().unwrap();
+        // workers finish in arbitrary order
+        results.loaded.sort();
+        results.skipped.sort();
+        results.shadowed.sort();
+        results.failed.sort_by(|a, b| a.0.cmp(&b.0));
+        report.merge(results);
+        report.cancelled = done.load(Ordering::SeqCst) < total;
+    }
+
+    /// Load a font file, or every font in an archive, into the registry
+    fn load_path(&self, path: &Path, results: &Mutex<SearchReport>) {
+        // stamped before reading, so that a write racing with the load is
+        // picked up by the next sync
+        if let Ok(meta) = std::fs::metadata(path) {
+            self.stamps
+                .insert(path.to_path_buf(), FileStamp::from_metadata(&meta));
+        }
+        #[cfg(feature = "archive")]
+        if is_archive_path(path) {
+            let mut report = SearchReport::default();
+            if let Err(e) = self.load_archive(path, &mut report) {
+                log::warn!("Failed loading archive {:?}: {:?}", path, e);
+                report.failed.push((path.to_path_buf(), e));
+            }
+            results.lock().unwrap().merge(report);
+            return;
+        }
+        match self.load_font_file(path) {
+            Ok(font) => {
+                let registered = !self.insert_font(font).is_empty();
+                let mut results = results.lock().unwrap();
+                if registered {
+                    results.loaded.push(path.to_path_buf());
+                } else {
+                    results.shadowed.push(path.to_path_buf());
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed loading font {:?}: {:?}", path, e);
+                results.lock().unwrap().failed.push((path.to_path_buf(), e));
+            }
+        }
+    }
+
+    fn load_font_file(&self, path: &Path) -> Result<Font, Error> {
+        let source = Arc::new(FileSource::new(path));
+        let config = self.config.load();
+        let font = Font::from_source(
+            source,
+            self.hit_counter.clone(),
+            config.strict_validation,
+            config.memory_mapping,
+        )?;
+        font.unload();
+        Ok(font)
+    }
+}
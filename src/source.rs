@@ -0,0 +1,103 @@
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::Error;
+
+/// Where the data of a font comes from. A font with a source is unloaded
+/// after indexing and when evicted by the LRU, and fetched from the source
+/// again the next time it is queried
+pub trait FontSource: Send + Sync {
+    /// Fetch the raw font data. WOFF and WOFF2 data is decompressed by the
+    /// caller
+    fn read(&self) -> Result<Vec<u8>, Error>;
+
+    /// The path reported for fonts loaded from this source, if any
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// A local file holding exactly the data `read` returns. Such sources are
+    /// mapped instead of read once `FontKit::set_memory_mapping` is enabled
+    fn file(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Font data stored in a local file
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    path: PathBuf,
+}
+
+impl FileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileSource { path: path.into() }
+    }
+}
+
+impl FontSource for FileSource {
+    fn read(&self) -> Result<Vec<u8>, Error> {
+        let mut buffer = Vec::new();
+        std::fs::File::open(&self.path)
+            .and_then(|mut file| file.read_to_end(&mut buffer))
+            .map_err(Error::file_access(&self.path))?;
+        Ok(buffer)
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+
+    fn file(&self) -> Option<&Path> {
+        Some(&self.path)
+    }
+}
+
+/// Font data kept in memory, e.g. a compressed WOFF2 buffer whose decoded
+/// data could be unloaded
+#[derive(Debug, Clone)]
+pub struct MemorySource {
+    buffer: Arc<Vec<u8>>,
+}
+
+impl MemorySource {
+    pub fn new(buffer: impl Into<Arc<Vec<u8>>>) -> Self {
+        MemorySource {
+            buffer: buffer.into(),
+        }
+    }
+}
+
+impl FontSource for MemorySource {
+    fn read(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.buffer.to_vec())
+    }
+}
+
+/// Font data fetched by a user-supplied callback, e.g. from a remote blob
+/// store
+pub struct LoaderSource {
+    loader: Box<dyn Fn() -> Result<Vec<u8>, Error> + Send + Sync>,
+}
+
+impl LoaderSource {
+    pub fn new(loader: impl Fn() -> Result<Vec<u8>, Error> + Send + Sync + 'static) -> Self {
+        LoaderSource {
+            loader: Box::new(loader),
+        }
+    }
+}
+
+impl FontSource for LoaderSource {
+    fn read(&self) -> Result<Vec<u8>, Error> {
+        (self.loader)()
+    }
+}
+
+impl fmt::Debug for LoaderSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoaderSource").finish_non_exhaustive()
+    }
+}
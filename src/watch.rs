@@ -0,0 +1,60 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{Error, FontChanges, FontKit, SearchReport};
+
+/// Keeps a shared [`FontKit`] in sync with watched paths. Every change on disk
+/// is applied through [`FontKit::sync_path`], and the callback receives the
+/// keys of the fonts that were added, updated or removed. Fonts edited in
+/// place must not be mapped, see `FontKit::set_memory_mapping`
+pub struct FontWatcher {
+    fontkit: Arc<FontKit>,
+    watcher: RecommendedWatcher,
+}
+
+impl FontWatcher {
+    pub fn new(
+        fontkit: Arc<FontKit>,
+        callback: impl Fn(FontChanges) + Send + 'static,
+    ) -> Result<Self, Error> {
+        let registry = fontkit.clone();
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    log::warn!("Font watcher error: {:?}", e);
+                    return;
+                }
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            for path in event.paths {
+                let changes = registry.sync_path(&path);
+                if !changes.is_empty() {
+                    callback(changes);
+                }
+            }
+        })?;
+        Ok(FontWatcher { fontkit, watcher })
+    }
+
+    /// Start watching a path recursively, and scan the fonts already in it
+    pub fn watch(&mut self, path: impl AsRef<Path>) -> Result<SearchReport, Error> {
+        // events are reported with canonical paths on some platforms, keep the
+        // registry paths comparable with them
+        let path = std::fs::canonicalize(path)?;
+        self.watcher.watch(&path, RecursiveMode::Recursive)?;
+        self.fontkit.search_fonts_from_path(&path)
+    }
+
+    /// Stop watching a path. Fonts loaded from it stay in the registry
+    pub fn unwatch(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.watcher.unwatch(&path)?;
+        Ok(())
+    }
+}
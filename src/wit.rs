@@ -1,10 +1,16 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use serde::Deserialize;
+
 use crate::bindings::exports::alibaba::fontkit::fontkit_interface as fi;
+use crate::coverage::Coverage;
 use crate::font::FontKey;
 use crate::metrics::TextMetrics;
-use crate::{Config, Font, FontKit, GlyphBitmap, StaticFace, VariationData};
+use crate::{
+    CollisionPolicy, Config, Error, FamilyMatch, Font, FontKit, GlyphBitmap, QueryCandidate,
+    StaticFace, VariationData,
+};
 
 use crate::bindings::exports::alibaba::fontkit::fontkit_interface::GuestTextMetrics;
 
@@ -17,6 +23,13 @@ impl fi::GuestFont for StaticFace {
         self.borrow_buffer().to_vec()
     }
 
+    fn instance_buffer(&self) -> Result<Vec<u8>, fi::Error> {
+        #[cfg(feature = "parse")]
+        return Ok(self.instance_buffer()?);
+        #[cfg(not(feature = "parse"))]
+        Err(unsupported("Instancing requires the parse feature"))
+    }
+
     fn path(&self) -> String {
         String::new()
     }
@@ -25,10 +38,8 @@ impl fi::GuestFont for StaticFace {
         self.key().into()
     }
 
-    fn measure(&self, text: String) -> Result<fi::TextMetrics, String> {
-        Ok(fi::TextMetrics::new(
-            self.measure(&text).map_err(|e| e.to_string())?,
-        ))
+    fn measure(&self, text: String) -> Result<fi::TextMetrics, fi::Error> {
+        Ok(fi::TextMetrics::new(self.measure(&text)?))
     }
 
     fn ascender(&self) -> i16 {
@@ -123,15 +134,41 @@ impl fi::GuestFontKit for FontKit {
         FontKit::new()
     }
 
-    fn add_font_from_buffer(&self, buffer: Vec<u8>) {
+    fn set_collision_policy(&self, policy: String) -> Result<(), fi::Error> {
+        let policy = match policy.as_str() {
+            "keep" => CollisionPolicy::Keep,
+            "replace" => CollisionPolicy::Replace,
+            "keep-both" => CollisionPolicy::KeepBoth,
+            _ => {
+                return Err(fi::Error {
+                    message: format!("Unknown collision policy {:?}", policy),
+                    path: None,
+                    face_index: None,
+                })
+            }
+        };
+        self.set_collision_policy(policy);
+        Ok(())
+    }
+
+    fn add_font_from_buffer(&self, buffer: Vec<u8>) -> Result<Vec<fi::FontKey>, fi::Error> {
         #[cfg(feature = "parse")]
-        let _ = self.add_font_from_buffer(buffer);
+        return Ok(self.add_font_from_buffer(buffer)?);
+        #[cfg(not(feature = "parse"))]
+        Err(unsupported("Loading fonts requires the parse feature"))
     }
 
     fn query(&self, key: fi::FontKey) -> Option<fi::Font> {
         self.query(&FontKey::from(key)).map(fi::Font::new)
     }
 
+    fn query_candidates(&self, key: fi::FontKey) -> Vec<fi::QueryCandidate> {
+        self.query_candidates(&FontKey::from(key))
+            .into_iter()
+            .map(fi::QueryCandidate::from)
+            .collect()
+    }
+
     fn exact_match(&self, key: fi::FontKey) -> Option<fi::Font> {
         self.exact_match(&FontKey::from(key)).map(fi::Font::new)
     }
@@ -144,9 +181,14 @@ impl fi::GuestFontKit for FontKit {
         self.remove(FontKey::from(key))
     }
 
-    fn add_search_path(&self, path: String) {
+    fn add_search_path(&self, path: String) -> Result<(), fi::Error> {
         #[cfg(feature = "parse")]
-        self.search_fonts_from_path(path).unwrap()
+        return self
+            .search_fonts_from_path(path)
+            .map(|_| ())
+            .map_err(Into::into);
+        #[cfg(not(feature = "parse"))]
+        Err(unsupported("Loading fonts requires the parse feature"))
     }
 
     fn fonts_info(&self) -> Vec<fi::FontInfo> {
@@ -157,57 +199,68 @@ impl fi::GuestFontKit for FontKit {
         Some(fi::TextMetrics::new(self.measure(&key.into(), &text)?))
     }
 
-    fn read_data(&self, data: String) {
-        let result: Result<Vec<serde_json::Map<String, serde_json::Value>>, _> =
-            serde_json::from_str(&data);
-        if let Ok(data) = result {
-            for item in data {
-                let path = item
-                    .get("path")
-                    .and_then(|v| serde_json::from_value::<Option<PathBuf>>(v.clone()).ok());
-                let variants = item
-                    .get("variants")
-                    .and_then(|v| serde_json::from_value::<Vec<VariationData>>(v.clone()).ok());
-                if let (Some(path), Some(variants)) = (path, variants) {
-                    let font = Font::new(path, variants, self.hit_counter.clone());
-                    let key = font.first_key();
-                    self.fonts.insert(key, font);
-                }
+    fn read_data(&self, data: String) -> Result<(), fi::Error> {
+        let data: Vec<serde_json::Value> = serde_json::from_str(&data).map_err(Error::from)?;
+        // check every entry before registering any, so that invalid data
+        // leaves the registry untouched
+        let mut fonts = Vec::with_capacity(data.len());
+        for (entry, item) in data.into_iter().enumerate() {
+            let invalid = |reason: String| Error::InvalidFontData { entry, reason };
+            let item: FontData =
+                serde_json::from_value(item).map_err(|e| invalid(e.to_string()))?;
+            if item.variants.is_empty() {
+                return Err(invalid("no variants".to_string()).into());
             }
+            fonts.push(Font::new(
+                item.path,
+                item.variants,
+                item.coverage,
+                self.hit_counter.clone(),
+            ));
         }
+        for font in fonts {
+            self.insert_font(font);
+        }
+        Ok(())
     }
 
-    fn write_data(&self) -> String {
+    fn write_data(&self) -> Result<String, fi::Error> {
         let mut result = vec![];
         for value in self.fonts.iter() {
             let font = value.value();
             let mut value = serde_json::Map::new();
             value.insert(
                 "path".to_string(),
-                serde_json::to_value(font.path()).unwrap(),
+                serde_json::to_value(font.path()).map_err(Error::from)?,
             );
             value.insert(
                 "variants".to_string(),
-                serde_json::to_value(font.variants()).unwrap(),
+                serde_json::to_value(font.variants()).map_err(Error::from)?,
+            );
+            value.insert(
+                "coverage".to_string(),
+                serde_json::to_value(font.coverage()).map_err(Error::from)?,
             );
             result.push(value);
         }
-        serde_json::to_string(&result).unwrap()
+        Ok(serde_json::to_string(&result).map_err(Error::from)?)
     }
 
     fn query_font_info(&self, key: fi::FontKey) -> Option<Vec<fi::FontInfo>> {
-        let font = self.fonts.get(&self.query_font(&key)?)?;
+        let font = self.fonts.get(&self.query_font(&key)?.0)?;
         Some(font_info(&*font))
     }
 
-    fn set_config(&self, limit: u32, cache_path: Option<String>) {
+    fn set_config(&self, limit: u32, cache_path: Option<String>) -> Result<(), fi::Error> {
         if let Some(p) = cache_path.as_ref() {
-            std::fs::create_dir_all(p).unwrap();
+            std::fs::create_dir_all(p).map_err(Error::file_access(p))?;
         }
-        self.config.store(Arc::new(Config {
+        self.config.rcu(|config| Config {
             lru_limit: limit,
-            cache_path,
-        }));
+            cache_path: cache_path.clone(),
+            ..Config::clone(config)
+        });
+        Ok(())
     }
 
     fn buffer_size(&self) -> u32 {
@@ -301,6 +354,56 @@ impl fi::Guest for Component {
 
 crate::bindings::export!(Component with_types_in crate::bindings);
 
+impl From<QueryCandidate> for fi::QueryCandidate {
+    fn from(c: QueryCandidate) -> Self {
+        let family_match = match c.family_match {
+            FamilyMatch::Exact => fi::FamilyMatch::Exact,
+            FamilyMatch::Name => fi::FamilyMatch::Name,
+            FamilyMatch::InstanceName => fi::FamilyMatch::InstanceName,
+            FamilyMatch::Similar { edits } => fi::FamilyMatch::Similar(edits),
+        };
+        fi::QueryCandidate {
+            key: c.key,
+            family_match,
+            stretch_distance: c.stretch_distance,
+            italic_matches: c.italic_matches,
+            weight_distance: c.weight_distance,
+            variations_match: c.variations_match,
+        }
+    }
+}
+
+/// An entry of the data written by `write-data`
+#[derive(Deserialize)]
+struct FontData {
+    path: Option<PathBuf>,
+    variants: Vec<VariationData>,
+    /// Missing from data written before coverage was saved, which leaves
+    /// fallback out
+    #[serde(default)]
+    coverage: Vec<Coverage>,
+}
+
+impl From<Error> for fi::Error {
+    fn from(e: Error) -> Self {
+        fi::Error {
+            message: e.to_string(),
+            path: e.path().and_then(|p| Some(p.to_str()?.to_string())),
+            face_index: e.face_index(),
+        }
+    }
+}
+
+/// An error of a call that the enabled features don't support
+#[cfg(not(feature = "parse"))]
+fn unsupported(message: &str) -> fi::Error {
+    fi::Error {
+        message: message.to_string(),
+        path: None,
+        face_index: None,
+    }
+}
+
 fn font_info(font: &Font) -> Vec<fi::FontInfo> {
     font.variants()
         .iter()
@@ -1,6 +1,11 @@
-use fontkit::{Area, Error, FontKey, FontKit, Line, Span, TextMetrics};
+use fontkit::{
+    AliasTable, Area, CollisionPolicy, Error, FamilyMatch, FontFormat, FontKey, FontKit, Line,
+    LoaderSource, MatchMode, NameMatching, SearchOptions, Span, TextMetrics, WoffBlocks,
+};
 use std::fs;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[test]
 pub fn test_font_loading() -> Result<(), Error> {
@@ -50,6 +55,1945 @@ pub fn test_search_font() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+pub fn test_search_directory() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let nested = dir.path().join("nested/deeper");
+    fs::create_dir_all(&nested)?;
+    fs::copy(
+        "examples/OpenSans-Italic.ttf",
+        nested.join("OpenSans-Italic.TTF"),
+    )?;
+    fs::write(dir.path().join("readme.txt"), "not a font")?;
+    fs::write(dir.path().join("broken.ttf"), [0_u8; 16])?;
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir.path(), nested.join("loop"))?;
+
+    let fontkit = FontKit::new();
+    let report = fontkit.search_fonts_from_path(dir.path())?;
+    assert_eq!(report.loaded, vec![nested.join("OpenSans-Italic.TTF")]);
+    assert_eq!(report.skipped, vec![dir.path().join("readme.txt")]);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, dir.path().join("broken.ttf"));
+    assert_eq!(fontkit.len(), 1);
+    Ok(())
+}
+
+#[test]
+pub fn test_parallel_search() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let original = fs::read("examples/OpenSans-Italic.ttf")?;
+    for i in 0..8 {
+        fs::write(
+            dir.path().join(format!("OpenSans-{}.ttf", i)),
+            open_sans_renamed(&original, i),
+        )?;
+    }
+
+    let fontkit = FontKit::new();
+    let progress = Arc::new(AtomicUsize::new(0));
+    let counter = progress.clone();
+    let options = SearchOptions {
+        threads: 4,
+        progress: Some(Box::new(move |p| {
+            assert_eq!(p.total, 8);
+            counter.fetch_add(1, Ordering::SeqCst);
+        })),
+        cancel: None,
+    };
+    let report = fontkit.search_fonts_from_paths([dir.path()], &options);
+    assert_eq!(report.loaded.len(), 8);
+    assert!(!report.cancelled);
+    assert_eq!(progress.load(Ordering::SeqCst), 8);
+    assert_eq!(fontkit.len(), 8);
+    for i in 0..8 {
+        let key = FontKey::new_with_family(format!("Fnt {:05}", i));
+        assert!(fontkit.query(&key).is_some());
+    }
+
+    let fontkit = FontKit::new();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = cancel.clone();
+    let options = SearchOptions {
+        threads: 2,
+        progress: Some(Box::new(move |_| flag.store(true, Ordering::SeqCst))),
+        cancel: Some(cancel),
+    };
+    let report = fontkit.search_fonts_from_paths([dir.path()], &options);
+    assert!(report.cancelled);
+    assert!(report.loaded.len() < 8);
+    Ok(())
+}
+
+#[test]
+pub fn test_index_persistence() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let font_path = dir.path().join("OpenSans-Italic.ttf");
+    let index = dir.path().join("index.json");
+    fs::copy("examples/OpenSans-Italic.ttf", &font_path)?;
+    let fontkit = FontKit::new();
+    fontkit.search_fonts_from_path(&font_path)?;
+    fontkit.save_index(&index)?;
+
+    let key = FontKey::new_with_family("Open Sans".to_string());
+    let fontkit = FontKit::new();
+    let report = fontkit.load_index(&index)?;
+    assert_eq!(report.restored, vec![font_path.clone()]);
+    assert!(fontkit.query(&key).is_some());
+
+    let mut buf = fs::read(&font_path)?;
+    buf.extend_from_slice(&[0; 4]);
+    fs::write(&font_path, buf)?;
+    let fontkit = FontKit::new();
+    let report = fontkit.load_index(&index)?;
+    assert!(report.restored.is_empty());
+    assert_eq!(report.rescanned.loaded, vec![font_path.clone()]);
+    assert!(fontkit.query(&key).is_some());
+
+    // a rewrite keeping the size and modification time is caught by the hash
+    // of files modified around the save
+    fontkit.save_index(&index)?;
+    let modified = fs::metadata(&font_path)?.modified()?;
+    let original = fs::read("examples/OpenSans-Italic.ttf")?;
+    let mut renamed = open_sans_renamed(&original, 0);
+    renamed.extend_from_slice(&[0; 4]);
+    fs::write(&font_path, renamed)?;
+    fs::File::options()
+        .write(true)
+        .open(&font_path)?
+        .set_modified(modified)?;
+    let fontkit = FontKit::new();
+    let report = fontkit.load_index(&index)?;
+    assert_eq!(report.rescanned.loaded, vec![font_path.clone()]);
+    assert!(fontkit.query(&key).is_none());
+
+    fs::remove_file(&font_path)?;
+    let report = FontKit::new().load_index(&index)?;
+    assert_eq!(report.removed, vec![font_path.clone()]);
+    // fonts whose file is gone are left out of the index
+    fontkit.save_index(&index)?;
+    let fontkit = FontKit::new();
+    let report = fontkit.load_index(&index)?;
+    assert!(report.removed.is_empty() && report.restored.is_empty());
+    assert_eq!(fontkit.len(), 0);
+
+    fs::write(&index, r#"{"version":0,"fonts":[]}"#)?;
+    assert!(matches!(
+        fontkit.load_index(&index),
+        Err(Error::IndexVersion { found: 0, .. })
+    ));
+    Ok(())
+}
+
+#[test]
+pub fn test_sync_path() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let font_path = dir.path().join("OpenSans-Italic.ttf");
+    let fontkit = FontKit::new();
+    assert!(fontkit.sync_path(&font_path).is_empty());
+
+    fs::copy("examples/OpenSans-Italic.ttf", &font_path)?;
+    let changes = fontkit.sync_path(dir.path());
+    assert_eq!(changes.added.len(), 1);
+    assert_eq!(changes.added[0].family, "Open Sans");
+
+    // unchanged files are left out
+    assert!(fontkit.sync_path(dir.path()).is_empty());
+    assert!(fontkit.sync_path(&font_path).is_empty());
+
+    // trailing data changes the size of the file, not its fonts
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&font_path)?
+        .write_all(&[0; 4])?;
+    let changes = fontkit.sync_path(dir.path());
+    assert_eq!(changes.updated.len(), 1);
+    assert!(changes.added.is_empty() && changes.removed.is_empty());
+    assert_eq!(fontkit.len(), 1);
+
+    // a file that no longer loads drops its fonts
+    fs::write(&font_path, b"not a font")?;
+    let changes = fontkit.sync_path(&font_path);
+    assert_eq!(changes.removed.len(), 1);
+    assert_eq!(fontkit.len(), 0);
+
+    fs::copy("examples/OpenSans-Italic.ttf", &font_path)?;
+    assert_eq!(fontkit.sync_path(&font_path).added.len(), 1);
+    fs::remove_file(&font_path)?;
+    let changes = fontkit.sync_path(&font_path);
+    assert_eq!(changes.removed.len(), 1);
+    assert_eq!(fontkit.len(), 0);
+    Ok(())
+}
+
+#[cfg(feature = "watch")]
+#[test]
+pub fn test_font_watcher() -> Result<(), Error> {
+    use fontkit::{FontChanges, FontWatcher};
+    use std::sync::mpsc;
+
+    let dir = tempfile::tempdir()?;
+    let watched = dir.path().join("watched");
+    fs::create_dir(&watched)?;
+    let fontkit = Arc::new(FontKit::new());
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = FontWatcher::new(fontkit.clone(), move |changes| {
+        let _ = tx.send(changes);
+    })?;
+    watcher.watch(&watched)?;
+    // a single file operation may be reported as several events
+    let wait = |found: &dyn Fn(&FontChanges) -> bool| loop {
+        let changes = rx.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+        if found(&changes) {
+            break changes;
+        }
+    };
+
+    // move the file in at once, so the watcher never sees a partial font
+    let staged = dir.path().join("OpenSans-Italic.ttf");
+    fs::copy("examples/OpenSans-Italic.ttf", &staged)?;
+    fs::rename(&staged, watched.join("OpenSans-Italic.ttf"))?;
+    let changes = wait(&|c| !c.added.is_empty());
+    assert_eq!(changes.added.len(), 1);
+    assert_eq!(fontkit.len(), 1);
+
+    fs::remove_file(watched.join("OpenSans-Italic.ttf"))?;
+    let changes = wait(&|c| !c.removed.is_empty());
+    assert_eq!(changes.removed.len(), 1);
+    assert_eq!(fontkit.len(), 0);
+    Ok(())
+}
+
+#[test]
+pub fn test_buffer_accounting() -> Result<(), Error> {
+    let fontkit = FontKit::new();
+    // Safety: the example fonts aren't modified while mapped
+    #[cfg(feature = "mmap")]
+    unsafe {
+        fontkit.set_memory_mapping(true);
+    }
+    fontkit.search_fonts_from_path("examples/OpenSans-Italic.ttf")?;
+    assert_eq!(fontkit.buffer_size(), 0);
+    let key = FontKey::new_with_family("Open Sans".to_string());
+    assert!(fontkit.query(&key).is_some());
+    let size = fs::metadata("examples/OpenSans-Italic.ttf")?.len() as usize;
+    assert_eq!(fontkit.buffer_size(), size);
+    if cfg!(feature = "mmap") {
+        assert_eq!(fontkit.mapped_buffer_size(), size);
+        assert_eq!(fontkit.heap_buffer_size(), 0);
+    } else {
+        assert_eq!(fontkit.mapped_buffer_size(), 0);
+        assert_eq!(fontkit.heap_buffer_size(), size);
+    }
+    Ok(())
+}
+
+#[test]
+pub fn test_loader_source() -> Result<(), Error> {
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let counter = fetches.clone();
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_source(LoaderSource::new(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Ok(fs::read("examples/OpenSans-Italic.ttf")?)
+    }))?;
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    assert_eq!(fontkit.buffer_size(), 0);
+
+    let key = FontKey::new_with_family("Open Sans".to_string());
+    assert!(fontkit.query(&key).is_some());
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    assert!(fontkit.buffer_size() > 0);
+    Ok(())
+}
+
+#[cfg(feature = "archive")]
+#[test]
+pub fn test_archive_fonts() -> Result<(), Error> {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir()?;
+    let font = fs::read("examples/OpenSans-Italic.ttf")?;
+
+    let zip_path = dir.path().join("bundle.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path)?);
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("fonts/OpenSans-Italic.ttf", options)
+        .unwrap();
+    zip.write_all(&font)?;
+    zip.start_file("readme.txt", options).unwrap();
+    zip.write_all(b"not a font")?;
+    zip.finish().unwrap();
+
+    let tar_path = dir.path().join("bundle.tar.gz");
+    let encoder =
+        flate2::write::GzEncoder::new(fs::File::create(&tar_path)?, flate2::Compression::default());
+    let mut tar = tar::Builder::new(encoder);
+    let mut header = tar::Header::new_gnu();
+    header.set_size(font.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tar.append_data(&mut header, "OpenSans-Italic.ttf", font.as_slice())?;
+    tar.into_inner()?.finish()?;
+
+    let fontkit = FontKit::new();
+    let report = fontkit.search_fonts_from_path(dir.path())?;
+    assert_eq!(
+        report.loaded,
+        vec![
+            tar_path.join("OpenSans-Italic.ttf"),
+            zip_path.join("fonts/OpenSans-Italic.ttf"),
+        ]
+    );
+    assert_eq!(report.skipped, vec![zip_path.join("readme.txt")]);
+    assert_eq!(fontkit.buffer_size(), 0);
+
+    let fontkit = FontKit::new();
+    fontkit.search_fonts_from_archive(&zip_path)?;
+    let key = FontKey::new_with_family("Open Sans".to_string());
+    assert!(fontkit.query(&key).is_some());
+    assert_eq!(fontkit.buffer_size(), font.len());
+
+    // member paths stay under the archive path
+    let unsafe_path = dir.path().join("unsafe.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&unsafe_path)?);
+    for name in [
+        "../OpenSans-Italic.ttf",
+        "/OpenSans-Italic.ttf",
+        "./fonts/./OpenSans-Italic.ttf",
+    ] {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(&font)?;
+    }
+    zip.finish().unwrap();
+    let report = FontKit::new().search_fonts_from_archive(&unsafe_path)?;
+    assert_eq!(
+        report.loaded,
+        vec![unsafe_path.join("fonts/OpenSans-Italic.ttf")]
+    );
+    assert_eq!(report.failed.len(), 2);
+    assert!(report
+        .failed
+        .iter()
+        .all(|(path, e)| path == &unsafe_path && matches!(e, Error::UnsafeArchiveMember { .. })));
+    assert!(fontkit::ArchiveSource::new(&unsafe_path, "../OpenSans-Italic.ttf").is_err());
+    Ok(())
+}
+
+#[cfg(feature = "fontconfig")]
+#[test]
+pub fn test_fontconfig_discovery() -> Result<(), Error> {
+    use fontkit::Fontconfig;
+
+    let config = Fontconfig::from_file("tests/fixtures/fontconfig/fonts.conf")?;
+    assert_eq!(config.dirs.len(), 2);
+    assert_eq!(
+        config.fallbacks("helvetica"),
+        vec!["Open Sans".to_string(), "sans-serif".to_string()]
+    );
+    assert_eq!(
+        config.generic_families()["sans-serif"],
+        vec![
+            "Open Sans".to_string(),
+            "Noto Sans".to_string(),
+            "DejaVu Sans".to_string()
+        ]
+    );
+
+    let mut fontkit = FontKit::new();
+    let report = fontkit.search_fonts_from_fontconfig(&config, &SearchOptions::default());
+    assert_eq!(report.loaded.len(), 1);
+    assert!(report.failed.is_empty());
+
+    fontkit.set_fallback_from_fontconfig(&config);
+    let key = FontKey::new_with_family("Arial".to_string());
+    assert!(fontkit.query(&key).is_none());
+    assert!(fontkit.measure(&key, "Hello").is_some());
+
+    fontkit.set_aliases(config.alias_table());
+    assert_eq!(fontkit.query(&key).unwrap().key().family, "Open Sans");
+    Ok(())
+}
+
+#[cfg(feature = "fontconfig")]
+#[test]
+pub fn test_fontconfig_dirs() -> Result<(), Error> {
+    use fontkit::Fontconfig;
+
+    let config = Fontconfig::from_file("tests/fixtures/fontconfig/dirs.conf")?;
+    let cwd = std::env::current_dir()?;
+    let config_dir = fs::canonicalize("tests/fixtures/fontconfig")?;
+    let examples = fs::canonicalize("examples")?;
+    // relative dirs without a prefix are resolved against the current
+    // directory, only `prefix="relative"` against the configuration file
+    let dirs = config.dirs.iter().map(fs::canonicalize).collect::<Vec<_>>();
+    assert!(dirs[..3].iter().all(|d| d.as_ref().ok() == Some(&examples)));
+    assert_eq!(config.dirs[3], cwd.join("conf.d"));
+    assert_eq!(config.dirs[4], config_dir.join("conf.d"));
+    Ok(())
+}
+
+/// Patch the weight and style of Open Sans Italic, to build a family with
+/// several faces
+fn open_sans_with_style(weight: u16, italic: bool) -> Result<Vec<u8>, Error> {
+    let mut buf = fs::read("examples/OpenSans-Italic.ttf")?;
+    let num_tables = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+    for i in 0..num_tables {
+        let record = 12 + i * 16;
+        let offset = u32::from_be_bytes(buf[record + 8..record + 12].try_into().unwrap()) as usize;
+        match &buf[record..record + 4] {
+            b"OS/2" => {
+                buf[offset + 4..offset + 6].copy_from_slice(&weight.to_be_bytes());
+                let fs_selection: u16 = if italic { 0x01 } else { 0x40 };
+                buf[offset + 62..offset + 64].copy_from_slice(&fs_selection.to_be_bytes());
+            }
+            b"post" if !italic => buf[offset + 4..offset + 8].fill(0),
+            _ => {}
+        }
+    }
+    Ok(buf)
+}
+
+#[test]
+pub fn test_css_matching() -> Result<(), Error> {
+    let fontkit = FontKit::new();
+    for (weight, italic) in [(300, false), (400, true), (600, false), (700, false)] {
+        fontkit.add_font_from_buffer(open_sans_with_style(weight, italic)?)?;
+    }
+    let query = |weight: u16, italic: bool| {
+        let mut key = FontKey::new_with_family("Open Sans".to_string());
+        key.weight = Some(weight);
+        key.italic = Some(italic);
+        fontkit
+            .query(&key)
+            .map(|face| (face.key().weight.unwrap(), face.key().italic.unwrap()))
+    };
+    assert_eq!(query(500, false), None);
+
+    fontkit.set_match_mode(MatchMode::Css);
+    assert_eq!(query(500, false), Some((300, false)));
+    assert_eq!(query(450, true), Some((400, true)));
+    assert_eq!(query(650, false), Some((700, false)));
+    assert_eq!(query(350, false), Some((300, false)));
+    assert_eq!(query(800, false), Some((700, false)));
+    assert_eq!(query(700, true), Some((400, true)));
+    Ok(())
+}
+
+#[test]
+pub fn test_query_candidates() -> Result<(), Error> {
+    let fontkit = FontKit::new();
+    for (weight, italic) in [(300, false), (400, true), (600, false), (700, false)] {
+        fontkit.add_font_from_buffer(open_sans_with_style(weight, italic)?)?;
+    }
+    let mut key = FontKey::new_with_family("Open Sans".to_string());
+    key.weight = Some(500);
+    assert!(fontkit.query(&key).is_none());
+    let candidates = fontkit.query_candidates(&key);
+    let ranked = candidates
+        .iter()
+        .map(|c| (c.key.weight.unwrap(), c.key.italic.unwrap()))
+        .collect::<Vec<_>>();
+    // ranked as CSS matching prefers them, the first is what it picks
+    assert_eq!(
+        ranked,
+        vec![(300, false), (600, false), (700, false), (400, true)]
+    );
+    fontkit.set_match_mode(MatchMode::Css);
+    assert_eq!(fontkit.query(&key).unwrap().key(), candidates[0].key);
+    key.weight = Some(300);
+    key.italic = Some(true);
+    let ranked = fontkit
+        .query_candidates(&key)
+        .iter()
+        .map(|c| (c.key.weight.unwrap(), c.key.italic.unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ranked,
+        vec![(400, true), (300, false), (600, false), (700, false)]
+    );
+    assert!(candidates
+        .iter()
+        .all(|c| c.family_match == FamilyMatch::Exact && !c.is_exact()));
+
+    key.family = "open sanz".to_string();
+    key.weight = Some(700);
+    key.italic = None;
+    let candidates = fontkit.query_candidates(&key);
+    assert_eq!(candidates.len(), 4);
+    assert_eq!(
+        candidates[0].family_match,
+        FamilyMatch::Similar { edits: 1 }
+    );
+    assert_eq!(candidates[0].key.weight, Some(700));
+
+    key.family = "Helvetica".to_string();
+    assert!(fontkit.query_candidates(&key).is_empty());
+    Ok(())
+}
+
+#[test]
+pub fn test_normalized_names() -> Result<(), Error> {
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(fs::read("examples/OpenSans-Italic.ttf")?)?;
+    let query = |family: &str| {
+        let key = FontKey::new_with_family(family.to_string());
+        fontkit.query(&key).is_some()
+    };
+    for family in [
+        "Open Sans",
+        "open sans",
+        "OpenSans",
+        "open-sans",
+        "ＯｐｅｎＳａｎｓ",
+    ] {
+        assert!(query(family), "{} should match", family);
+    }
+    assert!(query("opensans-italic"));
+    assert!(!query("Open Serif"));
+
+    fontkit.set_name_matching(NameMatching::Strict);
+    assert!(query("Open Sans"));
+    assert!(query("OpenSans-Italic"));
+    assert!(!query("open sans"));
+    assert!(!query("OpenSans"));
+    Ok(())
+}
+
+/// Byte range of a table in a font file
+fn table_range(buf: &[u8], tag: &[u8; 4]) -> std::ops::Range<usize> {
+    let num_tables = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+    let record = (0..num_tables)
+        .map(|i| 12 + i * 16)
+        .find(|record| &buf[*record..*record + 4] == tag)
+        .unwrap();
+    let field = |at: usize| {
+        u32::from_be_bytes(buf[record + at..record + at + 4].try_into().unwrap()) as usize
+    };
+    field(8)..field(8) + field(12)
+}
+
+/// Turn the Windows English family name of Open Sans Italic into a Simplified
+/// Chinese one
+fn open_sans_with_chinese_family(family: &str) -> Result<Vec<u8>, Error> {
+    let encoded = family
+        .encode_utf16()
+        .flat_map(|c| c.to_be_bytes())
+        .collect::<Vec<_>>();
+    open_sans_with_family_record([3, 1, 0x0804], &encoded)
+}
+
+/// Replace the English Windows family name record, with the platform,
+/// encoding and language IDs of `ids`
+fn open_sans_with_family_record(ids: [u16; 3], encoded: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut buf = fs::read("examples/OpenSans-Italic.ttf")?;
+    let table = table_range(&buf, b"name").start;
+    let read = |buf: &[u8], at: usize| u16::from_be_bytes([buf[at], buf[at + 1]]);
+    let count = read(&buf, table + 2) as usize;
+    let storage = table + read(&buf, table + 4) as usize;
+    for i in 0..count {
+        let record = table + 6 + i * 12;
+        let current = [0, 4, 6].map(|at| read(&buf, record + at));
+        if current != [3, 0x0409, 1] {
+            continue;
+        }
+        assert!(encoded.len() <= read(&buf, record + 8) as usize);
+        let offset = storage + read(&buf, record + 10) as usize;
+        for (at, id) in [0, 2, 4].into_iter().zip(ids) {
+            buf[record + at..record + at + 2].copy_from_slice(&id.to_be_bytes());
+        }
+        buf[record + 8..record + 10].copy_from_slice(&(encoded.len() as u16).to_be_bytes());
+        buf[offset..offset + encoded.len()].copy_from_slice(encoded);
+    }
+    Ok(buf)
+}
+
+#[test]
+pub fn test_localized_names() -> Result<(), Error> {
+    let family = "开源无衬线";
+    let buf = open_sans_with_chinese_family(family)?;
+
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(buf.clone())?;
+    let key = FontKey::new_with_family("Open Sans".to_string());
+    let names = fontkit.localized_family_names(&key).unwrap();
+    assert_eq!(names["zh-CN"], vec![family.to_string()]);
+    assert!(names["en"].contains(&"Open Sans".to_string()));
+    let localized = FontKey::new_with_family(family.to_string());
+    let face = fontkit.query_localized(&localized, "zh").unwrap();
+    assert_eq!(face.key().family, "Open Sans");
+
+    let fontkit = FontKit::new();
+    fontkit.set_family_language(Some("zh-CN"));
+    fontkit.add_font_from_buffer(buf)?;
+    assert_eq!(fontkit.keys()[0].family, family);
+    assert!(fontkit.query(&key).is_some());
+    Ok(())
+}
+
+#[test]
+pub fn test_legacy_name_encodings() -> Result<(), Error> {
+    let key = FontKey::new_with_family("Open Sans".to_string());
+    // "开源无衬线" in GB2312, stored in 16-bit units
+    let gbk = [0xbf, 0xaa, 0xd4, 0xb4, 0xce, 0xde, 0xb3, 0xc4, 0xcf, 0xdf];
+    let buf = open_sans_with_family_record([3, 3, 0x0804], &gbk)?;
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(buf)?;
+    let names = fontkit.localized_family_names(&key).unwrap();
+    assert_eq!(names["zh-CN"], vec!["开源无衬线".to_string()]);
+
+    // Mac Roman, with an accented letter outside ASCII
+    let buf = open_sans_with_family_record([1, 0, 0], b"Caf\x8e Sans")?;
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(buf)?;
+    assert!(fontkit
+        .query(&FontKey::new_with_family("Café Sans".to_string()))
+        .is_some());
+
+    // Shift-JIS with single-byte characters padded to 16 bits
+    let sjis = [0, b'A', 0x83, 0x65, 0x83, 0x58, 0x83, 0x67];
+    let buf = open_sans_with_family_record([3, 2, 0x0411], &sjis)?;
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(buf)?;
+    let names = fontkit.localized_family_names(&key).unwrap();
+    assert_eq!(names["ja-JP"], vec!["Aテスト".to_string()]);
+    Ok(())
+}
+
+#[test]
+pub fn test_aliases() -> Result<(), Error> {
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(fs::read("examples/OpenSans-Italic.ttf")?)?;
+    // generic families aren't aliased unless asked for
+    assert_eq!(*fontkit.aliases(), AliasTable::new());
+    let sans_serif = FontKey::new_with_family("sans-serif".to_string());
+    assert!(fontkit.query(&sans_serif).is_none());
+
+    let mut aliases = AliasTable::generic();
+    aliases.add("Helvetica", None, ["Open Sans"])?;
+    assert!(matches!(
+        aliases.add("open-sans", None, ["sans-serif"]),
+        Err(Error::AliasCycle(_))
+    ));
+    aliases.add("Headline", Some("zh"), ["Helvetica"])?;
+    fontkit.set_aliases(aliases);
+    assert_eq!(
+        fontkit.query(&sans_serif).unwrap().key().family,
+        "Open Sans"
+    );
+    let headline = FontKey::new_with_family("Headline".to_string());
+    assert!(fontkit.query(&headline).is_none());
+    assert!(fontkit.query_candidates(&headline).is_empty());
+    fontkit.set_locale(Some("zh-CN"));
+    assert!(fontkit.query(&headline).is_some());
+    // candidates follow the aliases like queries
+    let candidates = fontkit.query_candidates(&headline);
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(candidates[0].key, fontkit.query(&headline).unwrap().key());
+    assert_eq!(candidates[0].family_match, FamilyMatch::Exact);
+
+    let dir = tempfile::tempdir()?;
+    let index = dir.path().join("index.json");
+    fontkit.save_index(&index)?;
+    let restored = FontKit::new();
+    restored.load_index(&index)?;
+    assert_eq!(restored.aliases(), fontkit.aliases());
+    Ok(())
+}
+
+#[test]
+pub fn test_coverage_fallback() -> Result<(), Error> {
+    // a bold Open Sans without any Unicode cmap subtable covers nothing
+    let mut blank = open_sans_renamed(&open_sans_with_style(700, false)?, 0);
+    let cmap = table_range(&blank, b"cmap").start;
+    blank[cmap + 4..cmap + 6].copy_from_slice(&1_u16.to_be_bytes());
+
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(blank)?;
+    fontkit.add_font_from_buffer(open_sans_with_style(400, true)?)?;
+    fontkit.add_font_from_buffer(open_sans_with_style(700, false)?)?;
+    let key = FontKey::new_with_family("Fnt 00000".to_string());
+    let fallback = fontkit.resolve_fallback(&key, "Hi 开源!");
+    assert_eq!(fallback.runs.len(), 2);
+    assert_eq!(fallback.runs[0].range, 0..3);
+    assert_eq!(fallback.runs[1].range, 5..6);
+    assert!(fallback
+        .runs
+        .iter()
+        .all(|run| run.key.family == "Open Sans" && run.key.weight == Some(700)));
+    assert_eq!(fallback.uncovered, vec!['开', '源']);
+
+    // measure only falls back on coverage when asked to
+    assert!(fontkit.measure(&key, "Hi!").unwrap().has_missing());
+    fontkit.set_coverage_fallback(true);
+    let metrics = fontkit.measure(&key, "Hi!").unwrap();
+    assert!(!metrics.has_missing());
+    assert!(fontkit.measure(&key, "开").unwrap().has_missing());
+
+    // removed fonts leave the coverage index
+    let bold = fontkit
+        .keys()
+        .into_iter()
+        .find(|k| k.family == "Open Sans" && k.weight == Some(700))
+        .unwrap();
+    fontkit.remove(bold);
+    let fallback = fontkit.resolve_fallback(&key, "Hi!");
+    assert_eq!(fallback.runs.len(), 1);
+    assert_eq!(fallback.runs[0].key.italic, Some(true));
+    Ok(())
+}
+
+/// Pack fonts into a TrueType collection, without sharing tables
+fn collection_of(fonts: &[Vec<u8>]) -> Vec<u8> {
+    let read = |buf: &[u8], at: usize| u32::from_be_bytes(buf[at..at + 4].try_into().unwrap());
+    let mut ttc = b"ttcf\x00\x01\x00\x00".to_vec();
+    ttc.extend_from_slice(&(fonts.len() as u32).to_be_bytes());
+    ttc.resize(12 + 4 * fonts.len(), 0);
+    for (i, font) in fonts.iter().enumerate() {
+        let start = ttc.len();
+        ttc[12 + i * 4..16 + i * 4].copy_from_slice(&(start as u32).to_be_bytes());
+        let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+        ttc.extend_from_slice(&font[..12 + 16 * num_tables]);
+        for t in 0..num_tables {
+            let record = 12 + 16 * t;
+            let (offset, length) = (read(font, record + 8), read(font, record + 12));
+            let moved = ttc.len() as u32;
+            ttc[start + record + 8..start + record + 12].copy_from_slice(&moved.to_be_bytes());
+            ttc.extend_from_slice(&font[offset as usize..(offset + length) as usize]);
+            ttc.resize(ttc.len().next_multiple_of(4), 0);
+        }
+    }
+    ttc
+}
+
+/// Wrap a TrueType collection into WOFF2, storing every table untransformed
+fn woff2_of_collection(ttc: &[u8]) -> Vec<u8> {
+    let read = |at: usize| u32::from_be_bytes(ttc[at..at + 4].try_into().unwrap());
+    let num_fonts = read(8) as usize;
+    let mut records: Vec<(u32, u32, u32)> = vec![];
+    let mut fonts = vec![];
+    for i in 0..num_fonts {
+        let start = read(12 + i * 4) as usize;
+        let num_tables = u16::from_be_bytes([ttc[start + 4], ttc[start + 5]]) as usize;
+        let mut indices = vec![];
+        for t in 0..num_tables {
+            let record = start + 12 + 16 * t;
+            let table = (read(record), read(record + 8), read(record + 12));
+            let index = records.iter().position(|t| *t == table).unwrap_or_else(|| {
+                records.push(table);
+                records.len() - 1
+            });
+            indices.push(index as u8);
+        }
+        fonts.push((read(start), indices));
+    }
+    let tables = records
+        .iter()
+        .map(|(tag, offset, length)| {
+            let tag = tag.to_be_bytes();
+            let null_transform = if &tag == b"glyf" || &tag == b"loca" {
+                3
+            } else {
+                0
+            };
+            let data = ttc[*offset as usize..(*offset + *length) as usize].to_vec();
+            (tag, null_transform, *length, data)
+        })
+        .collect::<Vec<_>>();
+    woff2_collection(&tables, &fonts, ttc.len())
+}
+
+/// A table of a WOFF2 file: tag, transform version, original length and
+/// stored data
+type Woff2Table = ([u8; 4], u8, u32, Vec<u8>);
+
+/// Write a WOFF2 collection of `tables`, shared by `fonts`, which list their
+/// flavor and the indices of their tables
+fn woff2_collection(tables: &[Woff2Table], fonts: &[(u32, Vec<u8>)], sfnt_size: usize) -> Vec<u8> {
+    let base128 = |mut value: u32| {
+        let mut bytes = vec![(value & 0x7F) as u8];
+        value >>= 7;
+        while value > 0 {
+            bytes.insert(0, (value & 0x7F) as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes
+    };
+    let mut directory = vec![];
+    let mut data = vec![];
+    for (tag, version, orig_length, stored) in tables.iter() {
+        directory.push(63 | version << 6);
+        directory.extend_from_slice(tag);
+        directory.extend_from_slice(&base128(*orig_length));
+        let null_transform = if tag == b"glyf" || tag == b"loca" {
+            3
+        } else {
+            0
+        };
+        if *version != null_transform {
+            directory.extend_from_slice(&base128(stored.len() as u32));
+        }
+        data.extend_from_slice(stored);
+    }
+    directory.extend_from_slice(&0x0001_0000_u32.to_be_bytes());
+    directory.push(fonts.len() as u8);
+    for (flavor, indices) in fonts {
+        // 255UInt16, with the word code for long lists
+        match u16::try_from(indices.len()).unwrap() {
+            count @ 0..=252 => directory.push(count as u8),
+            count => {
+                directory.push(253);
+                directory.extend_from_slice(&count.to_be_bytes());
+            }
+        }
+        directory.extend_from_slice(&flavor.to_be_bytes());
+        directory.extend_from_slice(indices);
+    }
+    let mut compressed = vec![];
+    // a low quality keeps tests packing many files fast
+    let params = brotli::enc::BrotliEncoderParams {
+        quality: 4,
+        ..Default::default()
+    };
+    brotli::BrotliCompress(&mut data.as_slice(), &mut compressed, &params).unwrap();
+
+    let mut woff2 = b"wOF2ttcf".to_vec();
+    let length = 48 + directory.len() + compressed.len();
+    woff2.extend_from_slice(&(length as u32).to_be_bytes());
+    woff2.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+    woff2.extend_from_slice(&[0, 0]);
+    woff2.extend_from_slice(&(sfnt_size as u32).to_be_bytes());
+    woff2.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+    woff2.extend_from_slice(&[0, 1, 0, 0]);
+    woff2.resize(48, 0);
+    woff2.extend_from_slice(&directory);
+    woff2.extend_from_slice(&compressed);
+    woff2
+}
+
+/// Read back the tables of a WOFF2 font as they are stored
+#[cfg(feature = "encode")]
+fn woff2_tables(woff2: &[u8]) -> Vec<Woff2Table> {
+    // the start of the "Known Table Tags" list, enough for Open Sans
+    const KNOWN_TAGS: [&[u8; 4]; 29] = [
+        b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+        b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+        b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB",
+    ];
+    let mut pos = 48;
+    let mut next = || {
+        pos += 1;
+        woff2[pos - 1]
+    };
+    let base128 = |next: &mut dyn FnMut() -> u8| {
+        let mut value = 0_u32;
+        loop {
+            let byte = next();
+            value = value << 7 | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+        }
+    };
+    let num_tables = u16::from_be_bytes([woff2[12], woff2[13]]);
+    let mut entries = vec![];
+    for _ in 0..num_tables {
+        let flags = next();
+        let tag = match flags & 63 {
+            63 => [next(), next(), next(), next()],
+            index => *KNOWN_TAGS[index as usize],
+        };
+        let version = flags >> 6;
+        let orig_length = base128(&mut next);
+        let null_transform = if &tag == b"glyf" || &tag == b"loca" {
+            3
+        } else {
+            0
+        };
+        let length = if version != null_transform {
+            base128(&mut next)
+        } else {
+            orig_length
+        };
+        entries.push((tag, version, orig_length, length as usize));
+    }
+    let compressed_size = u32::from_be_bytes(woff2[20..24].try_into().unwrap()) as usize;
+    let mut data = vec![];
+    brotli::Decompressor::new(&woff2[pos..pos + compressed_size], 4096)
+        .read_to_end(&mut data)
+        .unwrap();
+    let mut offset = 0;
+    entries
+        .into_iter()
+        .map(|(tag, version, orig_length, length)| {
+            offset += length;
+            (
+                tag,
+                version,
+                orig_length,
+                data[offset - length..offset].to_vec(),
+            )
+        })
+        .collect()
+}
+
+/// Pack fonts into a WOFF2 collection with transformed glyf, loca and hmtx
+/// tables. glyf and loca are transformed by the WOFF2 encoder, hmtx drops the
+/// side bearings equal to the xMin of their glyph
+#[cfg(feature = "encode")]
+fn transformed_woff2_collection(fonts: &[Vec<u8>]) -> Result<Vec<u8>, Error> {
+    let mut tables: Vec<Woff2Table> = vec![];
+    let mut members = vec![];
+    for font in fonts {
+        let fontkit = FontKit::new();
+        fontkit.add_font_from_buffer(font.clone())?;
+        let face = fontkit.query(&fontkit.keys()[0]).unwrap();
+        let woff2 = face.encode(FontFormat::Woff2, &WoffBlocks::default())?;
+        let mut font_tables = woff2_tables(&woff2);
+
+        let face = ttf_parser::Face::parse(font, 0).unwrap();
+        let num_h_metrics = face.tables().hhea.number_of_metrics;
+        let mut hmtx = vec![0];
+        let (mut proportional, mut monospaced) = (vec![], vec![]);
+        for glyph in 0..face.number_of_glyphs() {
+            let glyph = ttf_parser::GlyphId(glyph);
+            let lsb = face.glyph_hor_side_bearing(glyph).unwrap();
+            let x_min = face.glyph_bounding_box(glyph).map_or(0, |bbox| bbox.x_min);
+            if glyph.0 < num_h_metrics {
+                hmtx.extend_from_slice(&face.glyph_hor_advance(glyph).unwrap().to_be_bytes());
+                proportional.push((lsb, x_min));
+            } else {
+                monospaced.push((lsb, x_min));
+            }
+        }
+        for (bit, lsbs) in [(1, proportional), (2, monospaced)] {
+            if lsbs.iter().all(|(lsb, x_min)| lsb == x_min) {
+                hmtx[0] |= bit;
+            } else {
+                lsbs.iter()
+                    .for_each(|(lsb, _)| hmtx.extend_from_slice(&lsb.to_be_bytes()));
+            }
+        }
+        assert_ne!(hmtx[0], 0, "the hmtx transform needs side bearings to drop");
+        let entry = font_tables.iter_mut().find(|t| &t.0 == b"hmtx").unwrap();
+        *entry = (*b"hmtx", 1, entry.2, hmtx);
+
+        let indices = font_tables
+            .into_iter()
+            .map(|table| {
+                let index = tables.iter().position(|t| *t == table).unwrap_or_else(|| {
+                    tables.push(table);
+                    tables.len() - 1
+                });
+                index as u8
+            })
+            .collect();
+        members.push((0x0001_0000, indices));
+    }
+    let sfnt_size = fonts.iter().map(Vec::len).sum();
+    Ok(woff2_collection(&tables, &members, sfnt_size))
+}
+
+#[test]
+pub fn test_font_collections() -> Result<(), Error> {
+    let ttc = collection_of(&[
+        open_sans_with_style(400, true)?,
+        open_sans_with_style(700, false)?,
+    ]);
+    for buf in [ttc.clone(), woff2_of_collection(&ttc)] {
+        let fontkit = FontKit::new();
+        fontkit.add_font_from_buffer(buf)?;
+        assert_eq!(fontkit.len(), 1);
+        assert_eq!(fontkit.keys().len(), 2);
+
+        let mut key = FontKey::new_with_family("Open Sans".to_string());
+        key.weight = Some(700);
+        let face = fontkit.query(&key).unwrap();
+        assert_eq!(face.collection_index(), 1);
+        assert!(face.has_glyph('a'));
+
+        key.weight = None;
+        let face = fontkit.query_collection_face(&key, 0).unwrap();
+        assert_eq!(face.key().weight, Some(400));
+        assert_eq!(face.key().italic, Some(true));
+        assert!(fontkit.query_collection_face(&key, 2).is_none());
+    }
+
+    // members with transformed glyf, loca and hmtx tables decode to the
+    // source fonts
+    #[cfg(feature = "encode")]
+    {
+        let fonts = [
+            open_sans_with_style(400, true)?,
+            open_sans_with_style(700, false)?,
+        ];
+        let fontkit = FontKit::new();
+        fontkit.add_font_from_buffer(transformed_woff2_collection(&fonts)?)?;
+        assert_eq!(fontkit.keys().len(), 2);
+        let key = FontKey::new_with_family("Open Sans".to_string());
+        for (index, font) in fonts.iter().enumerate() {
+            let face = fontkit.query_collection_face(&key, index as u32).unwrap();
+            let decoded = face.encode(FontFormat::Sfnt, &WoffBlocks::default())?;
+            assert_same_font(font, &decoded);
+        }
+    }
+    Ok(())
+}
+
+/// Add a table to a font, moving the data of every table after the grown
+/// table directory
+fn with_table(font: &[u8], tag: &[u8; 4], table: &[u8]) -> Vec<u8> {
+    let read = |at: usize| u32::from_be_bytes(font[at..at + 4].try_into().unwrap()) as usize;
+    let num_tables = u16::from_be_bytes([font[4], font[5]]) as usize;
+    let mut tables = (0..num_tables)
+        .map(|t| {
+            let record = 12 + 16 * t;
+            let tag: [u8; 4] = font[record..record + 4].try_into().unwrap();
+            let (offset, length) = (read(record + 8), read(record + 12));
+            (tag, &font[offset..offset + length])
+        })
+        .collect::<Vec<_>>();
+    tables.push((*tag, table));
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let mut buf = font[..12].to_vec();
+    buf[4..6].copy_from_slice(&(tables.len() as u16).to_be_bytes());
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, data) in tables.iter() {
+        buf.extend_from_slice(tag);
+        buf.extend_from_slice(&[0; 4]);
+        buf.extend_from_slice(&(offset as u32).to_be_bytes());
+        buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in tables {
+        buf.extend_from_slice(data);
+        buf.resize(buf.len().next_multiple_of(4), 0);
+    }
+    buf
+}
+
+/// Make Open Sans Italic a variable font with `wght` and `wdth` axes and the
+/// named instances at `instances`, without any glyph variations
+fn open_sans_variable(instances: &[(f32, f32)]) -> Result<Vec<u8>, Error> {
+    let font = fs::read("examples/OpenSans-Italic.ttf")?;
+    let fixed = |v: f32| ((v * 65536.0) as i32).to_be_bytes();
+    let mut fvar = vec![0, 1, 0, 0, 0, 16, 0, 2, 0, 2, 0, 20];
+    fvar.extend_from_slice(&(instances.len() as u16).to_be_bytes());
+    fvar.extend_from_slice(&14_u16.to_be_bytes());
+    for (tag, min, default, max) in [
+        (b"wght", 300.0, 400.0, 800.0),
+        (b"wdth", 75.0, 100.0, 100.0),
+    ] {
+        fvar.extend_from_slice(tag);
+        for value in [min, default, max] {
+            fvar.extend_from_slice(&fixed(value));
+        }
+        fvar.extend_from_slice(&[0, 0, 1, 0]);
+    }
+    for (weight, width) in instances {
+        fvar.extend_from_slice(&[0, 2, 0, 0]);
+        fvar.extend_from_slice(&fixed(*weight));
+        fvar.extend_from_slice(&fixed(*width));
+        fvar.extend_from_slice(&[0, 6]);
+    }
+    Ok(with_table(&font, b"fvar", &fvar))
+}
+
+#[test]
+pub fn test_variation_coordinates() -> Result<(), Error> {
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(open_sans_variable(&[
+        (400.0, 100.0),
+        (700.0, 100.0),
+        (400.0, 75.0),
+    ])?)?;
+    let mut stretches = fontkit
+        .keys()
+        .iter()
+        .map(|k| k.stretch.unwrap())
+        .collect::<Vec<_>>();
+    stretches.sort();
+    assert_eq!(stretches, vec![3, 5, 5]);
+
+    let query = |weight: u16, stretch: u16, variations: Vec<(&str, f32)>| {
+        let mut key = FontKey::new_with_family("Open Sans".to_string());
+        key.weight = Some(weight);
+        key.italic = Some(true);
+        key.stretch = Some(stretch);
+        key.variations = variations
+            .into_iter()
+            .map(|(tag, value)| (tag.to_string(), value))
+            .collect();
+        fontkit.query(&key).map(|face| face.key())
+    };
+    for match_mode in [MatchMode::Filter, MatchMode::Css] {
+        fontkit.set_match_mode(match_mode);
+        let key = query(350, 5, vec![]).unwrap();
+        assert_eq!(key.weight, Some(350));
+        assert_eq!(
+            key.variations,
+            vec![("wght".to_string(), 350.0), ("wdth".to_string(), 100.0)]
+        );
+
+        let key = query(900, 4, vec![]).unwrap();
+        assert_eq!((key.weight, key.stretch), (Some(800), Some(4)));
+        assert_eq!(key.variations[1], ("wdth".to_string(), 87.5));
+
+        let key = query(400, 5, vec![("wdth", 87.5), ("wght", 612.5)]).unwrap();
+        assert_eq!((key.weight, key.stretch), (Some(613), Some(4)));
+    }
+
+    let mut key = FontKey::new_with_family("Open Sans".to_string());
+    key.italic = Some(true);
+    key.weight = Some(700);
+    key.variations = vec![("wght".to_string(), 700.004)];
+    assert!(fontkit.query_candidates(&key)[0].is_exact());
+    key.weight = Some(450);
+    key.variations = vec![];
+    assert!(fontkit
+        .query_candidates(&key)
+        .iter()
+        .all(|c| c.key.weight == Some(450)));
+    Ok(())
+}
+
+/// An item variation store with one item per delta, each applying its delta
+/// in full at the maximum of the `wght` axis of [`open_sans_variable`]
+fn item_variation_store(deltas: &[i16]) -> Vec<u8> {
+    let mut store = vec![0, 1, 0, 0, 0, 12, 0, 1, 0, 0, 0, 28];
+    store.extend_from_slice(&[0, 2, 0, 1, 0, 0, 0x40, 0, 0x40, 0, 0, 0, 0, 0, 0, 0]);
+    store.extend_from_slice(&(deltas.len() as u16).to_be_bytes());
+    store.extend_from_slice(&[0, 1, 0, 1, 0, 0]);
+    for delta in deltas {
+        store.extend_from_slice(&delta.to_be_bytes());
+    }
+    store
+}
+
+/// Add HVAR and MVAR tables to a font from [`open_sans_variable`], growing
+/// every advance by 120 and the ascender, descender and line gap by 75, -30
+/// and 10 at the maximum weight
+fn with_metric_variations(font: &[u8]) -> Vec<u8> {
+    // every glyph maps to the single advance delta
+    let mut hvar = vec![0, 1, 0, 0, 0, 0, 0, 25, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0];
+    hvar.extend_from_slice(&[0, 0, 0, 1, 0]);
+    hvar.extend_from_slice(&item_variation_store(&[120]));
+    let mut mvar = vec![0, 1, 0, 0, 0, 0, 0, 8, 0, 3, 0, 36];
+    for (index, tag) in [b"hasc", b"hdsc", b"hlgp"].into_iter().enumerate() {
+        mvar.extend_from_slice(tag);
+        mvar.extend_from_slice(&[0, 0, 0, index as u8]);
+    }
+    mvar.extend_from_slice(&item_variation_store(&[75, -30, 10]));
+    with_table(&with_table(font, b"HVAR", &hvar), b"MVAR", &mvar)
+}
+
+#[test]
+pub fn test_variable_metrics() -> Result<(), Error> {
+    let font = with_metric_variations(&open_sans_variable(&[(400.0, 100.0), (700.0, 100.0)])?);
+
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(font)?;
+    let face = |weight: u16| {
+        let mut key = FontKey::new_with_family("Open Sans".to_string());
+        key.weight = Some(weight);
+        key.italic = Some(true);
+        fontkit.query(&key).unwrap()
+    };
+    let regular = face(400);
+    let units = regular.units_per_em() as f32;
+    let width = |weight: u16| face(weight).measure("aaaa").unwrap().width(units, 0.0);
+    let height = |weight: u16| face(weight).measure("a").unwrap().height(units, None);
+
+    // at wght 500 a quarter of each delta applies, rounded
+    let medium = face(500);
+    assert_eq!(medium.ascender(), regular.ascender() + 19);
+    assert_eq!(medium.descender(), regular.descender() - 8);
+    assert_eq!(medium.line_gap(), regular.line_gap() + 3);
+    assert_eq!(height(500), height(400) + 19.0 + 8.0 + 3.0);
+    assert_eq!(width(500), width(400) + 4.0 * 30.0);
+
+    let black = face(800);
+    assert_eq!(black.ascender(), regular.ascender() + 75);
+    assert_eq!(width(800), width(400) + 4.0 * 120.0);
+    assert_eq!(face(900).ascender(), black.ascender());
+    Ok(())
+}
+
+/// Range of the data of a glyph in a font
+fn glyph_range(font: &[u8], glyph: usize) -> std::ops::Range<usize> {
+    let read16 = |at: usize| u16::from_be_bytes([font[at], font[at + 1]]) as usize;
+    let (head, loca) = (table_range(font, b"head"), table_range(font, b"loca"));
+    let offset = |glyph: usize| {
+        if read16(head.start + 50) == 0 {
+            2 * read16(loca.start + 2 * glyph)
+        } else {
+            u32::from_be_bytes(font[loca.start + 4 * glyph..][..4].try_into().unwrap()) as usize
+        }
+    };
+    let glyf = table_range(font, b"glyf").start;
+    glyf + offset(glyph)..glyf + offset(glyph + 1)
+}
+
+/// Number of points of a simple glyph
+fn glyph_points(font: &[u8], glyph: usize) -> usize {
+    let read16 = |at: usize| u16::from_be_bytes([font[at], font[at + 1]]) as usize;
+    let start = glyph_range(font, glyph).start;
+    let contours = read16(start);
+    read16(start + 10 + 2 * (contours - 1)) + 1
+}
+
+/// For every composite glyph, which of its components have the
+/// WE_HAVE_INSTRUCTIONS flag
+fn composite_instruction_flags(font: &[u8]) -> Vec<Vec<bool>> {
+    let read16 = |at: usize| u16::from_be_bytes([font[at], font[at + 1]]);
+    let maxp = table_range(font, b"maxp").start;
+    let mut composites = vec![];
+    for glyph in 0..read16(maxp + 4) as usize {
+        let range = glyph_range(font, glyph);
+        if range.is_empty() || read16(range.start) as i16 >= 0 {
+            continue;
+        }
+        let mut at = range.start + 10;
+        let mut components = vec![];
+        loop {
+            let flags = read16(at);
+            let args = if flags & 0x0001 != 0 { 4 } else { 2 };
+            let transform = match flags {
+                _ if flags & 0x0008 != 0 => 2,
+                _ if flags & 0x0040 != 0 => 4,
+                _ if flags & 0x0080 != 0 => 8,
+                _ => 0,
+            };
+            at += 4 + args + transform;
+            components.push(flags & 0x0100 != 0);
+            if flags & 0x0020 == 0 {
+                break;
+            }
+        }
+        composites.push(components);
+    }
+    composites
+}
+
+/// A gvar table for a font from [`open_sans_variable`], moving the outline of
+/// `glyph` by `dx` and its origin, the left phantom point, by `origin_dx` at
+/// the maximum weight
+fn gvar_moving_glyph(font: &[u8], glyph: u16, dx: i8, origin_dx: i8) -> Vec<u8> {
+    let maxp = table_range(font, b"maxp");
+    let num_glyphs = u16::from_be_bytes([font[maxp.start + 4], font[maxp.start + 5]]);
+    let points = glyph_points(font, glyph as usize);
+    // every point, then runs of x deltas and of zero y deltas
+    let mut data = vec![0, 1, 0, 12, 0, 0, 0xA0, 0, 0x40, 0, 0, 0, 0];
+    let mut deltas = vec![dx; points];
+    deltas.extend([origin_dx, 0, 0, 0]);
+    for run in deltas.chunks(64) {
+        data.push(run.len() as u8 - 1);
+        data.extend(run.iter().map(|d| *d as u8));
+    }
+    for run in deltas.chunks(64) {
+        data.push(0x80 | (run.len() as u8 - 1));
+    }
+    let size = (data.len() - 12) as u16;
+    data[4..6].copy_from_slice(&size.to_be_bytes());
+    data.resize(data.len().next_multiple_of(2), 0);
+
+    let mut gvar = vec![0, 1, 0, 0, 0, 2, 0, 0];
+    let data_offset = 20 + 2 * (num_glyphs as u32 + 1);
+    gvar.extend_from_slice(&data_offset.to_be_bytes());
+    gvar.extend_from_slice(&num_glyphs.to_be_bytes());
+    gvar.extend_from_slice(&[0, 0]);
+    gvar.extend_from_slice(&data_offset.to_be_bytes());
+    for index in 0..=num_glyphs {
+        let offset = if index > glyph { data.len() / 2 } else { 0 };
+        gvar.extend_from_slice(&(offset as u16).to_be_bytes());
+    }
+    gvar.extend_from_slice(&data);
+    gvar
+}
+
+#[test]
+pub fn test_variable_side_bearings() -> Result<(), Error> {
+    // `a` (glyph 68) gets its origin 15 units left of its xMin, and moves by
+    // 40 units at the maximum weight while its origin moves by 10
+    let mut font = open_sans_variable(&[(400.0, 100.0), (800.0, 100.0)])?;
+    let hmtx = table_range(&font, b"hmtx").start + 4 * 68;
+    let advance = u16::from_be_bytes([font[hmtx], font[hmtx + 1]]);
+    let lsb = i16::from_be_bytes([font[hmtx + 2], font[hmtx + 3]]) + 15;
+    font[hmtx + 2..hmtx + 4].copy_from_slice(&lsb.to_be_bytes());
+    #[cfg(feature = "ras")]
+    let x_min = ttf_parser::Face::parse(&font, 0)
+        .unwrap()
+        .glyph_bounding_box(ttf_parser::GlyphId(68))
+        .unwrap()
+        .x_min;
+    let gvar = gvar_moving_glyph(&font, 68, 40, 10);
+    let font = with_table(&font, b"gvar", &gvar);
+
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(font)?;
+    let face = |weight: u16| {
+        let mut key = FontKey::new_with_family("Open Sans".to_string());
+        key.weight = Some(weight);
+        key.italic = Some(true);
+        fontkit.query(&key).unwrap()
+    };
+    // the side bearing grows by the outline shift minus the origin shift, and
+    // the advance, from the phantom points, shrinks by the origin shift
+    for (weight, shift, origin_shift) in [(400, 0, 0), (600, 20, 5), (800, 40, 10)] {
+        let metrics = face(weight).measure_char('a').unwrap();
+        assert_eq!(metrics.lsb, lsb + shift - origin_shift);
+        assert_eq!(metrics.advanced_x, advance - origin_shift as u16);
+        #[cfg(feature = "ras")]
+        {
+            let face = face(weight);
+            let bitmap = face.bitmap('a', face.units_per_em() as f32, 0.0).unwrap();
+            assert_eq!(bitmap.x_min(), (x_min + shift) as f32);
+        }
+    }
+
+    // static instances agree with the variable face
+    let variable = face(600);
+    let instances = FontKit::new();
+    instances.add_font_from_buffer(variable.instance_buffer()?)?;
+    let instance = instances.query(&instances.keys()[0]).unwrap();
+    let (expected, actual) = (
+        variable.measure_char('a').unwrap(),
+        instance.measure_char('a').unwrap(),
+    );
+    assert_eq!(
+        (actual.lsb, actual.advanced_x),
+        (expected.lsb, expected.advanced_x)
+    );
+    Ok(())
+}
+
+/// Whether a font file has a table
+fn has_table(buf: &[u8], tag: &[u8; 4]) -> bool {
+    let num_tables = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+    (0..num_tables).any(|i| &buf[12 + i * 16..16 + i * 16] == tag)
+}
+
+#[test]
+pub fn test_static_instances() -> Result<(), Error> {
+    let font = with_metric_variations(&open_sans_variable(&[(400.0, 100.0)])?);
+    let maxp = table_range(&font, b"maxp");
+    let num_glyphs = u16::from_be_bytes([font[maxp.start + 4], font[maxp.start + 5]]);
+    // at the maximum weight, move the first contour of `a` (glyph 68) by
+    // (40, -20), with the first point's delta inferred for the whole contour
+    let mut gvar = vec![0, 1, 0, 0, 0, 2, 0, 0];
+    let data_offset = 20 + 2 * (num_glyphs as u32 + 1);
+    gvar.extend_from_slice(&data_offset.to_be_bytes());
+    gvar.extend_from_slice(&num_glyphs.to_be_bytes());
+    gvar.extend_from_slice(&[0, 0]);
+    gvar.extend_from_slice(&data_offset.to_be_bytes());
+    for glyph in 0..=num_glyphs {
+        gvar.extend_from_slice(&[0, if glyph > 68 { 10 } else { 0 }]);
+    }
+    gvar.extend_from_slice(&[0, 1, 0, 12, 0, 7, 0xA0, 0, 0x40, 0, 0, 0]);
+    gvar.extend_from_slice(&[1, 0, 0, 0, 40, 0, 0xEC, 0]);
+    // and every control value by 10
+    let mut cvar = vec![0, 1, 0, 0, 0, 1, 0, 16, 0, 83, 0xA0, 0, 0x40, 0, 0, 0, 0];
+    for run in [64, 16] {
+        cvar.push(run - 1);
+        cvar.extend(std::iter::repeat_n(10, run as usize));
+    }
+    let font = with_table(&with_table(&font, b"gvar", &gvar), b"cvar", &cvar);
+
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(font.clone())?;
+    let mut key = FontKey::new_with_family("Open Sans".to_string());
+    key.weight = Some(600);
+    key.italic = Some(true);
+    let variable = fontkit.query(&key).unwrap();
+    let instance = variable.instance_buffer()?;
+    for tag in [b"fvar", b"gvar", b"cvar", b"HVAR", b"MVAR"] {
+        assert!(!has_table(&instance, tag));
+    }
+    let cvt = |buf: &[u8]| {
+        buf[table_range(buf, b"cvt ")]
+            .chunks(2)
+            .map(|v| i16::from_be_bytes([v[0], v[1]]))
+            .collect::<Vec<_>>()
+    };
+    let varied_cvt = cvt(&font).iter().map(|v| v + 5).collect::<Vec<_>>();
+    assert_eq!(cvt(&instance), varied_cvt);
+    // composite glyphs keep their instructions flagged on the last component
+    let composites = composite_instruction_flags(&instance);
+    assert!(composites.iter().any(|flags| flags.contains(&true)));
+    assert_eq!(composites, composite_instruction_flags(&font));
+
+    let instances = FontKit::new();
+    instances.add_font_from_buffer(instance)?;
+    let keys = instances.keys();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].weight, Some(600));
+    assert!(keys[0].variations.is_empty());
+    let static_face = instances.query(&keys[0]).unwrap();
+    assert_eq!(static_face.ascender(), variable.ascender());
+    assert_eq!(static_face.descender(), variable.descender());
+    assert_eq!(static_face.line_gap(), variable.line_gap());
+    let units = variable.units_per_em() as f32;
+    let width = |face: &fontkit::StaticFace| face.measure("aba").unwrap().width(units, 0.0);
+    assert_eq!(width(&static_face), width(&variable));
+    // outlines follow the variations, as they do in the variable face
+    #[cfg(feature = "ras")]
+    {
+        for c in ['a', 'b'] {
+            let (expected, actual) = (
+                variable.bitmap(c, 40.0, 0.0).unwrap(),
+                static_face.bitmap(c, 40.0, 0.0).unwrap(),
+            );
+            assert_eq!(actual.bitmap(), expected.bitmap());
+            assert_eq!(
+                (actual.width(), actual.x_min(), actual.y_max()),
+                (expected.width(), expected.x_min(), expected.y_max())
+            );
+        }
+        key.weight = Some(400);
+        assert_ne!(
+            fontkit
+                .query(&key)
+                .unwrap()
+                .bitmap('a', 40.0, 0.0)
+                .unwrap()
+                .bitmap(),
+            variable.bitmap('a', 40.0, 0.0).unwrap().bitmap()
+        );
+    }
+    Ok(())
+}
+
+#[test]
+pub fn test_subsetting() -> Result<(), Error> {
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(fs::read("examples/OpenSans-Italic.ttf")?)?;
+    let key = fontkit.keys().pop().unwrap();
+    let face = fontkit.query(&key).unwrap();
+    let text = "Héllo";
+    let subset = face.subset_text(text, FontFormat::Sfnt)?;
+    let maxp = table_range(&subset, b"maxp").start;
+    // .notdef, H, e, l, o and é, a composite of e and the acute accent
+    assert_eq!(u16::from_be_bytes([subset[maxp + 4], subset[maxp + 5]]), 7);
+    assert!(!has_table(&subset, b"GSUB"));
+    assert!(face.subset_glyphs(&[u16::MAX], FontFormat::Sfnt).is_err());
+
+    let mut formats = vec![FontFormat::Sfnt, FontFormat::Woff];
+    #[cfg(feature = "encode")]
+    formats.push(FontFormat::Woff2);
+    let units = face.units_per_em() as f32;
+    for format in formats {
+        let subsets = FontKit::new();
+        subsets.add_font_from_buffer(face.subset_text(text, format)?)?;
+        let subset = subsets.query(&key).unwrap();
+        assert!(text.chars().all(|c| subset.has_glyph(c)));
+        assert!(!subset.has_glyph('x'));
+        assert_eq!(
+            subset.measure(text).unwrap().width(units, 0.0),
+            face.measure(text).unwrap().width(units, 0.0)
+        );
+        #[cfg(feature = "ras")]
+        for c in text.chars() {
+            assert_eq!(
+                subset.bitmap(c, 40.0, 0.0).unwrap().bitmap(),
+                face.bitmap(c, 40.0, 0.0).unwrap().bitmap()
+            );
+        }
+    }
+
+    // kerning pairs of kept glyphs survive, renumbered
+    let kerned = "AVTo";
+    let width =
+        |face: &fontkit::StaticFace, text: &str| face.measure(text).unwrap().width(units, 0.0);
+    assert!(width(&face, "AV") < width(&face, "A") + width(&face, "V"));
+    assert!(width(&face, "To") < width(&face, "T") + width(&face, "o"));
+    let subsets = FontKit::new();
+    subsets.add_font_from_buffer(face.subset_text(kerned, FontFormat::Sfnt)?)?;
+    let subset = subsets.query(&key).unwrap();
+    for text in ["AV", "To", "oA", kerned] {
+        assert_eq!(width(&subset, text), width(&face, text));
+    }
+    Ok(())
+}
+
+#[test]
+pub fn test_cff_subsetting() -> Result<(), Error> {
+    let original = fs::read("tests/fixtures/FontAwesome.otf")?;
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(original.clone())?;
+    let key = fontkit.keys().pop().unwrap();
+    let face = fontkit.query(&key).unwrap();
+    let text = "\u{f000}\u{f09b}\u{f2b4}";
+    let subset = face.subset_text(text, FontFormat::Sfnt)?;
+    let original_face = ttf_parser::Face::parse(&original, 0).unwrap();
+    let subset_face = ttf_parser::Face::parse(&subset, 0).unwrap();
+    assert!(subset_face.tables().cff.is_some());
+    assert_eq!(subset_face.number_of_glyphs(), 4);
+    for c in text.chars() {
+        let (original_glyph, subset_glyph) = (
+            original_face.glyph_index(c).unwrap(),
+            subset_face.glyph_index(c).unwrap(),
+        );
+        let (mut original_outline, mut subset_outline) = (Outline::default(), Outline::default());
+        assert_eq!(
+            original_face.outline_glyph(original_glyph, &mut original_outline),
+            subset_face.outline_glyph(subset_glyph, &mut subset_outline)
+        );
+        assert!(!original_outline.0.is_empty());
+        assert_eq!(original_outline, subset_outline);
+        assert_eq!(
+            original_face.glyph_hor_advance(original_glyph),
+            subset_face.glyph_hor_advance(subset_glyph)
+        );
+    }
+    Ok(())
+}
+
+/// Records outlines as flat lists of commands and coordinates
+#[derive(Default, PartialEq, Debug)]
+struct Outline(Vec<f32>);
+
+impl ttf_parser::OutlineBuilder for Outline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.extend([0.0, x, y]);
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.extend([1.0, x, y]);
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.0.extend([2.0, x1, y1, x, y]);
+    }
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.0.extend([3.0, x1, y1, x2, y2, x, y]);
+    }
+    fn close(&mut self) {
+        self.0.push(4.0);
+    }
+}
+
+#[test]
+pub fn test_woff_encoding() -> Result<(), Error> {
+    let original = fs::read("examples/OpenSans-Italic.ttf")?;
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(original.clone())?;
+    let key = fontkit.keys().pop().unwrap();
+    let face = fontkit.query(&key).unwrap();
+    let metadata = r#"<?xml version="1.0" encoding="UTF-8"?><metadata version="1.0"/>"#;
+    let blocks = WoffBlocks {
+        metadata: Some(metadata.to_string()),
+        private: Some(b"private".to_vec()),
+    };
+
+    let mut formats = vec![FontFormat::Woff];
+    #[cfg(feature = "encode")]
+    formats.push(FontFormat::Woff2);
+    for format in formats {
+        let encoded = face.encode(format, &blocks)?;
+        let field =
+            |at: usize| u32::from_be_bytes(encoded[at..at + 4].try_into().unwrap()) as usize;
+        // the metadata fields start after the compressed size in WOFF2
+        let header = if format == FontFormat::Woff { 24 } else { 28 };
+        assert_eq!(field(8), encoded.len());
+        let (meta_offset, meta_length) = (field(header), field(header + 4));
+        assert_eq!(field(header + 8), metadata.len());
+        assert_eq!(meta_offset % 4, 0);
+        let mut decompressed = String::new();
+        let compressed = &encoded[meta_offset..meta_offset + meta_length];
+        if format == FontFormat::Woff {
+            flate2::read::ZlibDecoder::new(compressed).read_to_string(&mut decompressed)?;
+        } else {
+            brotli::Decompressor::new(compressed, 4096).read_to_string(&mut decompressed)?;
+        }
+        assert_eq!(decompressed, metadata);
+        let (private_offset, private_length) = (field(header + 12), field(header + 16));
+        assert_eq!(private_offset % 4, 0);
+        assert_eq!(
+            &encoded[private_offset..private_offset + private_length],
+            b"private"
+        );
+
+        // decoding gives back the tables, glyf and loca as equivalent outlines
+        let decoded_kit = FontKit::new();
+        decoded_kit.add_font_from_buffer(encoded)?;
+        let decoded = decoded_kit
+            .query(&key)
+            .unwrap()
+            .encode(FontFormat::Sfnt, &WoffBlocks::default())?;
+        assert_same_font(&original, &decoded);
+    }
+    Ok(())
+}
+
+/// Check that a decoded font has the tables of the original, glyf and loca
+/// as equivalent outlines
+fn assert_same_font(original: &[u8], decoded: &[u8]) {
+    let original_face = ttf_parser::RawFace::parse(original, 0).unwrap();
+    let decoded_face = ttf_parser::RawFace::parse(decoded, 0).unwrap();
+    assert_eq!(
+        original_face.table_records.len(),
+        decoded_face.table_records.len()
+    );
+    for record in original_face.table_records {
+        let table = decoded_face.table(record.tag).unwrap();
+        match &record.tag.to_bytes() {
+            b"glyf" | b"loca" => {}
+            // but for the checksum adjustment
+            b"head" => {
+                let original_table = original_face.table(record.tag).unwrap();
+                assert_eq!(original_table[..8], table[..8]);
+                assert_eq!(original_table[12..], table[12..]);
+            }
+            _ => assert_eq!(original_face.table(record.tag).unwrap(), table),
+        }
+    }
+    let original_face = ttf_parser::Face::parse(original, 0).unwrap();
+    let decoded_face = ttf_parser::Face::parse(decoded, 0).unwrap();
+    for glyph in 0..original_face.number_of_glyphs() {
+        let glyph = ttf_parser::GlyphId(glyph);
+        let (mut original_outline, mut decoded_outline) = (Outline::default(), Outline::default());
+        assert_eq!(
+            original_face.outline_glyph(glyph, &mut original_outline),
+            decoded_face.outline_glyph(glyph, &mut decoded_outline)
+        );
+        assert_eq!(original_outline, decoded_outline);
+    }
+}
+
+#[test]
+pub fn test_strict_validation() -> Result<(), Error> {
+    let original = fs::read("examples/OpenSans-Italic.ttf")?;
+    let fontkit = FontKit::new();
+    fontkit.set_strict_validation(true);
+    fontkit.add_font_from_buffer(original.clone())?;
+    let record = |tag: &[u8; 4]| {
+        (0..u16::from_be_bytes([original[4], original[5]]) as usize)
+            .map(|i| 12 + 16 * i)
+            .find(|record| &original[*record..*record + 4] == tag)
+            .unwrap()
+    };
+
+    // only strict validation rejects a wrong checksum
+    let mut corrupted = original.clone();
+    corrupted[table_range(&original, b"name").start + 10] ^= 0xFF;
+    assert!(matches!(
+        fontkit.add_font_from_buffer(corrupted.clone()),
+        Err(Error::TableChecksum { tag, .. }) if tag == "name"
+    ));
+    FontKit::new().add_font_from_buffer(corrupted)?;
+
+    let mut out_of_bounds = original.clone();
+    let post = record(b"post");
+    out_of_bounds[post + 12..post + 16].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert!(matches!(
+        fontkit.add_font_from_buffer(out_of_bounds),
+        Err(Error::TableOutOfBounds { tag }) if tag == "post"
+    ));
+    let mut overlapping = original.clone();
+    let name = record(b"name");
+    overlapping.copy_within(name + 8..name + 12, post + 8);
+    assert!(matches!(
+        fontkit.add_font_from_buffer(overlapping),
+        Err(Error::TablesOverlap { .. })
+    ));
+
+    // fvar records and WOFF tables past the end of their data are errors
+    // in any mode
+    let variable = open_sans_variable(&[(400.0, 100.0)])?;
+    let fvar = table_range(&variable, b"fvar").start;
+    for count in [100_u16, 0xFFFF] {
+        let mut variable = variable.clone();
+        variable[fvar + 12..fvar + 14].copy_from_slice(&count.to_be_bytes());
+        assert!(matches!(
+            FontKit::new().add_font_from_buffer(variable),
+            Err(Error::InvalidTable { tag, .. }) if tag == "fvar"
+        ));
+    }
+    let key = fontkit.keys().pop().unwrap();
+    let mut woff = fontkit
+        .query(&key)
+        .unwrap()
+        .encode(FontFormat::Woff, &WoffBlocks::default())?;
+    woff.truncate(woff.len() / 2);
+    assert!(matches!(
+        FontKit::new().add_font_from_buffer(woff),
+        Err(Error::TableOutOfBounds { .. })
+    ));
+
+    // WOFF2 directories are checked before their tables are decoded
+    let mut empty = b"wOF2".to_vec();
+    empty.extend_from_slice(&0x0001_0000_u32.to_be_bytes());
+    empty.extend_from_slice(&48_u32.to_be_bytes());
+    empty.resize(48, 0);
+    let name = [(*b"name", 0, 0, vec![])];
+    for woff2 in [
+        empty,
+        woff2_collection(&name, &[(0x0001_0000, vec![])], 0),
+        woff2_collection(&name, &[(0x0001_0000, vec![1])], 0),
+        woff2_collection(&name, &[(0x0001_0000, vec![0, 0])], 0),
+    ] {
+        assert!(matches!(
+            fontkit.add_font_from_buffer(woff2),
+            Err(Error::InvalidDirectory(_))
+        ));
+    }
+    Ok(())
+}
+
+/// Corrupt the bytes of a WOFF2 file, and the transformed glyf stream inside
+/// it, which the compression would otherwise mostly reject. Decoding has to
+/// fail with an error or succeed, never panic
+#[cfg(feature = "encode")]
+#[test]
+pub fn test_malformed_woff2() -> Result<(), Error> {
+    let fontkit = FontKit::new();
+    fontkit.add_font_from_buffer(fs::read("examples/OpenSans-Italic.ttf")?)?;
+    let face = fontkit.query(&fontkit.keys()[0]).unwrap();
+    let woff2 = face.subset_text("fontkit", FontFormat::Woff2)?;
+    let decode = |buf: Vec<u8>| {
+        let _ = FontKit::new().add_font_from_buffer(buf);
+    };
+    // xorshift, so that failures reproduce
+    let mut state = 0x2545_F491_u32;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    for len in 0..woff2.len() {
+        decode(woff2[..len].to_vec());
+    }
+    for at in 0..woff2.len() {
+        let mut corrupted = woff2.clone();
+        corrupted[at] ^= random() as u8 | 1;
+        decode(corrupted);
+    }
+
+    let tables = woff2_tables(&woff2);
+    let glyf = tables.iter().position(|t| &t.0 == b"glyf").unwrap();
+    let fonts = [(0x0001_0000, (0..tables.len() as u8).collect::<Vec<_>>())];
+    for _ in 0..500 {
+        let mut tables = tables.clone();
+        let data = &mut tables[glyf].3;
+        for _ in 0..1 + random() % 4 {
+            // half of the corruptions hit the header and stream sizes
+            let end = if random() % 2 == 0 { 36 } else { data.len() };
+            let at = random() as usize % end;
+            data[at] = random() as u8;
+        }
+        decode(woff2_collection(&tables, &fonts, 0));
+    }
+    Ok(())
+}
+
+#[test]
+pub fn test_loading_errors() -> Result<(), Error> {
+    let dir = tempfile::tempdir()?;
+    let fontkit = FontKit::new();
+    let missing = dir.path().join("missing");
+    let error = fontkit.search_fonts_from_path(&missing).unwrap_err();
+    assert!(matches!(error, Error::FileAccess { .. }));
+    assert_eq!(error.path(), Some(missing.as_path()));
+
+    // a face without a head table can't be parsed
+    let mut headless = fs::read("examples/OpenSans-Italic.ttf")?;
+    let head = table_range(&headless, b"head").start;
+    let record = (12..headless.len())
+        .step_by(16)
+        .find(|r| headless[*r + 8..*r + 12] == (head as u32).to_be_bytes())
+        .unwrap();
+    headless[record..record + 4].copy_from_slice(b"hea_");
+    let error = fontkit.add_font_from_buffer(headless.clone()).unwrap_err();
+    assert!(matches!(error, Error::FaceParsing { path: None, .. }));
+    assert_eq!(error.face_index(), Some(0));
+
+    let file = dir.path().join("headless.ttf");
+    fs::write(&file, headless)?;
+    let report = fontkit.search_fonts_from_path(dir.path())?;
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].1.path(), Some(file.as_path()));
+    assert_eq!(report.failed[0].1.face_index(), Some(0));
+    assert_eq!(fontkit.len(), 0);
+
+    // crafted WOFF2 collections are rejected before their data is inflated
+    let font = fs::read("examples/OpenSans-Italic.ttf")?;
+    let mut crowded = woff2_of_collection(&collection_of(&[font]));
+    crowded[12..14].copy_from_slice(&4096_u16.to_be_bytes());
+    assert!(fontkit.add_font_from_buffer(crowded).is_err());
+    let huge = [(*b"name", 0, u32::MAX, vec![])];
+    let huge = woff2_collection(&huge, &[(0x0001_0000, vec![0])], 0);
+    assert!(fontkit.add_font_from_buffer(huge).is_err());
+    let name = [(*b"name", 0, 0, vec![])];
+    let crowded = woff2_collection(&name, &[(0x0001_0000, vec![0; 4096])], 0);
+    assert!(fontkit.add_font_from_buffer(crowded).is_err());
+    let empty = woff2_collection(&name, &[(0x0001_0000, vec![])], 0);
+    assert!(fontkit.add_font_from_buffer(empty).is_err());
+    let mut empty = b"wOF2".to_vec();
+    empty.extend_from_slice(&0x0001_0000_u32.to_be_bytes());
+    empty.extend_from_slice(&48_u32.to_be_bytes());
+    empty.resize(48, 0);
+    assert!(fontkit.add_font_from_buffer(empty).is_err());
+
+    // a rebuilt glyf past 128 KB can't be indexed by a short loca
+    let glyphs = 3;
+    let mut glyf = vec![0, 0, 0, 0];
+    glyf.extend_from_slice(&(glyphs as u16).to_be_bytes());
+    glyf.extend_from_slice(&[0, 0]);
+    let instructions = 60000_u16;
+    let streams = [
+        [0, 1].repeat(glyphs),
+        vec![1; glyphs],
+        vec![0; glyphs],
+        [&[0, 253][..], &instructions.to_be_bytes()]
+            .concat()
+            .repeat(glyphs),
+        vec![],
+        vec![0; 4],
+        vec![0; instructions as usize * glyphs],
+    ];
+    for stream in &streams {
+        glyf.extend_from_slice(&(stream.len() as u32).to_be_bytes());
+    }
+    glyf.extend(streams.concat());
+    let oversized = [(*b"glyf", 0, 0, glyf), (*b"loca", 0, 0, vec![])];
+    let oversized = woff2_collection(&oversized, &[(0x0001_0000, vec![0, 1])], 0);
+    assert!(matches!(
+        fontkit.add_font_from_buffer(oversized),
+        Err(Error::InvalidTable { tag, .. }) if tag == "glyf"
+    ));
+    assert_eq!(fontkit.len(), 0);
+    Ok(())
+}
+
+#[test]
+pub fn test_key_collisions() -> Result<(), Error> {
+    let buffer = fs::read("examples/OpenSans-Italic.ttf")?;
+    let fontkit = FontKit::new();
+    let keys = fontkit.add_font_from_buffer(buffer.clone())?;
+    assert_eq!(keys, fontkit.keys());
+    assert_eq!(keys[0].family, "Open Sans");
+    fontkit.set_collision_policy(CollisionPolicy::Keep);
+    assert!(fontkit.add_font_from_buffer(buffer.clone())?.is_empty());
+    fontkit.set_collision_policy(CollisionPolicy::Replace);
+    assert_eq!(fontkit.add_font_from_buffer(buffer.clone())?, keys);
+    assert_eq!(fontkit.len(), 1);
+
+    // concurrent additions of the same font can't both take the key
+    for (policy, count) in [(CollisionPolicy::Keep, 1), (CollisionPolicy::KeepBoth, 4)] {
+        let fontkit = FontKit::new();
+        fontkit.set_collision_policy(policy);
+        let added = std::thread::scope(|s| {
+            let handles = (0..4)
+                .map(|_| s.spawn(|| fontkit.add_font_from_buffer(buffer.clone()).unwrap()))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap())
+                .filter(|keys| !keys.is_empty())
+                .count()
+        });
+        assert_eq!(added, count);
+        assert_eq!(fontkit.len(), count);
+    }
+
+    // the policy applies to every variant of a collection, not only its first
+    let bold = open_sans_with_style(700, false)?;
+    let collection = collection_of(&[open_sans_with_style(400, true)?, bold.clone()]);
+    for (policy, count) in [
+        (CollisionPolicy::Keep, 1),
+        (CollisionPolicy::Replace, 1),
+        (CollisionPolicy::KeepBoth, 2),
+    ] {
+        let fontkit = FontKit::new();
+        fontkit.set_collision_policy(policy);
+        let bold_keys = fontkit.add_font_from_buffer(bold.clone())?;
+        let keys = fontkit.add_font_from_buffer(collection.clone())?;
+        assert_eq!(fontkit.len(), count);
+        match policy {
+            CollisionPolicy::Keep => {
+                assert!(keys.is_empty());
+                assert_eq!(fontkit.keys(), bold_keys);
+            }
+            CollisionPolicy::Replace => assert_eq!(fontkit.keys().len(), 2),
+            CollisionPolicy::KeepBoth => assert!(keys.iter().all(|k| k.handle == Some(2))),
+        }
+    }
+
+    let dir = tempfile::tempdir()?;
+    for name in ["a.ttf", "b.ttf"] {
+        fs::copy("examples/OpenSans-Italic.ttf", dir.path().join(name))?;
+    }
+    let fontkit = FontKit::new();
+    fontkit.search_fonts_from_path(dir.path())?;
+    assert_eq!(fontkit.len(), 1);
+
+    // shadowed files aren't reported as loaded
+    let fontkit = FontKit::new();
+    fontkit.set_collision_policy(CollisionPolicy::Keep);
+    let report = fontkit.search_fonts_from_path(dir.path())?;
+    assert_eq!(report.loaded.len(), 1);
+    assert_eq!(report.shadowed.len(), 1);
+    assert_eq!(fontkit.len(), 1);
+
+    let fontkit = FontKit::new();
+    fontkit.set_collision_policy(CollisionPolicy::KeepBoth);
+    fontkit.search_fonts_from_path(dir.path())?;
+    // searching again reloads both files instead of adding them again
+    let report = fontkit.search_fonts_from_path(dir.path())?;
+    assert_eq!(report.loaded.len(), 2);
+    assert_eq!(fontkit.len(), 2);
+    let mut keys = fontkit.keys();
+    keys.sort_by_key(|k| k.handle);
+    assert_eq!(keys[0].family, "Open Sans");
+    assert_eq!(keys[1].family, "Open Sans");
+    assert_eq!((keys[0].handle, keys[1].handle), (None, Some(2)));
+    // queries without a handle find the registered font only
+    let key = FontKey::new_with_family("Open Sans".to_string());
+    assert_eq!(fontkit.query(&key).unwrap().key().handle, None);
+    for mode in [MatchMode::Filter, MatchMode::Css] {
+        fontkit.set_match_mode(mode);
+        assert_eq!(fontkit.query(&key).unwrap().key(), keys[0]);
+        assert_eq!(fontkit.query(&keys[1]).unwrap().key(), keys[1]);
+    }
+    assert!(fontkit.exact_match(&keys[1]).is_some());
+    fontkit.remove(keys[1].clone());
+    assert_eq!(fontkit.len(), 1);
+    assert!(fontkit.query(&keys[1]).is_none());
+    assert!(fontkit.query(&key).is_some());
+    Ok(())
+}
+
+/// Rename Open Sans Italic to `Fnt {index:05}`, keeping the length of every
+/// name record
+fn open_sans_renamed(original: &[u8], index: usize) -> Vec<u8> {
+    let mut buf = original.to_vec();
+    let name_table = table_range(&buf, b"name");
+    let names = &mut buf[name_table];
+    let replacements = [
+        ("Open Sans", format!("Fnt {:05}", index)),
+        ("OpenSans", format!("Fnt{:05}", index)),
+    ];
+    for (from, to) in replacements {
+        let utf16 = |s: &str| s.encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+        for (from, to) in [
+            (from.as_bytes().to_vec(), to.as_bytes().to_vec()),
+            (utf16(from), utf16(&to)),
+        ] {
+            let mut i = 0;
+            while i + from.len() <= names.len() {
+                if names[i..i + from.len()] == from[..] {
+                    names[i..i + from.len()].copy_from_slice(&to);
+                    i += from.len();
+                } else {
+                    i += 1;
+                }
+            }
+        }
+    }
+    buf
+}
+
 #[test]
 pub fn test_text_wrap() -> Result<(), Error> {
     let fontkit = FontKit::new();